@@ -0,0 +1,20 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use squash::OwnedSlice;
+
+fn clone(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clone");
+    for len in [16, 1024, 1024 * 1024] {
+        let src = (0..len as u64).collect::<Vec<_>>();
+        let s: OwnedSlice<u64> = OwnedSlice::new(&src).unwrap();
+        group.bench_with_input(BenchmarkId::new("clone", len), &len, |bench, _| {
+            bench.iter(|| black_box(&s).clone())
+        });
+        group.bench_with_input(BenchmarkId::new("try_copy_clone", len), &len, |bench, _| {
+            bench.iter(|| black_box(&s).try_copy_clone().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, clone);
+criterion_main!(benches);
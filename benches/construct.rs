@@ -0,0 +1,17 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use squash::OwnedSlice;
+
+fn construct(c: &mut Criterion) {
+    let mut group = c.benchmark_group("construct");
+    let data = (0..10_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    group.bench_function("new-u8-10k", |bench| {
+        bench.iter(|| OwnedSlice::<u8>::new(black_box(&data)).unwrap())
+    });
+    group.bench_function("copy_from_slice-u8-10k", |bench| {
+        bench.iter(|| OwnedSlice::<u8>::copy_from_slice(black_box(&data)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, construct);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use squash::{ArcHeader, OwnedSlice};
+
+fn deref(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deref");
+    let slices = (0..1000u32)
+        .map(|i| OwnedSlice::<u8>::new(&i.to_le_bytes()[..(i % 4) as usize + 1]).unwrap())
+        .collect::<Vec<_>>();
+    let arc_slices = slices
+        .iter()
+        .map(|s| OwnedSlice::<u64, ArcHeader>::from_fn(s.len(), |i| i as u64).unwrap())
+        .collect::<Vec<_>>();
+    group.bench_function("len", |bench| {
+        bench.iter(|| black_box(&slices).iter().map(|s| s.len()).sum::<usize>())
+    });
+    group.bench_function("index", |bench| {
+        bench.iter(|| {
+            black_box(&slices)
+                .iter()
+                .map(|s| usize::from(s[0]))
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("index-arc-u64", |bench| {
+        bench.iter(|| black_box(&arc_slices).iter().map(|s| s[0]).sum::<u64>())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, deref);
+criterion_main!(benches);
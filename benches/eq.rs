@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use squash::Str;
+
+fn eq(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eq");
+    for len in [8, 32, 256] {
+        let src = "x".repeat(len);
+        // Same length, differs at the very end
+        let miss = "x".repeat(len - 1) + "y";
+
+        let a = src.clone();
+        let b = src.clone();
+        let m = miss.clone();
+        group.bench_with_input(BenchmarkId::new("String/hit", len), &len, |bench, _| {
+            bench.iter(|| black_box(&a) == black_box(&b))
+        });
+        group.bench_with_input(BenchmarkId::new("String/miss", len), &len, |bench, _| {
+            bench.iter(|| black_box(&a) == black_box(&m))
+        });
+
+        let a: Str = Str::new(&src).unwrap();
+        let b: Str = Str::new(&src).unwrap();
+        let m: Str = Str::new(&miss).unwrap();
+        group.bench_with_input(BenchmarkId::new("Str/hit", len), &len, |bench, _| {
+            bench.iter(|| black_box(&a) == black_box(&b))
+        });
+        group.bench_with_input(BenchmarkId::new("Str/miss", len), &len, |bench, _| {
+            bench.iter(|| black_box(&a) == black_box(&m))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, eq);
+criterion_main!(benches);
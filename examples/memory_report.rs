@@ -0,0 +1,41 @@
+//! Compares the memory needed to store a lot of short strings as `String`, `Box<str>` and `Str`.
+//!
+//! Run with `cargo run --example memory_report --features stats`.
+
+use std::mem;
+
+use squash::{stats, Str};
+
+const COUNT: usize = 1_000_000;
+
+fn main() {
+    let words = (0..COUNT)
+        .map(|i| format!("word-{}", i % 10_000))
+        .collect::<Vec<_>>();
+    let text_bytes = words.iter().map(String::len).sum::<usize>();
+    println!("{} strings, {} bytes of text", COUNT, text_bytes);
+
+    // String and Box<str> allocate exactly the text (not counting allocator overhead), as
+    // shrunk to fit.
+    let report = |name: &str, stack: usize, heap: usize| {
+        println!(
+            "{:>10}: {:>10} bytes on stack, {:>10} bytes on heap, {:>10} total",
+            name,
+            stack * COUNT,
+            heap,
+            stack * COUNT + heap
+        );
+    };
+    report("String", mem::size_of::<String>(), text_bytes);
+    report("Box<str>", mem::size_of::<Box<str>>(), text_bytes);
+
+    stats::reset();
+    let squashed = words
+        .iter()
+        .map(|w| Str::new(w).unwrap())
+        .collect::<Vec<Str>>();
+    let s = stats::snapshot();
+    assert_eq!(COUNT, s.allocations);
+    report("Str", mem::size_of::<Str>(), s.live_bytes);
+    drop(squashed);
+}
@@ -0,0 +1,584 @@
+//! Growable buffers, turning into the slices without copying.
+
+use alloc::alloc::{handle_alloc_error, Layout};
+use alloc::fmt::{Debug, Formatter, Result as FmtResult, Write};
+use core::cmp;
+use core::iter::FromIterator;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+use core::slice;
+use core::str;
+
+use crate::header::assert_global_alloc;
+use crate::stats::{mem_alloc, mem_dealloc, mem_realloc};
+use crate::{BoxHeader, Header, OwnedSlice, Str, TooLong};
+
+/// A growable buffer for constructing an [`OwnedSlice`] of yet unknown length.
+///
+/// The [`OwnedSlice`] can't change its length, which makes it awkward to build element by element.
+/// This works like a [`Vec`][alloc::vec::Vec], but its allocation already has the layout of the
+/// slice (with room for the header in front of the data). Therefore, [`finish`][SliceBuf::finish]
+/// only writes the header and shrinks the allocation in place, the data are not copied into a new
+/// allocation.
+///
+/// As the number of bytes needed to encode the length is not known in advance, the buffer
+/// reserves the most the header may need. If the final length needs fewer, the data are moved
+/// forward within the allocation when finishing.
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::{OwnedSlice, SliceBuf};
+///
+/// let mut buf = SliceBuf::new();
+/// for i in 0..10 {
+///     if i % 3 != 0 {
+///         buf.push(i);
+///     }
+/// }
+/// buf.extend_from_slice(&[42, 43]);
+/// let s: OwnedSlice<u32> = buf.finish().unwrap();
+/// assert_eq!(&[1, 2, 4, 5, 7, 8, 42, 43], &s[..]);
+/// ```
+pub struct SliceBuf<T, H = BoxHeader>
+where
+    H: Header,
+{
+    /// The start of the allocation, if there's one.
+    ptr: Option<NonNull<u8>>,
+    cap: usize,
+    len: usize,
+    _data: PhantomData<(T, H)>,
+}
+
+impl<T, H> SliceBuf<T, H>
+where
+    H: Header,
+{
+    /// Creates an empty buffer.
+    ///
+    /// It doesn't allocate until the first element is pushed.
+    pub fn new() -> Self {
+        Self {
+            ptr: None,
+            cap: 0,
+            len: 0,
+            _data: PhantomData,
+        }
+    }
+
+    /// Creates an empty buffer with room for at least `capacity` elements.
+    ///
+    /// # Panics
+    ///
+    /// If the allocation would be too large for the address space.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut buf = Self::new();
+        buf.reserve(capacity);
+        buf
+    }
+
+    /// The number of elements the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// The layout of an allocation for `cap` elements and the offset of the data in it.
+    ///
+    /// Room for the longest possible length encoding is left in front of the data. Returns `None`
+    /// if the allocation would be too large for the address space.
+    fn layout(cap: usize) -> Option<(Layout, usize)> {
+        let extra = H::extra_needed(H::MAX_LEN).ok()?;
+        let head = Layout::new::<H>()
+            .extend(Layout::array::<u8>(extra).ok()?)
+            .ok()?
+            .0;
+        let data = Layout::array::<T>(cap).ok()?;
+        head.extend(data).ok()
+    }
+
+    /// The layout of the current allocation and the offset of the data in it.
+    fn allocated_layout(&self) -> (Layout, usize) {
+        Self::layout(self.cap).expect("Already allocated with this layout")
+    }
+
+    #[inline]
+    fn data(&self) -> *mut T {
+        match self.ptr {
+            Some(ptr) => unsafe { ptr.as_ptr().add(self.allocated_layout().1).cast() },
+            None => NonNull::dangling().as_ptr(),
+        }
+    }
+
+    /// Makes room for at least `additional` more elements.
+    ///
+    /// Like with the [`Vec`][alloc::vec::Vec], more may be reserved, to amortize the
+    /// reallocations.
+    ///
+    /// # Panics
+    ///
+    /// If the allocation would be too large for the address space.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.len.checked_add(additional).expect("Capacity overflow");
+        if needed <= self.cap {
+            return;
+        }
+        let new_cap = if mem::size_of::<T>() == 0 {
+            usize::MAX
+        } else {
+            cmp::max(needed, cmp::max(self.cap * 2, 4))
+        };
+        let (new_layout, _) = Self::layout(new_cap).expect("Capacity overflow");
+        let ptr = unsafe {
+            match self.ptr {
+                Some(ptr) => {
+                    let (old_layout, _) = self.allocated_layout();
+                    mem_realloc(ptr.as_ptr(), old_layout, new_layout.size())
+                }
+                None => mem_alloc(new_layout),
+            }
+        };
+        self.ptr = Some(NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(new_layout)));
+        self.cap = new_cap;
+    }
+
+    /// Appends an element.
+    ///
+    /// # Panics
+    ///
+    /// If the allocation would be too large for the address space.
+    pub fn push(&mut self, value: T) {
+        self.reserve(1);
+        unsafe { ptr::write(self.data().add(self.len), value) };
+        self.len += 1;
+    }
+
+    /// Appends clones of all the elements.
+    ///
+    /// # Panics
+    ///
+    /// If the allocation would be too large for the address space.
+    pub fn extend_from_slice(&mut self, src: &[T])
+    where
+        T: Clone,
+    {
+        self.reserve(src.len());
+        let data = self.data();
+        for v in src {
+            unsafe { ptr::write(data.add(self.len), v.clone()) };
+            // Only after it is written, in case the clone panics
+            self.len += 1;
+        }
+    }
+
+    /// Turns the buffer into the slice.
+    ///
+    /// The header is written into the space reserved for it and the allocation is shrunk to the
+    /// size of the slice (which may or may not move it, depending on the allocator). An empty
+    /// buffer results in the empty slice, not allocating anything.
+    ///
+    /// # Errors
+    ///
+    /// If the buffer is longer than the header can encode, [`TooLong`] is returned (and the
+    /// elements are dropped).
+    ///
+    /// # Panics
+    ///
+    /// If the header doesn't support memory of the global allocator (see
+    /// [`Header::GLOBAL_ALLOC`]).
+    pub fn finish(self) -> Result<OwnedSlice<T, H>, TooLong> {
+        assert_global_alloc::<H>();
+        let len = self.len;
+        let (new_layout, len_off, data_off) = OwnedSlice::<T, H>::layout_and_offsets(len)?;
+        let ptr = match self.ptr {
+            Some(ptr) if len > 0 => ptr.as_ptr(),
+            // Drop the empty allocation, if any
+            _ => return Ok(OwnedSlice::default()),
+        };
+        let (old_layout, old_data_off) = self.allocated_layout();
+        mem::forget(self);
+
+        unsafe {
+            // If the header needs more extra bytes than reserved (it shouldn't, but the header
+            // doesn't promise it), the allocation needs to grow before moving the data.
+            let ptr = if new_layout.size() > old_layout.size() {
+                mem_realloc(ptr, old_layout, new_layout.size())
+            } else {
+                ptr
+            };
+            if ptr.is_null() {
+                handle_alloc_error(new_layout);
+            }
+            if data_off != old_data_off {
+                ptr::copy(
+                    ptr.add(old_data_off).cast::<T>(),
+                    ptr.add(data_off).cast::<T>(),
+                    len,
+                );
+            }
+            ptr::write(ptr.cast::<H>(), H::encode_len(len, ptr.add(len_off)));
+            let ptr = if new_layout.size() < old_layout.size() {
+                mem_realloc(ptr, old_layout, new_layout.size())
+            } else {
+                ptr
+            };
+            if ptr.is_null() {
+                handle_alloc_error(new_layout);
+            }
+            Ok(OwnedSlice::from_raw(NonNull::new_unchecked(ptr.cast())))
+        }
+    }
+}
+
+impl<T, H> Drop for SliceBuf<T, H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.data(), self.len));
+            if let Some(ptr) = self.ptr {
+                mem_dealloc(ptr.as_ptr(), self.allocated_layout().0);
+            }
+        }
+    }
+}
+
+impl<T, H> Default for SliceBuf<T, H>
+where
+    H: Header,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, H> Deref for SliceBuf<T, H>
+where
+    H: Header,
+{
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.data(), self.len) }
+    }
+}
+
+impl<T, H> DerefMut for SliceBuf<T, H>
+where
+    H: Header,
+{
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.data(), self.len) }
+    }
+}
+
+impl<T, H> Debug for SliceBuf<T, H>
+where
+    T: Debug,
+    H: Header,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        Debug::fmt(self.deref(), fmt)
+    }
+}
+
+impl<T, H> Extend<T> for SliceBuf<T, H>
+where
+    H: Header,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for v in iter {
+            self.push(v);
+        }
+    }
+}
+
+impl<'a, T, H> Extend<&'a T> for SliceBuf<T, H>
+where
+    T: Clone + 'a,
+    H: Header,
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+impl<T, H> FromIterator<T> for SliceBuf<T, H>
+where
+    H: Header,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut buf = Self::new();
+        buf.extend(iter);
+        buf
+    }
+}
+
+unsafe impl<T: Send, H: Header> Send for SliceBuf<T, H> {}
+unsafe impl<T: Sync, H: Header> Sync for SliceBuf<T, H> {}
+
+/// A growable buffer for constructing a [`Str`] of yet unknown length.
+///
+/// This is the [`SliceBuf`] for strings. It also implements [`Write`], so it can be
+/// the target of [`write!`].
+///
+/// # Examples
+///
+/// ```rust
+/// use std::fmt::Write;
+/// use squash::{Str, StrBuf};
+///
+/// let mut buf = StrBuf::new();
+/// buf.push_str("Hello");
+/// write!(buf, " {}", 42).unwrap();
+/// buf.push('!');
+/// let s: Str = buf.finish().unwrap();
+/// assert_eq!("Hello 42!", &s as &str);
+/// ```
+pub struct StrBuf<H = BoxHeader>(SliceBuf<u8, H>)
+where
+    H: Header;
+
+impl<H> StrBuf<H>
+where
+    H: Header,
+{
+    /// Creates an empty buffer.
+    ///
+    /// It doesn't allocate until something is pushed.
+    pub fn new() -> Self {
+        Self(SliceBuf::new())
+    }
+
+    /// Creates an empty buffer with room for at least `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(SliceBuf::with_capacity(capacity))
+    }
+
+    /// The number of bytes the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    /// Makes room for at least `additional` more bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+
+    /// Appends a character.
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Appends a string.
+    pub fn push_str(&mut self, s: &str) {
+        self.0.extend_from_slice(s.as_bytes());
+    }
+
+    /// Turns the buffer into the string.
+    ///
+    /// See [`SliceBuf::finish`].
+    ///
+    /// # Errors
+    ///
+    /// If the string is longer than the header can encode, [`TooLong`] is returned.
+    pub fn finish(self) -> Result<Str<H>, TooLong> {
+        self.0.finish().map(Str)
+    }
+}
+
+impl<H> Default for StrBuf<H>
+where
+    H: Header,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H> Deref for StrBuf<H>
+where
+    H: Header,
+{
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        // Only whole strings are ever pushed
+        unsafe { str::from_utf8_unchecked(&self.0) }
+    }
+}
+
+impl<H> Debug for StrBuf<H>
+where
+    H: Header,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        Debug::fmt(self.deref(), fmt)
+    }
+}
+
+impl<H> Write for StrBuf<H>
+where
+    H: Header,
+{
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<'a, H> Extend<&'a str> for StrBuf<H>
+where
+    H: Header,
+{
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        for s in iter {
+            self.push_str(s);
+        }
+    }
+}
+
+impl<H> Extend<char> for StrBuf<H>
+where
+    H: Header,
+{
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for c in iter {
+            self.push(c);
+        }
+    }
+}
+
+impl<'a, H> Extend<&'a char> for StrBuf<H>
+where
+    H: Header,
+{
+    fn extend<I: IntoIterator<Item = &'a char>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use core::cell::Cell;
+
+    use super::*;
+    use crate::header::mock::ShortMockHeader;
+    use crate::ArcHeader;
+
+    #[test]
+    fn same_as_vec() {
+        for len in [0, 1, 3, 63, 64, 65, 300, 16_384, 20_000] {
+            let v: Vec<u32> = (0..len).collect();
+            let mut buf = SliceBuf::<u32>::new();
+            for &i in &v {
+                buf.push(i);
+            }
+            assert_eq!(v, &buf[..]);
+            let s = buf.finish().unwrap();
+            assert_eq!(OwnedSlice::<u32>::new(&v).unwrap(), s);
+            assert_eq!(len as usize, s.len());
+        }
+
+        let v: Vec<u8> = (0..=255).collect();
+        let mut buf: SliceBuf<u8, ArcHeader> = v[..10].iter().copied().collect();
+        buf.extend_from_slice(&v[10..]);
+        assert_eq!(buf.finish().unwrap(), v[..]);
+    }
+
+    #[test]
+    fn extend_refs() {
+        let strings = ["a".to_owned(), "bc".to_owned(), String::new()];
+        let mut buf = SliceBuf::<String, ArcHeader>::new();
+        for s in &strings {
+            buf.extend([s]);
+        }
+        buf.extend(&strings[1..]);
+        let s = buf.finish().unwrap();
+        assert_eq!(["a", "bc", "", "bc", ""], s[..]);
+
+        let mut buf = StrBuf::<BoxHeader>::new();
+        buf.extend(&['a', 'ř']);
+        assert_eq!("ař", &buf.finish().unwrap() as &str);
+    }
+
+    #[test]
+    fn empty() {
+        let s = SliceBuf::<String>::new().finish().unwrap();
+        assert_eq!(OwnedSlice::<String>::default().as_ptr(), s.as_ptr());
+
+        let mut buf = SliceBuf::<u64>::with_capacity(10);
+        assert!(buf.capacity() >= 10);
+        assert!(buf.is_empty());
+        buf.push(1);
+        buf[0] = 2;
+        assert_eq!([2], buf[..]);
+        let s = SliceBuf::<u64>::with_capacity(10).finish().unwrap();
+        assert_eq!(OwnedSlice::<u64>::default().as_ptr(), s.as_ptr());
+
+        let units: SliceBuf<()> = core::iter::repeat_n((), 1000).collect();
+        assert_eq!(1000, units.finish().unwrap().len());
+    }
+
+    #[test]
+    fn too_long() {
+        let mut buf = SliceBuf::<u8, ShortMockHeader>::new();
+        buf.extend_from_slice(&[1, 2, 3, 4]);
+        assert_eq!([1, 2, 3, 4], buf.finish().unwrap()[..]);
+        let mut buf = SliceBuf::<u8, ShortMockHeader>::new();
+        buf.extend_from_slice(&[1, 2, 3, 4, 5]);
+        buf.finish().unwrap_err();
+    }
+
+    #[derive(Clone)]
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn drops() {
+        let drops = Rc::new(Cell::new(0));
+        let mut buf = SliceBuf::<DropCounter>::new();
+        buf.extend((0..10).map(|_| DropCounter(Rc::clone(&drops))));
+        drop(buf);
+        assert_eq!(10, drops.get());
+
+        let mut buf = SliceBuf::<DropCounter>::new();
+        buf.extend((0..10).map(|_| DropCounter(Rc::clone(&drops))));
+        let s = buf.finish().unwrap();
+        assert_eq!(10, drops.get());
+        drop(s);
+        assert_eq!(20, drops.get());
+    }
+
+    #[test]
+    fn str_buf() {
+        let mut buf = StrBuf::<ArcHeader>::new();
+        buf.push_str("čau");
+        buf.push(' ');
+        write!(buf, "{}", 42).unwrap();
+        buf.extend(["a", "b"]);
+        buf.extend("xyz".chars());
+        assert_eq!("čau 42abxyz", &buf as &str);
+        assert_eq!("čau 42abxyz", &buf.finish().unwrap() as &str);
+
+        let long: String = core::iter::repeat_n('ř', 1000).collect();
+        let mut buf = StrBuf::<BoxHeader>::with_capacity(1);
+        buf.extend(long.chars());
+        assert_eq!(long, &buf.finish().unwrap() as &str);
+        assert!(StrBuf::<BoxHeader>::new().finish().unwrap().is_empty());
+    }
+}
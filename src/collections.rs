@@ -0,0 +1,263 @@
+//! Collections storing squashed keys.
+//!
+//! When keeping many small byte strings (for example a table of interned strings), the per-key
+//! overhead of `HashSet<Box<[u8]>>` is dominated by the fat pointers. The [`SquashSet`] stores
+//! each key as a single-word [`OwnedSlice`] instead.
+//!
+//! Available with the `hashbrown` feature.
+
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use core::hash::BuildHasher;
+use core::iter::FusedIterator;
+
+use hashbrown::hash_table::{self, Entry, HashTable};
+use hashbrown::DefaultHashBuilder;
+
+use crate::{BoxHeader, Header, OwnedSlice, TooLong};
+
+/// A set of byte strings, each stored as an [`OwnedSlice`].
+///
+/// Lookups and inserts of keys already present work directly with the borrowed `&[u8]` and never
+/// allocate. A new squashed slice is created only when a key is actually inserted.
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::collections::SquashSet;
+///
+/// let mut set = SquashSet::<squash::BoxHeader>::new();
+/// assert!(set.insert(b"hello").unwrap());
+/// assert!(set.insert(b"world").unwrap());
+/// assert!(!set.insert(b"hello").unwrap());
+///
+/// assert_eq!(2, set.len());
+/// assert!(set.contains(b"world"));
+/// assert!(!set.contains(b"nope"));
+/// assert_eq!(b"hello", &set.get(b"hello").unwrap()[..]);
+/// ```
+pub struct SquashSet<H = BoxHeader, S = DefaultHashBuilder>
+where
+    H: Header,
+{
+    table: HashTable<OwnedSlice<u8, H>>,
+    hasher: S,
+}
+
+impl<H> SquashSet<H>
+where
+    H: Header,
+{
+    /// Creates an empty set.
+    ///
+    /// Doesn't allocate until the first key is inserted.
+    pub fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::default())
+    }
+
+    /// Creates an empty set with space for at least `capacity` keys.
+    ///
+    /// Only the table is preallocated, each key still gets its own allocation.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            table: HashTable::with_capacity(capacity),
+            hasher: DefaultHashBuilder::default(),
+        }
+    }
+}
+
+impl<H, S> SquashSet<H, S>
+where
+    H: Header,
+    S: BuildHasher,
+{
+    /// Creates an empty set, using the provided hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            table: HashTable::new(),
+            hasher,
+        }
+    }
+
+    /// The number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Checks if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Inserts the key, unless it is already present.
+    ///
+    /// Returns if the key was newly inserted. If it was already present, nothing is allocated.
+    ///
+    /// # Errors
+    ///
+    /// If the key is too long for the header. The set is left unchanged.
+    pub fn insert(&mut self, key: &[u8]) -> Result<bool, TooLong> {
+        let hasher = &self.hasher;
+        let entry = self.table.entry(
+            hasher.hash_one(key),
+            |stored| stored[..] == *key,
+            |stored| hasher.hash_one(&stored[..]),
+        );
+        match entry {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(vacant) => {
+                vacant.insert(OwnedSlice::new(key)?);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Checks if the key is present.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the stored copy of the key, if present.
+    ///
+    /// With a sharing header (like [`RcHeader`][crate::RcHeader]), it can be cloned cheaply to
+    /// get another owner of the same data.
+    pub fn get(&self, key: &[u8]) -> Option<&OwnedSlice<u8, H>> {
+        self.table
+            .find(self.hasher.hash_one(key), |stored| stored[..] == *key)
+    }
+
+    /// Iterates through the keys, in arbitrary order.
+    pub fn iter(&self) -> Iter<'_, H> {
+        Iter(self.table.iter())
+    }
+}
+
+impl<H, S> Clone for SquashSet<H, S>
+where
+    H: Header,
+    OwnedSlice<u8, H>: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<H, S> Default for SquashSet<H, S>
+where
+    H: Header,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<H, S> Debug for SquashSet<H, S>
+where
+    H: Header,
+    S: BuildHasher,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, H, S> IntoIterator for &'a SquashSet<H, S>
+where
+    H: Header,
+    S: BuildHasher,
+{
+    type Item = &'a OwnedSlice<u8, H>;
+    type IntoIter = Iter<'a, H>;
+
+    fn into_iter(self) -> Iter<'a, H> {
+        self.iter()
+    }
+}
+
+/// An iterator over the keys of a [`SquashSet`].
+///
+/// Created by [`SquashSet::iter`].
+pub struct Iter<'a, H>(hash_table::Iter<'a, OwnedSlice<u8, H>>)
+where
+    H: Header;
+
+impl<'a, H> Iterator for Iter<'a, H>
+where
+    H: Header,
+{
+    type Item = &'a OwnedSlice<u8, H>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<H> ExactSizeIterator for Iter<'_, H> where H: Header {}
+
+impl<H> FusedIterator for Iter<'_, H> where H: Header {}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::header::mock::ShortMockHeader;
+    use crate::RcHeader;
+
+    #[test]
+    fn insert_contains() {
+        let mut set = SquashSet::<BoxHeader>::new();
+        assert!(set.is_empty());
+        assert!(!set.contains(b"hello"));
+        assert!(set.get(b"hello").is_none());
+
+        assert!(set.insert(b"hello").unwrap());
+        assert!(set.insert(b"").unwrap());
+        assert!(!set.insert(b"hello").unwrap());
+        assert!(!set.insert(b"").unwrap());
+        assert_eq!(2, set.len());
+
+        assert!(set.contains(b"hello"));
+        assert!(set.contains(b""));
+        assert!(!set.contains(b"hell"));
+        assert!(!set.contains(b"hello!"));
+        assert_eq!(b"hello", &set.get(b"hello").unwrap()[..]);
+    }
+
+    #[test]
+    fn iterate() {
+        let words = ["a", "bb", "ccc", "", "a", "bb"];
+        let mut set = SquashSet::<RcHeader>::default();
+        for w in &words {
+            set.insert(w.as_bytes()).unwrap();
+        }
+        let expected = words.iter().map(|w| w.as_bytes()).collect::<HashSet<_>>();
+        assert_eq!(expected.len(), set.iter().len());
+        let found = set.iter().map(|k| &k[..]).collect::<HashSet<_>>();
+        assert_eq!(expected, found);
+
+        // The shared keys are handed out without copying
+        let key = set.get(b"ccc").unwrap().clone();
+        assert_eq!(key.as_ptr(), set.get(b"ccc").unwrap().as_ptr());
+
+        let cloned = set.clone();
+        assert_eq!(found, (&cloned).into_iter().map(|k| &k[..]).collect());
+    }
+
+    #[test]
+    fn too_long() {
+        let mut set = SquashSet::<ShortMockHeader>::new();
+        assert!(set.insert(b"1234").unwrap());
+        assert!(set.insert(b"12345").is_err());
+        assert_eq!(1, set.len());
+        assert!(!set.contains(b"12345"));
+    }
+}
@@ -0,0 +1,107 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::{slice_from_vec, str_from_string};
+use crate::{Header, OwnedSlice, Str};
+
+/// Generates the slice the same way as a [`Vec`] would be.
+///
+/// Slices too long for the header are truncated.
+impl<'a, T, H> Arbitrary<'a> for OwnedSlice<T, H>
+where
+    T: Arbitrary<'a>,
+    H: Header,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Vec::arbitrary(u).map(slice_from_vec)
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        Vec::arbitrary_take_rest(u).map(slice_from_vec)
+    }
+
+    #[inline]
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <Vec<T> as Arbitrary>::size_hint(depth)
+    }
+}
+
+/// Generates the string the same way as a [`String`] would be.
+///
+/// Strings too long for the header are truncated.
+impl<'a, H> Arbitrary<'a> for Str<H>
+where
+    H: Header,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        String::arbitrary(u).map(str_from_string)
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        String::arbitrary_take_rest(u).map(str_from_string)
+    }
+
+    #[inline]
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <String as Arbitrary>::size_hint(depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Deref;
+
+    use super::*;
+    use crate::{BoxHeader, RcHeader};
+
+    /// Some reproducible noise to feed the generators with.
+    fn noise(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut seen_empty = false;
+        let seeds = if cfg!(miri) { 1 } else { 100 };
+        for seed in 0..seeds {
+            let data = noise(seed, 2048);
+            let mut u = Unstructured::new(&data);
+            while !u.is_empty() {
+                let s = OwnedSlice::<u8, BoxHeader>::arbitrary(&mut u).unwrap();
+                seen_empty |= s.is_empty();
+                let copy = OwnedSlice::<u8, RcHeader>::new(&s).unwrap();
+                assert_eq!(s.deref(), copy.deref());
+
+                let s = Str::<BoxHeader>::arbitrary(&mut u).unwrap();
+                let copy: Str<RcHeader> = Str::new(&s).unwrap();
+                assert_eq!(s.deref(), copy.deref());
+            }
+            let s = OwnedSlice::<u8, BoxHeader>::arbitrary_take_rest(Unstructured::new(&data));
+            let s = s.unwrap();
+            let v = Vec::<u8>::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+            assert_eq!(v, s.deref());
+
+            let s = Str::<BoxHeader>::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+            let string = String::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+            assert_eq!(string, s.deref());
+        }
+        assert!(seen_empty);
+
+        // Each element is preceded by a "continue" flag, so random noise rarely produces long
+        // slices. Craft one that does, to get a multi-byte header.
+        let data = [0xff; 1000];
+        let s = OwnedSlice::<u8, BoxHeader>::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        assert!(s.len() > 64);
+        assert!(s.iter().all(|&b| b == 0xff));
+    }
+}
@@ -0,0 +1,190 @@
+//! Support for the [`arc-swap`](https://crates.io/crates/arc-swap) crate.
+//!
+//! The slices with the [`ArcHeader`] are atomically reference counted pointers already, so they
+//! can be stored in the [`ArcSwapAny`][::arc_swap::ArcSwapAny] directly.
+//!
+//! The empty slices all point to the same sentinel, which has no reference count. That's fine,
+//! they are all the same value and the conversions to and from the raw pointer just pass the
+//! sentinel through.
+//!
+//! A panic inside the [`ArcSwapAny`][::arc_swap::ArcSwapAny] could leave it in an inconsistent
+//! state. Therefore, overflowing the reference count aborts the process, the same way the std
+//! [`Arc`][alloc::sync::Arc] does.
+
+use core::ptr::NonNull;
+
+use ::arc_swap::RefCnt;
+
+use crate::header::abort;
+use crate::{ArcHeader, Header, OwnedSlice, Str};
+
+/// Allows storing the slice in an [`ArcSwapAny`][::arc_swap::ArcSwapAny].
+///
+/// # Examples
+///
+/// ```rust
+/// use arc_swap::ArcSwapAny;
+/// use squash::{ArcHeader, Str};
+///
+/// let current: ArcSwapAny<Str<ArcHeader>> = ArcSwapAny::new(Str::new("old").unwrap());
+/// assert_eq!("old", &current.load() as &str);
+/// current.store(Str::new("new").unwrap());
+/// assert_eq!("new", &current.load() as &str);
+/// ```
+unsafe impl<T> RefCnt for OwnedSlice<T, ArcHeader> {
+    type Base = ArcHeader;
+
+    fn into_ptr(me: Self) -> *mut ArcHeader {
+        OwnedSlice::into_raw(me).as_ptr()
+    }
+
+    fn as_ptr(me: &Self) -> *mut ArcHeader {
+        OwnedSlice::as_opaque_ptr(me).cast_mut().cast()
+    }
+
+    fn inc(me: &Self) -> *mut ArcHeader {
+        let ptr = <Self as RefCnt>::as_ptr(me);
+        if !me.is_sentinel() && !unsafe { (*ptr).inc() } {
+            abort();
+        }
+        ptr
+    }
+
+    unsafe fn from_ptr(ptr: *const ArcHeader) -> Self {
+        OwnedSlice::from_raw(NonNull::new_unchecked(ptr.cast_mut()))
+    }
+}
+
+/// Allows storing the string in an [`ArcSwapAny`][::arc_swap::ArcSwapAny].
+unsafe impl RefCnt for Str<ArcHeader> {
+    type Base = ArcHeader;
+
+    fn into_ptr(me: Self) -> *mut ArcHeader {
+        RefCnt::into_ptr(me.0)
+    }
+
+    fn as_ptr(me: &Self) -> *mut ArcHeader {
+        RefCnt::as_ptr(&me.0)
+    }
+
+    fn inc(me: &Self) -> *mut ArcHeader {
+        RefCnt::inc(&me.0)
+    }
+
+    unsafe fn from_ptr(ptr: *const ArcHeader) -> Self {
+        Str(RefCnt::from_ptr(ptr))
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use std::env;
+    use std::panic;
+    use std::process::Command;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+
+    use ::arc_swap::ArcSwapAny;
+
+    use super::*;
+
+    #[test]
+    fn raw_round_trip() {
+        let s = OwnedSlice::<u32, ArcHeader>::new(&[1, 2, 3]).unwrap();
+        let ptr = RefCnt::as_ptr(&s);
+        let inc = RefCnt::inc(&s);
+        assert_eq!(ptr, inc);
+        assert_eq!(ptr, RefCnt::into_ptr(s));
+        let mut s = unsafe { OwnedSlice::<u32, ArcHeader>::from_ptr(ptr) };
+        assert_eq!([1, 2, 3], s[..]);
+        unsafe { OwnedSlice::<u32, ArcHeader>::dec(inc) };
+        assert!(OwnedSlice::get_mut(&mut s).is_some());
+
+        // The sentinel survives the round trips and the reference counting does nothing to it
+        let empty = Str::<ArcHeader>::new("").unwrap();
+        let ptr = RefCnt::as_ptr(&empty);
+        assert_eq!(ptr, RefCnt::inc(&empty));
+        unsafe { Str::<ArcHeader>::dec(ptr) };
+        let empty = unsafe { Str::<ArcHeader>::from_ptr(RefCnt::into_ptr(empty)) };
+        assert!(empty.is_empty());
+        assert_eq!(ptr, RefCnt::as_ptr(&Str::<ArcHeader>::new("").unwrap()));
+    }
+
+    /// Runs the aborting part in a child process (this same test binary), as it kills the process.
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn inc_overflow_aborts() {
+        const VAR: &str = "SQUASH_ARC_SWAP_ABORT_CHILD";
+        if env::var_os(VAR).is_some() {
+            let s = OwnedSlice::<u8, ArcHeader>::new(b"Hello").unwrap();
+            unsafe { (*RefCnt::as_ptr(&s)).saturate() };
+            let _ = panic::catch_unwind(|| RefCnt::inc(&s));
+            unreachable!("Should have aborted");
+        }
+
+        let output = Command::new(env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "ext::arc_swap::tests::inc_overflow_aborts",
+                "--nocapture",
+            ])
+            .env(VAR, "1")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            // SIGABRT
+            assert_eq!(Some(6), output.status.signal());
+        }
+    }
+
+    #[test]
+    fn swap_stress() {
+        const READERS: usize = 4;
+        #[cfg(miri)]
+        const ROUNDS: usize = 50;
+        #[cfg(not(miri))]
+        const ROUNDS: usize = 10_000;
+
+        let current: ArcSwapAny<Str<ArcHeader>> = ArcSwapAny::new(Str::new("0").unwrap());
+        let done = AtomicBool::new(false);
+        let barrier = Barrier::new(READERS + 1);
+        thread::scope(|s| {
+            for _ in 0..READERS {
+                s.spawn(|| {
+                    barrier.wait();
+                    let mut last = 0;
+                    while !done.load(Ordering::Relaxed) {
+                        let value = current.load();
+                        let num: usize = if value.is_empty() {
+                            last
+                        } else {
+                            value.parse().unwrap()
+                        };
+                        // The writer only counts up
+                        assert!(num >= last);
+                        last = num;
+                        let full = current.load_full();
+                        drop(value);
+                        drop(full);
+                    }
+                });
+            }
+            barrier.wait();
+            for i in 1..=ROUNDS {
+                // Mix in the empty sentinel too
+                let new = if i % 10 == 5 {
+                    Str::new("").unwrap()
+                } else {
+                    Str::new(&i.to_string()).unwrap()
+                };
+                current.store(new);
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(ROUNDS.to_string(), &current.load() as &str);
+    }
+}
@@ -0,0 +1,159 @@
+use core::marker::PhantomData;
+
+use ::bumpalo::Bump;
+
+use crate::slice::Builder;
+use crate::{BoxHeader, Header, OwnedSlice, Shared, Str, TooLong};
+
+/// A header for slices allocated inside a [`bumpalo`](https://docs.rs/bumpalo) arena.
+///
+/// The memory is owned by the arena and is freed all at once when the arena is dropped. Therefore,
+/// the slices don't free anything on their own. Note that this also means the destructors of the
+/// elements are *never* run (this is similar to what [`Bump::alloc`] does).
+///
+/// Because the arena owns the memory, cloning such slice is cheap (it only copies the pointer),
+/// but it is not possible to get a mutable access to the data.
+///
+/// The slices with this header need to be created by the dedicated constructors
+/// ([`OwnedSlice::new_in`], [`Str::new_in`]). The ordinary constructors (and
+/// [`convert`][OwnedSlice::convert] into this header) panic, as the memory from the global
+/// allocator would be leaked.
+///
+/// The length is encoded the same way as in the [`BoxHeader`].
+///
+/// # Examples
+///
+/// ```rust
+/// use bumpalo::Bump;
+/// use squash::{BumpHeader, Str};
+///
+/// let arena = Bump::new();
+/// let s: Str<BumpHeader> = Str::new_in(&arena, "Hello").unwrap();
+/// assert_eq!("Hello", &s as &str);
+/// ```
+pub struct BumpHeader<'bump> {
+    len: BoxHeader,
+    _arena: PhantomData<&'bump Bump>,
+}
+
+unsafe impl Header for BumpHeader<'_> {
+    type Ownership = Shared;
+    const MAX_LEN: usize = BoxHeader::MAX_LEN;
+    #[inline]
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        BoxHeader::extra_needed(len)
+    }
+    #[inline]
+    unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+        Self {
+            len: BoxHeader::encode_len(len, extra),
+            _arena: PhantomData,
+        }
+    }
+    #[inline]
+    unsafe fn decode_len(&self, extra: *const u8) -> usize {
+        self.len.decode_len(extra)
+    }
+    #[inline]
+    fn extra_len(&self) -> Option<usize> {
+        self.len.extra_len()
+    }
+    #[inline]
+    fn inc(&self) -> bool {
+        // The memory lives as long as the arena does, so we can share it freely.
+        true
+    }
+    #[inline]
+    fn dec(&self) -> bool {
+        // The arena frees everything at once
+        false
+    }
+    #[inline]
+    fn is_unique(&self) -> bool {
+        // We don't track the clones
+        false
+    }
+
+    const GLOBAL_ALLOC: bool = false;
+}
+
+impl<'bump, T> OwnedSlice<T, BumpHeader<'bump>> {
+    /// Creates a new owned slice inside the arena, by cloning the content of the passed one.
+    ///
+    /// # Errors
+    ///
+    /// If the slice is bigger than the header can encode, [`TooLong`] is returned.
+    pub fn new_in(arena: &'bump Bump, src: &[T]) -> Result<Self, TooLong>
+    where
+        T: Clone,
+    {
+        let mut builder = Builder::new_in(src.len(), |layout| arena.alloc_layout(layout), false)?;
+        for elem in src {
+            builder.push(elem.clone());
+        }
+
+        Ok(builder.finish())
+    }
+}
+
+impl<'bump> Str<BumpHeader<'bump>> {
+    /// Creates a new owned string slice inside the arena.
+    #[inline]
+    pub fn new_in(arena: &'bump Bump, s: &str) -> Result<Self, TooLong> {
+        OwnedSlice::new_in(arena, s.as_bytes()).map(Str)
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use core::ops::Deref;
+
+    use super::*;
+
+    #[test]
+    fn many_strings() {
+        // Miri is slow, give it a smaller amount
+        let count = if cfg!(miri) { 100 } else { 10_000 };
+        let arena = Bump::new();
+        let strings = (0..count)
+            .map(|i| Str::new_in(&arena, &i.to_string()).unwrap())
+            .collect::<Vec<_>>();
+        for (i, s) in strings.iter().enumerate() {
+            assert_eq!(i.to_string(), s.deref());
+        }
+
+        let s2 = strings[42].clone();
+        assert_eq!(strings[42].as_ptr(), s2.as_ptr());
+    }
+
+    #[test]
+    fn slices() {
+        let arena = Bump::new();
+        let s = OwnedSlice::new_in(&arena, &[1u64, 2, 3]).unwrap();
+        assert_eq!(&[1, 2, 3], s.deref());
+        let long = OwnedSlice::new_in(&arena, &[0u8; 300]).unwrap();
+        assert_eq!(300, long.len());
+        let empty = OwnedSlice::<u8, BumpHeader>::new_in(&arena, &[]).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    /// The ordinary constructors would leak the memory of the global allocator.
+    #[test]
+    #[should_panic(expected = "global allocator")]
+    fn global_new() {
+        let _ = OwnedSlice::<u8, BumpHeader>::new(&[1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "global allocator")]
+    fn global_from_fn() {
+        let _ = OwnedSlice::<u8, BumpHeader>::from_fn(2, |i| i as u8);
+    }
+
+    #[test]
+    #[should_panic(expected = "global allocator")]
+    fn global_convert() {
+        let s = OwnedSlice::<u8>::new(&[1, 2]).unwrap();
+        let _ = s.convert::<BumpHeader>();
+    }
+}
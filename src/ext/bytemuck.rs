@@ -0,0 +1,614 @@
+//! Support for the [`bytemuck`](https://crates.io/crates/bytemuck) crate.
+//!
+//! The [`OwnedSlice`] itself is a pointer, so it can't be [`Pod`]. But its data can be cast to
+//! slices of other plain old data types.
+
+use alloc::alloc::handle_alloc_error;
+use alloc::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+use core::slice;
+
+use ::bytemuck::{AnyBitPattern, NoUninit, Pod, PodCastError, Zeroable};
+
+use crate::slice::Builder;
+use crate::stats::mem_alloc_zeroed;
+use crate::{BoxHeader, ByteHeader, DecodeError, Header, OwnedSlice, TooLong};
+
+/// An error when reinterpreting the [`OwnedSlice`] as a slice of another type.
+///
+/// See [`OwnedSlice::try_cast`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CastError {
+    /// The size of the data is not a multiple of the size of the target type (or one of the
+    /// types is zero sized).
+    Size,
+    /// The allocation doesn't have the layout of a slice of the target type.
+    ///
+    /// The position of the data after the header depends on the alignment of the element type
+    /// and the number of bytes needed to encode the length, so a slice of a less aligned type
+    /// usually can't be reinterpreted as a slice of a more aligned one.
+    Layout,
+    /// The length would change, but the data is shared with other owners.
+    Shared,
+}
+
+impl Display for CastError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            CastError::Size => write!(fmt, "Not a multiple of the element size"),
+            CastError::Layout => write!(fmt, "Incompatible layout of the allocation"),
+            CastError::Shared => write!(fmt, "Can't change the length of shared data"),
+        }
+    }
+}
+
+impl Error for CastError {}
+
+impl<T, H> OwnedSlice<T, H>
+where
+    H: Header,
+{
+    /// Reinterprets the data as a slice of another plain old data type.
+    ///
+    /// Only the data is cast, the header with the length is not part of the result. The length of
+    /// the result is computed from the size in bytes, so it differs from the length of `self` if
+    /// the sizes of the types differ.
+    ///
+    /// The empty slice can be cast to any type.
+    ///
+    /// # Panics
+    ///
+    /// If the cast is not possible (see [`try_cast_slice`][OwnedSlice::try_cast_slice]).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u32> = OwnedSlice::new(&[0x0403_0201]).unwrap();
+    /// assert_eq!(4, s.cast_slice::<u8>().len());
+    /// ```
+    #[inline]
+    pub fn cast_slice<U>(&self) -> &[U]
+    where
+        T: NoUninit,
+        U: AnyBitPattern,
+    {
+        match self.try_cast_slice() {
+            Ok(result) => result,
+            Err(e) => panic!("Can't cast the slice: {}", e),
+        }
+    }
+
+    /// Reinterprets the data as a slice of another plain old data type, if possible.
+    ///
+    /// # Errors
+    ///
+    /// The cast fails if the data is not aligned enough for `U` or if its size is not a multiple
+    /// of the size of `U`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u8> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// assert!(s.try_cast_slice::<u16>().is_err());
+    /// ```
+    #[inline]
+    pub fn try_cast_slice<U>(&self) -> Result<&[U], PodCastError>
+    where
+        T: NoUninit,
+        U: AnyBitPattern,
+    {
+        if self.is_empty() {
+            // The empty slice has no data to cast, but its dangling pointer may not be aligned
+            // enough for U.
+            return Ok(&[]);
+        }
+        ::bytemuck::try_cast_slice(&self[..])
+    }
+
+    /// Reinterprets the slice as a slice of another plain old data type, without copying.
+    ///
+    /// The allocation is reused as it is, only the length in the header is rewritten (if it
+    /// changes). This is possible only if the data of the new type would be placed at the same
+    /// position and the whole allocation would be of the same size and alignment (see
+    /// [`CastError::Layout`]). That's always the case for types of the same size and alignment
+    /// (like `u32` and `f32`). Between types of different alignment, it depends on the header ‒ it
+    /// works if the header with its extra bytes ends at a position aligned for both. If not
+    /// possible, the slice is returned back with the error. No copy is made as a fallback,
+    /// [`cast_slice`][OwnedSlice::cast_slice] can do that.
+    ///
+    /// # Errors
+    ///
+    /// See [`CastError`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{ArcHeader, CastError, OwnedSlice};
+    /// let s: OwnedSlice<u32> = OwnedSlice::new(&[0x3f80_0000]).unwrap();
+    /// let floats: OwnedSlice<f32> = s.try_cast().unwrap();
+    /// assert_eq!(&[1.0], &floats[..]);
+    ///
+    /// // The 12 bytes of the ArcHeader keep the data aligned for u32.
+    /// let bytes: OwnedSlice<u8, ArcHeader> = OwnedSlice::new(&[0; 8]).unwrap();
+    /// let words: OwnedSlice<u32, ArcHeader> = bytes.try_cast().unwrap();
+    /// assert_eq!(&[0, 0], &words[..]);
+    ///
+    /// // The data of bytes are right after the single byte of header, not aligned for u16.
+    /// let bytes: OwnedSlice<u8> = OwnedSlice::new(&[1, 2, 3, 4]).unwrap();
+    /// let (bytes, err) = bytes.try_cast::<u16>().unwrap_err();
+    /// assert_eq!(CastError::Layout, err);
+    /// assert_eq!(4, bytes.len());
+    /// ```
+    pub fn try_cast<U>(self) -> Result<OwnedSlice<U, H>, (Self, CastError)>
+    where
+        T: Pod,
+        U: Pod,
+    {
+        if self.is_empty() {
+            return Ok(OwnedSlice::default());
+        }
+        if mem::size_of::<T>() == 0 || mem::size_of::<U>() == 0 {
+            return Err((self, CastError::Size));
+        }
+        let len = self.len();
+        let bytes = len * mem::size_of::<T>();
+        if !bytes.is_multiple_of(mem::size_of::<U>()) {
+            return Err((self, CastError::Size));
+        }
+        let new_len = bytes / mem::size_of::<U>();
+
+        let old = Self::layout_and_offsets(len).expect("Already have layout for this size");
+        let new = OwnedSlice::<U, H>::layout_and_offsets(new_len);
+        if new.ok() != Some(old) {
+            return Err((self, CastError::Layout));
+        }
+        let header = Self::into_raw(self);
+        unsafe {
+            if new_len != len {
+                // Other owners (including weak ones) would still see the old length.
+                if !header.as_ref().is_unique() {
+                    return Err((Self::from_raw(header), CastError::Shared));
+                }
+                let (_, len_off, _) = old;
+                let extra = header.as_ptr().cast::<u8>().add(len_off);
+                ptr::write(header.as_ptr(), H::encode_len(new_len, extra));
+            }
+            Ok(OwnedSlice::from_raw(header))
+        }
+    }
+
+    /// Creates a slice of the given length, filled with zeroes.
+    ///
+    /// This is the equivalent of `vec![0; len]`. The memory is requested zeroed from the
+    /// allocator, the elements are not written one by one.
+    ///
+    /// # Errors
+    ///
+    /// If the length is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<f32> = OwnedSlice::zeroed(3).unwrap();
+    /// assert_eq!(&[0.0; 3], &s[..]);
+    /// ```
+    pub fn zeroed(len: usize) -> Result<Self, TooLong>
+    where
+        T: Zeroable,
+    {
+        let alloc = |layout| {
+            let ptr = unsafe { mem_alloc_zeroed(layout) };
+            NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(layout))
+        };
+        let mut builder = Builder::<T, H>::new_in(len, alloc, true)?;
+        // All zero bytes are a valid T.
+        unsafe { builder.assume_initialized() };
+        Ok(builder.finish())
+    }
+}
+
+/// A slice borrowed from bytes in the format written by [`OwnedSlice::write_to`].
+///
+/// This allows using the data in place, without copying them into a new allocation, for example
+/// from a memory mapped file. The bytes are validated when parsing, so a corrupted input results
+/// in an error, not undefined behaviour.
+///
+/// The header `H` decides the encoding of the length and needs to be the same as the one of the
+/// written slices. Only headers valid for any bytes can be used (the [`BoxHeader`] and the
+/// [`ByteHeader`]), the reference counted ones don't make sense outside of an allocation.
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::{OwnedSlice, SliceRef};
+///
+/// let mut buf = Vec::new();
+/// OwnedSlice::<u8>::new(b"Hello").unwrap().write_to(&mut buf);
+/// OwnedSlice::<u8>::new(b"World").unwrap().write_to(&mut buf);
+///
+/// let (hello, rest) = SliceRef::<u8>::parse(&buf).unwrap();
+/// let (world, rest) = SliceRef::<u8>::parse(rest).unwrap();
+/// assert_eq!(b"Hello", &hello[..]);
+/// assert_eq!(b"World", &world[..]);
+/// assert!(rest.is_empty());
+/// ```
+pub struct SliceRef<'a, T, H = BoxHeader> {
+    data: &'a [T],
+    _header: PhantomData<H>,
+}
+
+impl<'a, T, H> SliceRef<'a, T, H>
+where
+    T: AnyBitPattern,
+    H: Header + AnyBitPattern,
+{
+    /// Parses one slice from the beginning of the bytes.
+    ///
+    /// Returns the slice and the bytes after it.
+    ///
+    /// The bytes must start at an address aligned for `T`. The slices of one type written one
+    /// after another into a buffer stay aligned if the buffer itself starts aligned. When mixing
+    /// types, a slice of a more aligned type needs to be placed at an aligned position.
+    ///
+    /// # Errors
+    ///
+    /// * [`DecodeError::Misaligned`] if the bytes are not aligned for `T`.
+    /// * [`DecodeError::Truncated`] if the bytes end before the slice does.
+    /// * [`DecodeError::Corrupted`] if the header is not a valid encoding of the length or the
+    ///   padding is not zeroed.
+    pub fn parse(bytes: &'a [u8]) -> Result<(Self, &'a [u8]), DecodeError> {
+        let align = mem::align_of::<T>();
+        if !(bytes.as_ptr() as usize).is_multiple_of(align) {
+            return Err(DecodeError::Misaligned);
+        }
+
+        let len_off = mem::size_of::<H>();
+        if bytes.len() < len_off {
+            return Err(DecodeError::Truncated);
+        }
+        // Any bit pattern is a valid H, but it may be misaligned.
+        let header = unsafe { ptr::read_unaligned(bytes.as_ptr().cast::<H>()) };
+        let extra = header.extra_len().ok_or(DecodeError::Corrupted)?;
+        if bytes.len() < len_off + extra {
+            return Err(DecodeError::Truncated);
+        }
+        let len = unsafe { header.decode_len(bytes.as_ptr().add(len_off)) };
+        // Only the shortest encoding is valid
+        if H::extra_needed(len).ok() != Some(extra) {
+            return Err(DecodeError::Corrupted);
+        }
+        // Don't let a huge length overflow the layout computation
+        match len.checked_mul(mem::size_of::<T>()) {
+            Some(size) if size <= bytes.len() => (),
+            _ => return Err(DecodeError::Truncated),
+        }
+        let (layout, _, data_off) =
+            OwnedSlice::<T, H>::layout_and_offsets(len).map_err(|_| DecodeError::Corrupted)?;
+        let end = layout.size();
+        let record = record_size::<T>(end);
+        if bytes.len() < record {
+            return Err(DecodeError::Truncated);
+        }
+        let padding = bytes[len_off + extra..data_off]
+            .iter()
+            .chain(&bytes[end..record]);
+        if padding.into_iter().any(|&b| b != 0) {
+            return Err(DecodeError::Corrupted);
+        }
+
+        // The data offset is a multiple of the alignment of T and any bit pattern is a valid T.
+        let data = unsafe { slice::from_raw_parts(bytes.as_ptr().add(data_off).cast(), len) };
+        let slice = SliceRef {
+            data,
+            _header: PhantomData,
+        };
+        Ok((slice, &bytes[record..]))
+    }
+}
+
+impl<'a, T, H> SliceRef<'a, T, H> {
+    /// Returns the borrowed data, with the lifetime of the original bytes.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.data
+    }
+}
+
+impl<T, H> Clone for SliceRef<'_, T, H> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, H> Copy for SliceRef<'_, T, H> {}
+
+impl<T: PartialEq, H> PartialEq for SliceRef<'_, T, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl<T: Eq, H> Eq for SliceRef<'_, T, H> {}
+
+impl<T, H> Deref for SliceRef<'_, T, H> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.data
+    }
+}
+
+impl<T: Debug, H> Debug for SliceRef<'_, T, H> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        Debug::fmt(self.data, fmt)
+    }
+}
+
+// Both are a single byte and any value is valid (the invalid encodings are rejected by
+// extra_len), so they can be read directly from the bytes.
+unsafe impl Zeroable for BoxHeader {}
+unsafe impl AnyBitPattern for BoxHeader {}
+unsafe impl Zeroable for ByteHeader {}
+unsafe impl AnyBitPattern for ByteHeader {}
+
+/// The size of a written slice ending at `end`, padded so the next one stays aligned.
+fn record_size<T>(end: usize) -> usize {
+    end.next_multiple_of(mem::align_of::<T>())
+}
+
+impl<T, H> OwnedSlice<T, H>
+where
+    H: Header + AnyBitPattern,
+{
+    /// Appends the slice to the buffer, to be borrowed back by [`SliceRef::parse`].
+    ///
+    /// The bytes are laid out the same way as the allocation of the slice and padded with zeroes
+    /// to a multiple of the alignment of `T`. The data are written in the native byte order. The
+    /// slice needs to be parsed with the same header (see [`SliceRef`]).
+    ///
+    /// Unlike [`raw_heap_bytes`][OwnedSlice::raw_heap_bytes], the empty slice writes its header
+    /// too, so it can be parsed back.
+    pub fn write_to(&self, buf: &mut Vec<u8>)
+    where
+        T: NoUninit,
+    {
+        let len = self.len();
+        let (layout, len_off, data_off) =
+            Self::layout_and_offsets(len).expect("Already have layout for this size");
+        let start = buf.len();
+        let record = record_size::<T>(layout.size());
+        buf.resize(start + data_off, 0);
+        unsafe {
+            let head = buf.as_mut_ptr().add(start);
+            let header = H::encode_len(len, head.add(len_off));
+            ptr::write_unaligned(head.cast::<H>(), header);
+        }
+        debug_assert!(len_off + H::extra_needed(len).unwrap() <= data_off);
+        buf.extend_from_slice(::bytemuck::cast_slice(self));
+        buf.resize(start + record, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::mock::ShortMockHeader;
+    use core::ops::Deref;
+
+    use crate::{ArcHeader, BoxHeader};
+
+    #[test]
+    fn u32_to_bytes() {
+        let s = OwnedSlice::<u32, ArcHeader>::new(&[0x0403_0201, 0xdead_beef]).unwrap();
+        let bytes = s.cast_slice::<u8>();
+        let mut expected = [0u8; 8];
+        expected[..4].copy_from_slice(&0x0403_0201u32.to_le_bytes());
+        expected[4..].copy_from_slice(&0xdead_beefu32.to_le_bytes());
+        if cfg!(target_endian = "little") {
+            assert_eq!(&[1, 2, 3, 4, 0xef, 0xbe, 0xad, 0xde], bytes);
+        }
+        assert_eq!(&expected, bytes);
+        assert_eq!(s.as_ptr().cast::<u8>(), bytes.as_ptr());
+    }
+
+    #[test]
+    fn bytes_to_wider() {
+        let s = OwnedSlice::<u8>::new(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        // The data is right after a single byte of header. The allocation of bytes is not required
+        // to be aligned for u16, so the data may end up either way.
+        if (s.as_ptr() as usize).is_multiple_of(2) {
+            assert_eq!(4, s.try_cast_slice::<u16>().unwrap().len());
+        } else {
+            assert!(matches!(
+                s.try_cast_slice::<u16>(),
+                Err(PodCastError::TargetAlignmentGreaterAndInputNotAligned)
+            ));
+        }
+
+        let s = OwnedSlice::<u16>::new(&[1, 2, 3]).unwrap();
+        assert!(matches!(
+            s.try_cast_slice::<u32>(),
+            Err(PodCastError::TargetAlignmentGreaterAndInputNotAligned
+                | PodCastError::OutputSliceWouldHaveSlop)
+        ));
+        let s = OwnedSlice::<u64>::new(&[1, 2]).unwrap();
+        assert_eq!(4, s.try_cast_slice::<u32>().unwrap().len());
+    }
+
+    #[test]
+    fn empty() {
+        let s = OwnedSlice::<u32>::default();
+        assert!(s.cast_slice::<u8>().is_empty());
+        assert!(s.cast_slice::<u64>().is_empty());
+    }
+
+    /// A view of the bytes as records, if aligned.
+    #[test]
+    fn bytes_as_records() {
+        let records = [0x0403_0201u32, 0xdead_beef];
+        let mut bytes = ::bytemuck::bytes_of(&records[0]).to_vec();
+        bytes.extend_from_slice(::bytemuck::bytes_of(&records[1]));
+
+        let aligned = OwnedSlice::<u8, ArcHeader>::new(&bytes).unwrap();
+        assert_eq!(&records, aligned.cast_slice::<u32>());
+        let misaligned = OwnedSlice::<u8, BoxHeader>::new(&bytes).unwrap();
+        // The allocation of bytes is not required to be aligned, so it may be by accident.
+        if !(misaligned.as_ptr() as usize).is_multiple_of(4) {
+            assert!(misaligned.try_cast_slice::<u32>().is_err());
+        }
+
+        let words = aligned.try_cast::<u32>().unwrap();
+        assert_eq!(records, words[..]);
+        let back = words.try_cast::<u8>().unwrap();
+        assert_eq!(bytes, back.deref());
+        assert_eq!(::bytemuck::bytes_of(&records[1]), &back[4..]);
+
+        let (misaligned, err) = misaligned.try_cast::<u32>().unwrap_err();
+        assert_eq!(CastError::Layout, err);
+        assert_eq!(bytes, misaligned.deref());
+    }
+
+    #[test]
+    fn try_cast_errors() {
+        let s = OwnedSlice::<u8, ArcHeader>::new(&[1, 2, 3]).unwrap();
+        let (s, err) = s.try_cast::<u32>().unwrap_err();
+        assert_eq!(CastError::Size, err);
+
+        // Shared data can't change their length, but can keep it
+        let bytes = OwnedSlice::<u8, ArcHeader>::new(&[0; 4]).unwrap();
+        let bytes2 = bytes.clone();
+        let (bytes, err) = bytes.try_cast::<u32>().unwrap_err();
+        assert_eq!(CastError::Shared, err);
+        let signed = bytes.try_cast::<i8>().unwrap();
+        assert_eq!(&[0; 4], signed.deref());
+        drop(bytes2);
+        assert_eq!(3, s.len());
+
+        // The length in the header is rewritten, with the weak references there'd be two
+        let bytes = OwnedSlice::<u8, ArcHeader>::new(&[0; 4]).unwrap();
+        let weak = OwnedSlice::downgrade(&bytes);
+        let (bytes, err) = bytes.try_cast::<u32>().unwrap_err();
+        assert_eq!(CastError::Shared, err);
+        drop(weak);
+        assert_eq!([0], bytes.try_cast::<u32>().unwrap()[..]);
+
+        // More extra bytes move the data
+        let long = OwnedSlice::<u8, ArcHeader>::new(&[0; 256]).unwrap();
+        assert_eq!(CastError::Layout, long.try_cast::<u32>().unwrap_err().1);
+
+        let empty = OwnedSlice::<u8>::default().try_cast::<u64>().unwrap();
+        assert!(empty.is_empty());
+        let unit = OwnedSlice::<()>::new(&[(), ()]).unwrap();
+        assert_eq!(CastError::Size, unit.try_cast::<u8>().unwrap_err().1);
+    }
+
+    #[test]
+    fn zeroed() {
+        let s = OwnedSlice::<u64, BoxHeader>::zeroed(100).unwrap();
+        assert!(s.iter().all(|&v| v == 0));
+        let s = OwnedSlice::<u32, ArcHeader>::zeroed(1000).unwrap();
+        assert_eq!(1000, s.len());
+        assert!(s.iter().all(|&v| v == 0));
+        let s = OwnedSlice::<f64>::zeroed(3).unwrap();
+        assert_eq!(&[0.0; 3], s.deref());
+
+        let empty = OwnedSlice::<u32>::zeroed(0).unwrap();
+        assert_eq!(OwnedSlice::<u32>::default().as_ptr(), empty.as_ptr());
+        OwnedSlice::<u8, ShortMockHeader>::zeroed(5).unwrap_err();
+    }
+
+    #[test]
+    fn write_and_parse() {
+        let wide = OwnedSlice::<u64>::new(&[u64::MAX, 7]).unwrap();
+        let nums = OwnedSlice::<u32>::new(&[1, 2, 3]).unwrap();
+        let bytes = OwnedSlice::<u8>::new(b"Hello").unwrap();
+        let long = OwnedSlice::<u8>::new(&[42; 300]).unwrap();
+
+        // From the most aligned types, so all the slices start aligned
+        let mut buf = Vec::new();
+        wide.write_to(&mut buf);
+        OwnedSlice::<u64>::default().write_to(&mut buf);
+        nums.write_to(&mut buf);
+        nums.write_to(&mut buf);
+        bytes.write_to(&mut buf);
+        long.write_to(&mut buf);
+        let short = OwnedSlice::<u8, ByteHeader>::new(&[1, 2, 3, 4]).unwrap();
+        short.write_to(&mut buf);
+
+        // The parsing needs the buffer to be aligned
+        let mut aligned = vec![0u64; buf.len().div_ceil(8)];
+        let aligned: &mut [u8] = ::bytemuck::cast_slice_mut(&mut aligned);
+        let aligned = &mut aligned[..buf.len()];
+        aligned.copy_from_slice(&buf);
+
+        let (s, rest) = SliceRef::<u64>::parse(aligned).unwrap();
+        assert_eq!(wide.deref(), s.deref());
+        let (s, rest) = SliceRef::<u64>::parse(rest).unwrap();
+        assert!(s.is_empty());
+        let (s, rest) = SliceRef::<u32>::parse(rest).unwrap();
+        assert_eq!(&[1, 2, 3], s.deref());
+        let (s, rest) = SliceRef::<u32>::parse(rest).unwrap();
+        assert_eq!(nums.deref(), s.deref());
+        let (s, rest) = SliceRef::<u8>::parse(rest).unwrap();
+        assert_eq!(b"Hello", s.deref());
+        let (s, rest) = SliceRef::<u8>::parse(rest).unwrap();
+        assert_eq!(long.deref(), s.as_slice());
+        let (s, rest) = SliceRef::<u8, ByteHeader>::parse(rest).unwrap();
+        assert_eq!(&[1, 2, 3, 4], s.deref());
+        assert!(rest.is_empty());
+
+        // A byte slice followed by something more aligned
+        let mut buf = Vec::new();
+        bytes.write_to(&mut buf);
+        nums.write_to(&mut buf);
+        let aligned = &mut aligned[..buf.len()];
+        aligned.copy_from_slice(&buf);
+        let (_, rest) = SliceRef::<u8>::parse(aligned).unwrap();
+        assert!(matches!(
+            SliceRef::<u32>::parse(rest).unwrap_err(),
+            DecodeError::Misaligned
+        ));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut aligned = [0u64; 4];
+        let aligned: &mut [u8] = ::bytemuck::cast_slice_mut(&mut aligned);
+        let mut buf = Vec::new();
+        OwnedSlice::<u32>::new(&[1, 2]).unwrap().write_to(&mut buf);
+        assert_eq!(12, buf.len());
+        aligned[..12].copy_from_slice(&buf);
+
+        assert!(matches!(
+            SliceRef::<u32>::parse(&aligned[1..]).unwrap_err(),
+            DecodeError::Misaligned
+        ));
+        assert!(matches!(
+            SliceRef::<u32>::parse(&aligned[..0]).unwrap_err(),
+            DecodeError::Truncated
+        ));
+        for end in 1..12 {
+            let e = SliceRef::<u32>::parse(&aligned[..end]).unwrap_err();
+            assert!(matches!(e, DecodeError::Truncated), "{}", end);
+        }
+        // Garbage in the padding
+        aligned[2] = 1;
+        assert!(matches!(
+            SliceRef::<u32>::parse(&aligned[..12]).unwrap_err(),
+            DecodeError::Corrupted
+        ));
+        // A length that doesn't fit
+        aligned[0] = 0xff;
+        assert!(matches!(
+            SliceRef::<u32>::parse(&aligned[..]).unwrap_err(),
+            DecodeError::Truncated
+        ));
+    }
+}
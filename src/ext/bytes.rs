@@ -0,0 +1,182 @@
+//! Support for the [`bytes`](https://crates.io/crates/bytes) crate.
+
+use ::bytes::{Buf, Bytes};
+
+use crate::{BoxHeader, Header, OwnedSlice};
+
+/// A reading cursor over an [`OwnedSlice<u8>`][OwnedSlice], implementing [`Buf`].
+///
+/// The slice itself is fixed and immutable, so the position of the cursor is kept next to it.
+///
+/// # Examples
+///
+/// ```rust
+/// use bytes::Buf;
+/// use squash::{BufOwnedSlice, OwnedSlice};
+///
+/// let s: OwnedSlice<u8> = OwnedSlice::new(b"\x00\x2aHello").unwrap();
+/// let mut buf = BufOwnedSlice::new(s);
+/// assert_eq!(42, buf.get_u16());
+/// assert_eq!(b"Hello", buf.chunk());
+/// ```
+pub struct BufOwnedSlice<H = BoxHeader>
+where
+    H: Header,
+{
+    inner: OwnedSlice<u8, H>,
+    pos: usize,
+}
+
+impl<H> BufOwnedSlice<H>
+where
+    H: Header,
+{
+    /// Creates the cursor at the start of the slice.
+    pub fn new(inner: OwnedSlice<u8, H>) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    /// The position of the cursor (the number of bytes already consumed).
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Provides the whole slice, including the already consumed bytes.
+    #[inline]
+    pub fn get_ref(&self) -> &OwnedSlice<u8, H> {
+        &self.inner
+    }
+
+    /// Returns the whole slice back, dropping the position.
+    #[inline]
+    pub fn into_inner(self) -> OwnedSlice<u8, H> {
+        self.inner
+    }
+}
+
+impl<H> From<OwnedSlice<u8, H>> for BufOwnedSlice<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn from(inner: OwnedSlice<u8, H>) -> Self {
+        Self::new(inner)
+    }
+}
+
+impl<H> Buf for BufOwnedSlice<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn remaining(&self) -> usize {
+        self.inner.len() - self.pos
+    }
+
+    #[inline]
+    fn chunk(&self) -> &[u8] {
+        &self.inner[self.pos..]
+    }
+
+    #[inline]
+    fn advance(&mut self, cnt: usize) {
+        assert!(
+            cnt <= self.remaining(),
+            "Advancing past the end of the buffer"
+        );
+        self.pos += cnt;
+    }
+}
+
+/// Keeps the slice alive inside the [`Bytes`].
+struct Owner<H: Header>(OwnedSlice<u8, H>);
+
+impl<H> AsRef<[u8]> for Owner<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Turns the slice into [`Bytes`] without copying the data.
+impl<H> From<OwnedSlice<u8, H>> for Bytes
+where
+    H: Header + 'static,
+    OwnedSlice<u8, H>: Send,
+{
+    fn from(slice: OwnedSlice<u8, H>) -> Self {
+        Bytes::from_owner(Owner(slice))
+    }
+}
+
+/// Turns the not yet consumed part of the buffer into [`Bytes`] without copying the data.
+impl<H> From<BufOwnedSlice<H>> for Bytes
+where
+    H: Header + 'static,
+    OwnedSlice<u8, H>: Send,
+{
+    fn from(buf: BufOwnedSlice<H>) -> Self {
+        Bytes::from(buf.inner).slice(buf.pos..)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ArcHeader;
+
+    const REQUEST: &[u8] = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+
+    #[test]
+    fn read_http() {
+        let mut buf = BufOwnedSlice::new(OwnedSlice::<u8>::new(REQUEST).unwrap());
+        assert_eq!(REQUEST.len(), buf.remaining());
+        let mut method = [0; 3];
+        buf.copy_to_slice(&mut method);
+        assert_eq!(b"GET", &method);
+        assert_eq!(b' ', buf.get_u8());
+        assert_eq!(4, buf.position());
+        let line_end = buf.chunk().iter().position(|&b| b == b'\r').unwrap();
+        assert_eq!(b"/ HTTP/1.1", &buf.chunk()[..line_end]);
+        buf.advance(line_end + 2);
+        assert_eq!(b"Host: example.com\r\n\r\n", buf.chunk());
+        buf.advance(buf.remaining());
+        assert!(!buf.has_remaining());
+        assert_eq!(REQUEST, &buf.into_inner()[..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn advance_past_end() {
+        let mut buf = BufOwnedSlice::new(OwnedSlice::<u8>::new(b"abc").unwrap());
+        buf.advance(4);
+    }
+
+    #[test]
+    fn empty() {
+        let buf = BufOwnedSlice::new(OwnedSlice::<u8>::default());
+        assert_eq!(0, buf.remaining());
+        assert!(buf.chunk().is_empty());
+        assert!(Bytes::from(buf).is_empty());
+    }
+
+    #[test]
+    fn to_bytes() {
+        let s = OwnedSlice::<u8, ArcHeader>::new(REQUEST).unwrap();
+        let ptr = s.as_ptr();
+        let bytes = Bytes::from(s.clone());
+        // No copy was made
+        assert_eq!(ptr, bytes.as_ptr());
+        assert_eq!(REQUEST, &bytes[..]);
+
+        let mut buf = BufOwnedSlice::new(s);
+        buf.advance(4);
+        let bytes = Bytes::from(buf);
+        assert_eq!(&REQUEST[4..], &bytes[..]);
+        assert_eq!(ptr.wrapping_add(4), bytes.as_ptr());
+    }
+}
@@ -0,0 +1,92 @@
+use ::deepsize::{Context, DeepSizeOf};
+
+#[cfg(feature = "prefix")]
+use crate::prefix::PrefixTable;
+use crate::{Header, InlineStr, OwnedSlice, Str};
+
+/// Counts the whole heap allocation (including the header) and the children of all the elements.
+///
+/// Note that shared slices are counted in full by each of the owners (there's no way to register
+/// them in the [`Context`] for deduplication).
+impl<T, H> DeepSizeOf for OwnedSlice<T, H>
+where
+    T: DeepSizeOf,
+    H: Header,
+{
+    fn deep_size_of_children(&self, context: &mut Context) -> usize {
+        self.heap_size()
+            + self
+                .iter()
+                .map(|child| child.deep_size_of_children(context))
+                .sum::<usize>()
+    }
+}
+
+impl<H> DeepSizeOf for Str<H>
+where
+    H: Header,
+{
+    fn deep_size_of_children(&self, _context: &mut Context) -> usize {
+        self.0.heap_size()
+    }
+}
+
+impl<H> DeepSizeOf for InlineStr<H>
+where
+    H: Header,
+{
+    fn deep_size_of_children(&self, _context: &mut Context) -> usize {
+        self.heap_size()
+    }
+}
+
+#[cfg(feature = "prefix")]
+impl<H> DeepSizeOf for PrefixTable<H>
+where
+    H: Header,
+{
+    fn deep_size_of_children(&self, _context: &mut Context) -> usize {
+        self.heap_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::mem;
+
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let s = OwnedSlice::<String>::default();
+        assert_eq!(mem::size_of::<usize>(), s.deep_size_of());
+        let s: Str = Str::new("").unwrap();
+        assert_eq!(mem::size_of::<usize>(), s.deep_size_of());
+    }
+
+    #[test]
+    fn strings() {
+        let hello = "Hello".to_owned();
+        let world = "World!".to_owned();
+        let s = OwnedSlice::<String>::new(&[hello, world]).unwrap();
+        // The 1-byte header padded to the alignment of String, two strings and the data inside
+        // them.
+        let heap = mem::align_of::<String>() + 2 * mem::size_of::<String>() + 5 + 6;
+        assert_eq!(mem::size_of::<usize>() + heap, s.deep_size_of());
+    }
+
+    #[test]
+    fn str() {
+        let s: Str = Str::new("Hello").unwrap();
+        assert_eq!(mem::size_of::<usize>() + 1 + 5, s.deep_size_of());
+    }
+
+    #[test]
+    fn inline_str() {
+        let s: InlineStr = InlineStr::new("hello").unwrap();
+        assert_eq!(mem::size_of::<usize>(), s.deep_size_of());
+        // The header padded to 2 bytes
+        let s: InlineStr = InlineStr::new("hello world").unwrap();
+        assert_eq!(mem::size_of::<usize>() + 2 + 11, s.deep_size_of());
+    }
+}
@@ -0,0 +1,69 @@
+//! Integrations with other crates, behind feature flags.
+
+#[cfg(any(feature = "arbitrary", feature = "proptest", feature = "quickcheck"))]
+use alloc::string::String;
+#[cfg(any(feature = "arbitrary", feature = "proptest", feature = "quickcheck"))]
+use alloc::vec::Vec;
+
+#[cfg(any(feature = "arbitrary", feature = "proptest", feature = "quickcheck"))]
+use crate::{Header, OwnedSlice, Str};
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(all(feature = "arc-swap", target_has_atomic = "32"))]
+mod arc_swap;
+#[cfg(feature = "bumpalo")]
+pub(crate) mod bumpalo;
+#[cfg(feature = "bytemuck")]
+pub(crate) mod bytemuck;
+#[cfg(feature = "bytes")]
+pub(crate) mod bytes;
+#[cfg(feature = "deepsize")]
+mod deepsize;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "quickcheck")]
+mod quickcheck;
+#[cfg(feature = "rayon")]
+mod rayon;
+#[cfg(feature = "rkyv")]
+mod rkyv;
+#[cfg(feature = "zerocopy")]
+pub(crate) mod zerocopy;
+
+/// Shortens the vector so the header is able to encode its length.
+///
+/// The generators produce unbounded sizes, but we don't want to fail on these. This practically
+/// never happens, so the simple halving is good enough.
+#[cfg(any(feature = "arbitrary", feature = "proptest", feature = "quickcheck"))]
+fn fit_vec<T, H: Header>(v: &mut Vec<T>) {
+    while H::extra_needed(v.len()).is_err() {
+        v.truncate(v.len() / 2);
+    }
+}
+
+/// Like [`fit_vec`], but for strings (keeps the char boundaries).
+#[cfg(any(feature = "arbitrary", feature = "proptest", feature = "quickcheck"))]
+fn fit_string<H: Header>(s: &mut String) {
+    while H::extra_needed(s.len()).is_err() {
+        let mut len = s.len() / 2;
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        s.truncate(len);
+    }
+}
+
+/// Creates the slice from a generated vector, truncated to fit the header.
+#[cfg(any(feature = "arbitrary", feature = "proptest", feature = "quickcheck"))]
+fn slice_from_vec<T, H: Header>(mut v: Vec<T>) -> OwnedSlice<T, H> {
+    fit_vec::<T, H>(&mut v);
+    OwnedSlice::from_iter_len(v.len(), v).expect("Fitted to the header")
+}
+
+/// Creates the string from a generated one, truncated to fit the header.
+#[cfg(any(feature = "arbitrary", feature = "proptest", feature = "quickcheck"))]
+fn str_from_string<H: Header>(mut s: String) -> Str<H> {
+    fit_string::<H>(&mut s);
+    Str::new(&s).expect("Fitted to the header")
+}
@@ -0,0 +1,126 @@
+//! Support for generating the data structures with [`proptest`](https://crates.io/crates/proptest).
+//!
+//! Apart from the strategy constructors in this module, the types implement the proptest's
+//! [`Arbitrary`] trait, so they can be generated by [`any`][::proptest::arbitrary::any]. Both
+//! delegate to the corresponding strategies for [`Vec`] and [`String`].
+//!
+//! Lengths too long for the header are never generated (they are truncated).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use ::proptest::arbitrary::{any_with, Arbitrary};
+use ::proptest::collection::{vec, SizeRange};
+use ::proptest::strategy::{Map, Strategy};
+
+use super::{slice_from_vec, str_from_string};
+use crate::{Header, OwnedSlice, Str};
+
+/// Creates a strategy generating slices of the given length with elements from the passed
+/// strategy.
+///
+/// # Examples
+///
+/// ```rust
+/// use proptest::prelude::*;
+/// use proptest::strategy::ValueTree;
+/// use proptest::test_runner::TestRunner;
+/// use squash::proptest::slice_strategy;
+/// use squash::BoxHeader;
+///
+/// let mut runner = TestRunner::default();
+/// let strategy = slice_strategy::<_, BoxHeader>(any::<u8>(), 0..10);
+/// let s = strategy.new_tree(&mut runner).unwrap().current();
+/// assert!(s.len() < 10);
+/// ```
+pub fn slice_strategy<T, H>(
+    element: T,
+    len: impl Into<SizeRange>,
+) -> impl Strategy<Value = OwnedSlice<T::Value, H>>
+where
+    T: Strategy,
+    H: Header,
+{
+    vec(element, len).prop_map(slice_from_vec)
+}
+
+/// Creates a strategy generating strings of the given length (in characters).
+pub fn str_strategy<H>(len: impl Into<SizeRange>) -> impl Strategy<Value = Str<H>>
+where
+    H: Header,
+{
+    vec(::proptest::char::any(), len).prop_map(|chars| str_from_string(chars.into_iter().collect()))
+}
+
+impl<T, H> Arbitrary for OwnedSlice<T, H>
+where
+    T: Arbitrary,
+    H: Header,
+{
+    type Parameters = <Vec<T> as Arbitrary>::Parameters;
+    type Strategy = Map<<Vec<T> as Arbitrary>::Strategy, fn(Vec<T>) -> Self>;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        any_with::<Vec<T>>(args).prop_map(slice_from_vec)
+    }
+}
+
+impl<H> Arbitrary for Str<H>
+where
+    H: Header,
+{
+    type Parameters = <String as Arbitrary>::Parameters;
+    type Strategy = Map<<String as Arbitrary>::Strategy, fn(String) -> Self>;
+
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        any_with::<String>(args).prop_map(str_from_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Deref;
+
+    use ::proptest::prelude::*;
+
+    use super::*;
+    use crate::{ArcHeader, BoxHeader, RcHeader};
+
+    proptest! {
+        #[test]
+        fn slice_round_trip(s in slice_strategy::<_, BoxHeader>(any::<u16>(), 0..200)) {
+            prop_assert!(s.len() < 200);
+            let copy = OwnedSlice::<u16, RcHeader>::new(&s).unwrap();
+            prop_assert_eq!(s.deref(), copy.deref());
+        }
+
+        #[test]
+        fn str_round_trip(s in str_strategy::<BoxHeader>(0..100)) {
+            prop_assert!(s.chars().count() < 100);
+            let copy: Str<RcHeader> = Str::new(&s).unwrap();
+            prop_assert_eq!(s.deref(), copy.deref());
+        }
+
+        #[test]
+        fn any_slice(s in any::<OwnedSlice<u8, RcHeader>>()) {
+            let copy = s.clone();
+            prop_assert_eq!(s.deref(), copy.deref());
+        }
+
+        #[test]
+        fn any_str(s in any::<Str>()) {
+            let copy: Str<RcHeader> = s.clone().convert().unwrap();
+            prop_assert_eq!(s.deref(), copy.deref());
+        }
+
+        /// The length stored in the header matches the data.
+        #[test]
+        fn invariants(s in any::<OwnedSlice<String, ArcHeader>>(), st in any::<Str<RcHeader>>()) {
+            prop_assert_eq!(s.len(), s.deref().len());
+            prop_assert_eq!(s.is_empty(), s.iter().next().is_none());
+            prop_assert_eq!(st.len(), st.deref().len());
+            prop_assert_eq!(st.is_empty(), st.deref().is_empty());
+            prop_assert!(core::str::from_utf8(st.as_bytes()).is_ok());
+        }
+    }
+}
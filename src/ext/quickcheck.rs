@@ -0,0 +1,112 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use ::quickcheck::{Arbitrary, Gen};
+
+use super::{slice_from_vec, str_from_string};
+use crate::{CloneStrategy, Header, OwnedSlice, Str};
+
+/// Generates and shrinks the slice the same way as a [`Vec`] would be.
+///
+/// Slices too long for the header are truncated.
+impl<T, H> Arbitrary for OwnedSlice<T, H>
+where
+    T: Arbitrary,
+    H: Header + 'static,
+    H::Ownership: CloneStrategy<T>,
+{
+    fn arbitrary(g: &mut Gen) -> Self {
+        slice_from_vec(Vec::arbitrary(g))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(self.to_vec().shrink().map(slice_from_vec))
+    }
+}
+
+/// Generates and shrinks the string the same way as a [`String`] would be.
+///
+/// Strings too long for the header are truncated.
+impl<H> Arbitrary for Str<H>
+where
+    H: Header + 'static,
+{
+    fn arbitrary(g: &mut Gen) -> Self {
+        str_from_string(String::arbitrary(g))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let s: &str = self;
+        Box::new(String::from(s).shrink().map(str_from_string))
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use core::ops::Deref;
+
+    use ::quickcheck::{QuickCheck, TestResult};
+
+    use super::*;
+    use crate::{ArcHeader, BoxHeader, RcHeader};
+
+    fn len_matches(v: Vec<u16>) -> bool {
+        let s = OwnedSlice::<u16, RcHeader>::new(&v).unwrap();
+        s.len() == v.len() && s.deref() == v.as_slice()
+    }
+
+    fn invariants(s: OwnedSlice<String, ArcHeader>, st: Str<BoxHeader>) -> bool {
+        s.len() == s.deref().len() && st.len() == st.deref().len()
+    }
+
+    #[test]
+    fn properties() {
+        let tests = if cfg!(miri) { 5 } else { 100 };
+        QuickCheck::new()
+            .tests(tests)
+            .quickcheck(len_matches as fn(Vec<u16>) -> bool);
+        QuickCheck::new()
+            .tests(tests)
+            .quickcheck(invariants as fn(OwnedSlice<String, ArcHeader>, Str<BoxHeader>) -> bool);
+    }
+
+    #[test]
+    fn empty() {
+        // The size of 1 generates only empty vectors.
+        let s = OwnedSlice::<u8>::arbitrary(&mut Gen::new(1));
+        assert_eq!(OwnedSlice::<u8>::default(), s);
+        assert_eq!(OwnedSlice::<u8>::default().as_ptr(), s.as_ptr());
+    }
+
+    /// Shrinking goes through the same values as of the vector.
+    #[test]
+    fn shrink() {
+        let v = vec![5u8, 0, 200];
+        let s = OwnedSlice::<u8>::new(&v).unwrap();
+        let expected = v.shrink().collect::<Vec<_>>();
+        let shrunk = s.shrink().map(|s| s.to_vec()).collect::<Vec<_>>();
+        assert_eq!(expected, shrunk);
+
+        let st: Str = Str::new("ab").unwrap();
+        let expected = String::from("ab").shrink().collect::<Vec<_>>();
+        let shrunk = st.shrink().map(|s| String::from(&*s)).collect::<Vec<_>>();
+        assert_eq!(expected, shrunk);
+    }
+
+    /// A failing property gets shrunk to a minimal slice.
+    #[test]
+    fn minimal_failure() {
+        fn no_big(s: OwnedSlice<u8, RcHeader>) -> TestResult {
+            TestResult::from_bool(s.iter().all(|&b| b < 200))
+        }
+        let result = std::panic::catch_unwind(|| {
+            QuickCheck::new()
+                .tests(1000)
+                .quickcheck(no_big as fn(OwnedSlice<u8, RcHeader>) -> TestResult)
+        });
+        let msg = result.unwrap_err();
+        let msg = msg.downcast_ref::<String>().unwrap();
+        assert!(msg.contains("[200]"), "{}", msg);
+    }
+}
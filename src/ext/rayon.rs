@@ -0,0 +1,233 @@
+//! Support for the [`rayon`](https://crates.io/crates/rayon) crate.
+//!
+//! The slices have fixed length, so there's no `ParallelExtend` or `FromParallelIterator` (which
+//! can't report the [`TooLong`] error). Use [`OwnedSlice::from_par_iter`] instead.
+
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::{ptr, slice};
+
+use ::rayon::iter::plumbing::{Consumer, Folder, Reducer};
+use ::rayon::iter::{IndexedParallelIterator, IntoParallelIterator};
+use ::rayon::slice::Iter;
+
+use crate::slice::Builder;
+use crate::{Header, OwnedSlice, TooLong};
+
+impl<'a, T, H> IntoParallelIterator for &'a OwnedSlice<T, H>
+where
+    T: Sync + 'a,
+    H: Header,
+{
+    type Item = &'a T;
+    type Iter = Iter<'a, T>;
+
+    fn into_par_iter(self) -> Iter<'a, T> {
+        (**self).into_par_iter()
+    }
+}
+
+/// The consumer writing the elements into the slice.
+///
+/// Each split gets its own disjoint part of the target, so a worker can't write outside of it.
+struct Collect<'c, T> {
+    target: &'c mut [MaybeUninit<T>],
+}
+
+impl<'c, T> Consumer<T> for Collect<'c, T>
+where
+    T: Send + 'c,
+{
+    type Folder = Written<'c, T>;
+    type Reducer = Merge;
+    type Result = Written<'c, T>;
+
+    fn split_at(self, index: usize) -> (Self, Self, Merge) {
+        // Panics if the iterator splits beyond its length.
+        let (left, right) = self.target.split_at_mut(index);
+        (Collect { target: left }, Collect { target: right }, Merge)
+    }
+
+    fn into_folder(self) -> Written<'c, T> {
+        Written {
+            start: self.target.as_mut_ptr().cast(),
+            total: self.target.len(),
+            len: 0,
+            _target: PhantomData,
+        }
+    }
+
+    fn full(&self) -> bool {
+        false
+    }
+}
+
+/// A guard of the elements already written to the beginning of a target range.
+///
+/// If dropped (because some worker panicked), it drops the elements. On success, all the ranges
+/// are merged into one and forgotten.
+struct Written<'c, T> {
+    start: *mut T,
+    total: usize,
+    len: usize,
+    _target: PhantomData<&'c mut [T]>,
+}
+
+// It owns the written elements, sending it sends them.
+unsafe impl<T: Send> Send for Written<'_, T> {}
+
+impl<T> Folder<T> for Written<'_, T> {
+    type Result = Self;
+
+    fn consume(mut self, value: T) -> Self {
+        // The iterator may lie about its length, but that must not lead to UB.
+        assert!(
+            self.len < self.total,
+            "Parallel iterator longer than its len"
+        );
+        unsafe { ptr::write(self.start.add(self.len), value) };
+        self.len += 1;
+        self
+    }
+
+    fn complete(self) -> Self {
+        self
+    }
+
+    fn full(&self) -> bool {
+        false
+    }
+}
+
+/// Merges the written ranges of neighbouring splits.
+struct Merge;
+
+impl<'c, T> Reducer<Written<'c, T>> for Merge {
+    fn reduce(self, mut left: Written<'c, T>, mut right: Written<'c, T>) -> Written<'c, T> {
+        // Only a fully written range continues right into the next one. Otherwise the right one is
+        // dropped here and the final length check fails.
+        if left.len == left.total && left.start.wrapping_add(left.total) == right.start {
+            left.total += right.total;
+            left.len += mem::replace(&mut right.len, 0);
+        }
+        left
+    }
+}
+
+impl<T> Drop for Written<'_, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.start, self.len)) };
+    }
+}
+
+impl<T, H> OwnedSlice<T, H>
+where
+    H: Header,
+{
+    /// Creates a new owned slice from an indexed parallel iterator.
+    ///
+    /// The slice is allocated once, with the length reported by the iterator, and the worker
+    /// threads write their parts of it directly. If any of them panics, the already produced
+    /// elements are dropped, the memory is freed and the panic is propagated.
+    ///
+    /// # Errors
+    ///
+    /// If the length is more than the header can encode, [`TooLong`] is returned (and the
+    /// iterator is not run).
+    ///
+    /// # Panics
+    ///
+    /// If the iterator produces a different number of elements than its length, or if it
+    /// panics.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rayon::prelude::*;
+    /// use squash::OwnedSlice;
+    ///
+    /// let squares = (0..1000u32).into_par_iter().map(|i| u64::from(i * i));
+    /// let s: OwnedSlice<u64> = OwnedSlice::from_par_iter(squares).unwrap();
+    /// assert_eq!(998_001, s[999]);
+    /// ```
+    pub fn from_par_iter<I>(iter: I) -> Result<Self, TooLong>
+    where
+        I: IndexedParallelIterator<Item = T>,
+        T: Send,
+    {
+        let total = iter.len();
+        let mut builder = Builder::<T, H>::new(total)?;
+        let target: &mut [MaybeUninit<T>] = if total == 0 {
+            // The data pointer of an empty builder may be unaligned.
+            &mut []
+        } else {
+            unsafe { slice::from_raw_parts_mut(builder.data_ptr().cast(), total) }
+        };
+        let written = iter.drive(Collect { target });
+        assert_eq!(total, written.len, "Parallel iterator shorter than its len");
+        // All the elements are written, pass them to the builder.
+        mem::forget(written);
+        unsafe { builder.assume_initialized() };
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+
+    use ::rayon::prelude::*;
+
+    use super::*;
+    use crate::ArcHeader;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn same_as_sequential() {
+        let par: OwnedSlice<String, ArcHeader> =
+            OwnedSlice::from_par_iter((0..100_000).into_par_iter().map(|i| i.to_string())).unwrap();
+        let seq = (0..100_000).map(|i| i.to_string()).collect::<Vec<_>>();
+        assert_eq!(seq, &par[..]);
+
+        let sum: usize = par.par_iter().map(String::len).sum();
+        assert_eq!(seq.iter().map(String::len).sum::<usize>(), sum);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn empty() {
+        let s: OwnedSlice<u8> = OwnedSlice::from_par_iter(Vec::new().into_par_iter()).unwrap();
+        assert_eq!(OwnedSlice::<u8>::default().as_ptr(), s.as_ptr());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn too_long() {
+        use crate::header::mock::ShortMockHeader;
+
+        let iter = (0..5)
+            .into_par_iter()
+            .map(|_| -> u8 { panic!("Must not run") });
+        OwnedSlice::<u8, ShortMockHeader>::from_par_iter(iter).unwrap_err();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn panic_propagated() {
+        let counter = Arc::new(());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let iter = (0..10_000).into_par_iter().map(|i| {
+                if i == 7777 {
+                    panic!("Worker panic");
+                }
+                Arc::clone(&counter)
+            });
+            OwnedSlice::<Arc<()>>::from_par_iter(iter)
+        }));
+        let err = result.unwrap_err();
+        assert_eq!(Some(&"Worker panic"), err.downcast_ref::<&str>());
+        // All the elements produced before the panic are dropped
+        assert_eq!(1, Arc::strong_count(&counter));
+    }
+}
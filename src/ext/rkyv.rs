@@ -0,0 +1,164 @@
+//! Support for the [`rkyv`](https://crates.io/crates/rkyv) zero-copy serialization.
+//!
+//! The archived forms are the same as of [`Vec`][alloc::vec::Vec] and
+//! [`String`][alloc::string::String] (the [`ArchivedVec`] and [`ArchivedString`]). The squashed
+//! length encoding exists only on the heap, the archive uses its own relative pointer and length.
+//! That also means the archives are interchangeable with the ones of the standard containers.
+
+use ::rkyv::rancor::{Fallible, Source};
+use ::rkyv::ser::{Allocator, Writer};
+use ::rkyv::string::{ArchivedString, StringResolver};
+use ::rkyv::vec::{ArchivedVec, VecResolver};
+use ::rkyv::{Archive, Deserialize, DeserializeUnsized, Place, Serialize, SerializeUnsized};
+
+use crate::slice::Builder;
+use crate::{Header, OwnedSlice, Str};
+
+impl<T, H> Archive for OwnedSlice<T, H>
+where
+    T: Archive,
+    H: Header,
+{
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_slice(self, resolver, out);
+    }
+}
+
+impl<T, H, S> Serialize<S> for OwnedSlice<T, H>
+where
+    T: Serialize<S>,
+    H: Header,
+    S: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        ArchivedVec::<T::Archived>::serialize_from_slice(self, serializer)
+    }
+}
+
+/// Deserializes into a freshly allocated slice.
+///
+/// Fails with [`TooLong`][crate::TooLong] (wrapped in the deserializer's error) if the archived
+/// slice is longer than the header can encode.
+impl<T, H, D> Deserialize<OwnedSlice<T, H>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    H: Header,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<OwnedSlice<T, H>, D::Error> {
+        let mut builder = Builder::new(self.len()).map_err(D::Error::new)?;
+        for item in self.iter() {
+            builder.push(item.deserialize(deserializer)?);
+        }
+        Ok(builder.finish())
+    }
+}
+
+impl<H> Archive for Str<H>
+where
+    H: Header,
+{
+    type Archived = ArchivedString;
+    type Resolver = StringResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedString::resolve_from_str(self, resolver, out);
+    }
+}
+
+impl<H, S> Serialize<S> for Str<H>
+where
+    H: Header,
+    S: Fallible + ?Sized,
+    S::Error: Source,
+    str: SerializeUnsized<S>,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        ArchivedString::serialize_from_str(self, serializer)
+    }
+}
+
+/// Deserializes into a freshly allocated string.
+///
+/// Fails with [`TooLong`][crate::TooLong] (wrapped in the deserializer's error) if the archived
+/// string is longer than the header can encode.
+impl<H, D> Deserialize<Str<H>, D> for ArchivedString
+where
+    H: Header,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+    str: DeserializeUnsized<str, D>,
+{
+    fn deserialize(&self, _: &mut D) -> Result<Str<H>, D::Error> {
+        Str::new(self.as_str()).map_err(D::Error::new)
+    }
+}
+
+// The relative pointers of rkyv don't play well with the stacked borrows model miri uses by
+// default (they pass with -Zmiri-tree-borrows).
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use core::ops::Deref;
+
+    use ::rkyv::rancor::Error;
+    use ::rkyv::Archived;
+
+    use super::*;
+    use crate::{BoxHeader, RcHeader};
+
+    #[test]
+    fn slice_round_trip() {
+        for len in [0, 1, 63, 64, 1000] {
+            let s = OwnedSlice::<u16>::from_fn(len, |i| i as u16).unwrap();
+            let bytes = ::rkyv::to_bytes::<Error>(&s).unwrap();
+            let archived = ::rkyv::access::<ArchivedVec<Archived<u16>>, Error>(&bytes).unwrap();
+            assert_eq!(len, archived.len());
+            assert!(archived
+                .iter()
+                .zip(s.iter())
+                .all(|(a, b)| a.to_native() == *b));
+
+            let back: OwnedSlice<u16, RcHeader> =
+                ::rkyv::deserialize::<_, Error>(archived).unwrap();
+            assert_eq!(s.deref(), back.deref());
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let strings = ["hello".to_owned(), String::new(), "world".to_owned()];
+        let s = OwnedSlice::<String>::new(&strings).unwrap();
+        let bytes = ::rkyv::to_bytes::<Error>(&s).unwrap();
+        let archived = ::rkyv::access::<ArchivedVec<ArchivedString>, Error>(&bytes).unwrap();
+        assert_eq!("world", archived[2].as_str());
+        let back: OwnedSlice<String> = ::rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(&strings, back.deref());
+    }
+
+    #[test]
+    fn str_round_trip() {
+        for src in ["", "hello", "ěščř", &"long".repeat(100)] {
+            let s: Str = Str::new(src).unwrap();
+            let bytes = ::rkyv::to_bytes::<Error>(&s).unwrap();
+            let archived = ::rkyv::access::<ArchivedString, Error>(&bytes).unwrap();
+            assert_eq!(src, archived.as_str());
+            let back: Str<RcHeader> = ::rkyv::deserialize::<_, Error>(archived).unwrap();
+            assert_eq!(src, back.deref());
+        }
+    }
+
+    /// The archives are compatible with the ones of Vec.
+    #[test]
+    fn from_vec() {
+        let v = vec![1u32, 2, 3];
+        let bytes = ::rkyv::to_bytes::<Error>(&v).unwrap();
+        let archived = ::rkyv::access::<ArchivedVec<Archived<u32>>, Error>(&bytes).unwrap();
+        let s: OwnedSlice<u32, BoxHeader> = ::rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(&v[..], s.deref());
+    }
+}
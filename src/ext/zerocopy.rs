@@ -0,0 +1,197 @@
+//! Support for the [`zerocopy`](https://crates.io/crates/zerocopy) crate.
+//!
+//! The [`OwnedSlice`] itself is just a pointer, so it can't implement the zerocopy traits (and
+//! these are meant to be derived only anyway). Instead, it provides access to the bytes of the
+//! data part of its allocation (without the header) and construction from bytes.
+
+use alloc::fmt::{Display, Formatter, Result as FmtResult};
+use core::error::Error;
+use core::mem;
+
+use ::zerocopy::{AsBytes, FromBytes};
+
+use crate::{Header, OwnedSlice, TooLong};
+
+/// An error when creating the [`OwnedSlice`] from bytes.
+///
+/// See [`OwnedSlice::from_bytes`].
+#[derive(Clone, Debug)]
+pub enum FromBytesError {
+    /// The number of bytes is not a multiple of the element size.
+    Size,
+    /// There are too many elements for the header.
+    TooLong(TooLong),
+}
+
+impl Display for FromBytesError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            FromBytesError::Size => write!(fmt, "Not a multiple of the element size"),
+            FromBytesError::TooLong(e) => Display::fmt(e, fmt),
+        }
+    }
+}
+
+impl Error for FromBytesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FromBytesError::Size => None,
+            FromBytesError::TooLong(e) => Some(e),
+        }
+    }
+}
+
+impl From<TooLong> for FromBytesError {
+    fn from(e: TooLong) -> Self {
+        FromBytesError::TooLong(e)
+    }
+}
+
+impl<T, H> OwnedSlice<T, H>
+where
+    H: Header,
+{
+    /// Provides the data as bytes.
+    ///
+    /// Only the data is included, the header with the length is skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2]).unwrap();
+    /// assert_eq!(4, s.as_bytes().len());
+    /// ```
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8]
+    where
+        T: AsBytes,
+    {
+        AsBytes::as_bytes(&self[..])
+    }
+
+    /// Creates the slice by reinterpreting the bytes as elements.
+    ///
+    /// The bytes are copied into the new allocation, so they don't have to be aligned for `T`.
+    ///
+    /// # Errors
+    ///
+    /// [`FromBytesError::Size`] is returned if the number of bytes is not a multiple of the
+    /// element size and [`FromBytesError::TooLong`] if there are too many elements.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::from_bytes(&[1, 0, 2, 0]).unwrap();
+    /// assert_eq!(&[1u16.to_le(), 2u16.to_le()], &s[..]);
+    /// assert!(OwnedSlice::<u16>::from_bytes(&[1, 0, 2]).is_err());
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FromBytesError>
+    where
+        T: FromBytes,
+    {
+        let size = mem::size_of::<T>();
+        if size == 0 {
+            // There's no way to tell how many zero-sized elements the bytes contain.
+            return if bytes.is_empty() {
+                Ok(Self::default())
+            } else {
+                Err(FromBytesError::Size)
+            };
+        }
+        if !bytes.len().is_multiple_of(size) {
+            return Err(FromBytesError::Size);
+        }
+        let elements = bytes
+            .chunks_exact(size)
+            .map(|chunk| T::read_from(chunk).expect("Chunk of the element size"));
+        Ok(Self::from_iter_len(bytes.len() / size, elements)?)
+    }
+}
+
+impl<T> OwnedSlice<T>
+where
+    T: AsBytes + FromBytes,
+{
+    /// Provides the data as mutable bytes.
+    ///
+    /// As with [`as_bytes`][OwnedSlice::as_bytes], the header is not included. Any bytes written
+    /// form valid elements, as they implement [`FromBytes`].
+    #[inline]
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        AsBytes::as_bytes_mut(&mut self[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::zerocopy::{AsBytes, FromBytes, FromZeroes};
+
+    use super::*;
+    use crate::{ArcHeader, BoxHeader};
+
+    /// A header of some made-up network protocol.
+    #[derive(AsBytes, Clone, Copy, Debug, FromBytes, FromZeroes, PartialEq)]
+    #[repr(C)]
+    struct Packet {
+        kind: u8,
+        flags: u8,
+        port: u16,
+        addr: u32,
+    }
+
+    fn packets() -> Vec<Packet> {
+        (0..10)
+            .map(|i| Packet {
+                kind: i,
+                flags: 0xff - i,
+                port: 8000 + u16::from(i),
+                addr: 0x7f00_0001 + u32::from(i),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let packets = packets();
+        let bytes = packets.as_bytes();
+        // Offset by one, to make sure unaligned input works
+        let mut unaligned = vec![0u8];
+        unaligned.extend_from_slice(bytes);
+
+        let s = OwnedSlice::<Packet, ArcHeader>::from_bytes(&unaligned[1..]).unwrap();
+        assert_eq!(packets, &s[..]);
+        assert_eq!(bytes, s.as_bytes());
+        assert_eq!(s.as_ptr().cast::<u8>(), s.as_bytes().as_ptr());
+    }
+
+    #[test]
+    fn modify_bytes() {
+        let mut s = OwnedSlice::<Packet>::new(&packets()).unwrap();
+        s.as_bytes_mut()[8..].fill(0);
+        assert_eq!(packets()[0], s[0]);
+        assert!(s[1..].iter().all(|p| *p == Packet::new_zeroed()));
+    }
+
+    #[test]
+    fn bad_size() {
+        let bytes = [0u8; 17];
+        let e = OwnedSlice::<Packet>::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(e, FromBytesError::Size));
+        assert!(OwnedSlice::<Packet>::from_bytes(&bytes[..16]).is_ok());
+        assert!(OwnedSlice::<Packet>::from_bytes(&[]).unwrap().is_empty());
+        assert!(OwnedSlice::<(), BoxHeader>::from_bytes(&[]).is_ok());
+        assert!(OwnedSlice::<(), BoxHeader>::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn too_long() {
+        use crate::header::mock::ShortMockHeader;
+
+        let bytes = [0u8; 5];
+        OwnedSlice::<u8, ShortMockHeader>::from_bytes(&bytes[..4]).unwrap();
+        let e = OwnedSlice::<u8, ShortMockHeader>::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(e, FromBytesError::TooLong(_)));
+    }
+}
@@ -0,0 +1,476 @@
+//! Multiple slices inside a single allocation.
+//!
+//! Structures holding several short slices or strings pay for each of them separately ‒ a pointer
+//! on the stack and an allocation (with its overhead) on the heap. The [`SliceGroup`] packs a
+//! fixed number of slices into one allocation behind a single pointer.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use squash::group::SliceGroup;
+//!
+//! let person: SliceGroup<u8, 3> = SliceGroup::new([b"John", b"Smith", b""]).unwrap();
+//! assert_eq!(b"Smith", person.get(1));
+//! assert!(person.get(2).is_empty());
+//! assert_eq!(8, std::mem::size_of_val(&person));
+//! ```
+
+use alloc::alloc::{handle_alloc_error, Layout};
+use alloc::fmt::{Debug, Formatter, Result as FmtResult};
+use core::array;
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr::{self, NonNull};
+use core::slice;
+
+use crate::stats::{self, mem_alloc, mem_dealloc};
+use crate::{BoxHeader, Header, Ownership, TooLong};
+
+// The same trick as with the OwnedSlice ‒ a group of only empty slices doesn't allocate and
+// points here instead.
+static ZERO_SENTINEL: u8 = 0;
+
+/// A fixed number of slices stored in a single allocation.
+///
+/// The heap layout starts with the header `H`, which encodes the total number of elements (and
+/// holds the reference counts, if any). It is followed by the lengths of all the slices except the
+/// last one (that one is computed from the total), each encoded the same way as in [`BoxHeader`].
+/// Then all the data come one after another.
+///
+/// Accessing one of the slices needs to decode the lengths of all the previous ones, so this is
+/// meant for small `N`.
+///
+/// See the [module documentation][self] for an example.
+pub struct SliceGroup<T, const N: usize, H = BoxHeader>
+where
+    H: Header,
+{
+    header: NonNull<H>,
+    _data: PhantomData<T>,
+}
+
+/// Offsets inside the allocation.
+struct Offsets {
+    total: usize,
+    /// Offset of the encoded lengths of the slices.
+    lens: usize,
+    /// Offset of the data.
+    data: usize,
+    layout: Layout,
+}
+
+impl<T, const N: usize, H> SliceGroup<T, N, H>
+where
+    H: Header,
+{
+    fn offsets(lens: &[usize; N]) -> Result<Offsets, TooLong> {
+        let total = lens
+            .iter()
+            .try_fold(0usize, |total, len| total.checked_add(*len))
+            .ok_or_else(TooLong::overflow::<H>)?;
+        let mut len_bytes = H::extra_needed(total)?;
+        for len in &lens[..N.saturating_sub(1)] {
+            len_bytes += mem::size_of::<BoxHeader>() + BoxHeader::extra_needed(*len)?;
+        }
+        let too_long = |_| TooLong {
+            len: total,
+            max: H::MAX_LEN,
+        };
+        let (l1, len_off) = Layout::new::<H>()
+            .extend(Layout::array::<u8>(len_bytes).map_err(too_long)?)
+            .map_err(too_long)?;
+        let data_layout = Layout::array::<T>(total).map_err(too_long)?;
+        let (layout, data) = l1.extend(data_layout).map_err(too_long)?;
+        Ok(Offsets {
+            total,
+            lens: len_off + H::extra_needed(total)?,
+            data,
+            layout,
+        })
+    }
+
+    /// Creates the group by cloning the slices.
+    ///
+    /// # Errors
+    ///
+    /// If the total length is too long for the header or any of the slices is too long for the
+    /// [`BoxHeader`] encoding, [`TooLong`] is returned.
+    pub fn new(parts: [&[T]; N]) -> Result<Self, TooLong>
+    where
+        T: Clone,
+    {
+        let lens = parts.map(<[T]>::len);
+        let offsets = Self::offsets(&lens)?;
+        if offsets.total == 0 {
+            return Ok(Self::default());
+        }
+
+        unsafe {
+            let ptr = mem_alloc(offsets.layout);
+            if ptr.is_null() {
+                handle_alloc_error(offsets.layout);
+            }
+            let len_off = mem::size_of::<H>();
+            ptr::write(
+                ptr.cast::<H>(),
+                H::encode_len(offsets.total, ptr.add(len_off)),
+            );
+            let mut pos = ptr.add(offsets.lens);
+            for len in &lens[..N - 1] {
+                let header = BoxHeader::encode_len(*len, pos.add(mem::size_of::<BoxHeader>()));
+                let extra = header.extra_len().expect("BoxHeader knows its extra len");
+                ptr::write(pos.cast::<BoxHeader>(), header);
+                pos = pos.add(mem::size_of::<BoxHeader>() + extra);
+            }
+            debug_assert!(pos <= ptr.add(offsets.data));
+
+            // Drops the already cloned elements and frees the memory if cloning panics.
+            struct Guard<T> {
+                ptr: *mut u8,
+                data: *mut T,
+                layout: Layout,
+                initialized: usize,
+            }
+
+            impl<T> Drop for Guard<T> {
+                fn drop(&mut self) {
+                    unsafe {
+                        for i in 0..self.initialized {
+                            ptr::drop_in_place(self.data.add(i));
+                        }
+                        mem_dealloc(self.ptr, self.layout);
+                    }
+                }
+            }
+
+            let mut guard = Guard {
+                ptr,
+                data: ptr.add(offsets.data).cast::<T>(),
+                layout: offsets.layout,
+                initialized: 0,
+            };
+            for part in &parts {
+                for item in *part {
+                    ptr::write(guard.data.add(guard.initialized), item.clone());
+                    guard.initialized += 1;
+                }
+            }
+            mem::forget(guard);
+
+            Ok(Self {
+                header: NonNull::new_unchecked(ptr.cast()),
+                _data: PhantomData,
+            })
+        }
+    }
+
+    #[inline]
+    fn is_sentinel(&self) -> bool {
+        ptr::eq(self.header.as_ptr().cast::<u8>(), &ZERO_SENTINEL)
+    }
+
+    /// Decodes the lengths of all the slices.
+    fn lens(&self) -> [usize; N] {
+        let mut lens = [0; N];
+        if self.is_sentinel() {
+            return lens;
+        }
+
+        unsafe {
+            let base = self.header.as_ptr().cast::<u8>();
+            let total = self
+                .header
+                .as_ref()
+                .decode_len(base.add(mem::size_of::<H>()));
+            let mut pos = base.add(mem::size_of::<H>() + H::extra_needed(total).unwrap());
+            let mut rest = total;
+            for len in &mut lens[..N - 1] {
+                let header = &*pos.cast::<BoxHeader>();
+                *len = header.decode_len(pos.add(mem::size_of::<BoxHeader>()));
+                rest -= *len;
+                pos = pos.add(mem::size_of::<BoxHeader>() + header.extra_len().unwrap());
+            }
+            lens[N - 1] = rest;
+        }
+        lens
+    }
+
+    fn data(&self, offsets: &Offsets) -> *mut T {
+        unsafe {
+            self.header
+                .as_ptr()
+                .cast::<u8>()
+                .add(offsets.data)
+                .cast::<T>()
+        }
+    }
+
+    /// Returns the slice at the given position.
+    ///
+    /// # Panics
+    ///
+    /// If the index is not smaller than `N`.
+    pub fn get(&self, idx: usize) -> &[T] {
+        assert!(idx < N, "Index {} out of {} slices", idx, N);
+        self.parts()[idx]
+    }
+
+    /// Returns all the slices.
+    pub fn parts(&self) -> [&[T]; N] {
+        if self.is_sentinel() {
+            return [&[]; N];
+        }
+
+        let lens = self.lens();
+        let offsets = Self::offsets(&lens).expect("Already allocated with these lengths");
+        let data = self.data(&offsets);
+        let mut start = 0;
+        array::from_fn(|i| {
+            let part = unsafe { slice::from_raw_parts(data.add(start), lens[i]) };
+            start += lens[i];
+            part
+        })
+    }
+
+    /// The total number of elements in all the slices.
+    pub fn total_len(&self) -> usize {
+        self.lens().iter().sum()
+    }
+
+    /// How many bytes the heap allocation takes (not counting any allocator overhead).
+    pub fn heap_size(&self) -> usize {
+        if self.is_sentinel() {
+            0
+        } else {
+            Self::offsets(&self.lens())
+                .expect("Already allocated with these lengths")
+                .layout
+                .size()
+        }
+    }
+}
+
+impl<T, const N: usize, H> Drop for SliceGroup<T, N, H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        if self.is_sentinel() {
+            return;
+        }
+
+        unsafe {
+            if self.header.as_ref().dec() {
+                let offsets =
+                    Self::offsets(&self.lens()).expect("Already allocated with these lengths");
+                if mem::needs_drop::<T>() {
+                    let data = self.data(&offsets);
+                    for i in 0..offsets.total {
+                        ptr::drop_in_place(data.add(i));
+                    }
+                }
+
+                if self.header.as_ref().dec_weak() {
+                    mem_dealloc(self.header.as_ptr().cast::<u8>(), offsets.layout);
+                }
+            }
+        }
+    }
+}
+
+impl<T, const N: usize, H> Clone for SliceGroup<T, N, H>
+where
+    T: Clone,
+    H: Header,
+{
+    fn clone(&self) -> Self {
+        if self.is_sentinel() || unsafe { self.header.as_ref().inc() } {
+            Self {
+                header: self.header,
+                _data: PhantomData,
+            }
+        } else {
+            H::on_overflow().overflowed();
+            Self::new(self.parts()).expect("Already have layout for these sizes")
+        }
+    }
+}
+
+impl<T, const N: usize, H> Default for SliceGroup<T, N, H>
+where
+    H: Header,
+{
+    /// Creates a group of empty slices (without allocating).
+    fn default() -> Self {
+        stats::record_sentinel();
+        Self {
+            header: NonNull::from(&ZERO_SENTINEL).cast(),
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<T, const N: usize, H> Debug for SliceGroup<T, N, H>
+where
+    T: Debug,
+    H: Header,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_list().entries(self.parts()).finish()
+    }
+}
+
+impl<T, U, const N: usize, H1, H2> PartialEq<SliceGroup<U, N, H2>> for SliceGroup<T, N, H1>
+where
+    T: PartialEq<U>,
+    H1: Header,
+    H2: Header,
+{
+    fn eq(&self, other: &SliceGroup<U, N, H2>) -> bool {
+        self.parts()
+            .iter()
+            .zip(other.parts().iter())
+            .all(|(a, b)| a == b)
+    }
+}
+
+impl<T, const N: usize, H> Eq for SliceGroup<T, N, H>
+where
+    T: Eq,
+    H: Header,
+{
+}
+
+// Same bounds as with the OwnedSlice, decided by the Ownership marker of the header.
+unsafe impl<T, const N: usize, H> Send for SliceGroup<T, N, H>
+where
+    H: Header + Send + Sync,
+    <H::Ownership as Ownership>::Marker<T>: Send,
+{
+}
+
+unsafe impl<T, const N: usize, H> Sync for SliceGroup<T, N, H>
+where
+    H: Header + Send + Sync,
+    <H::Ownership as Ownership>::Marker<T>: Sync,
+{
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::header::mock::ShortMockHeader;
+    use crate::{ArcHeader, OwnedSlice, RcHeader};
+
+    #[test]
+    fn three_bytes() {
+        let g: SliceGroup<u8, 3> = SliceGroup::new([b"Hello", b"", b"World"]).unwrap();
+        assert_eq!(mem::size_of::<usize>(), mem::size_of_val(&g));
+        assert_eq!(
+            mem::size_of::<usize>(),
+            mem::size_of::<Option<SliceGroup<u8, 3>>>()
+        );
+        assert_eq!(b"Hello", g.get(0));
+        assert!(g.get(1).is_empty());
+        assert_eq!(b"World", g.get(2));
+        assert_eq!(10, g.total_len());
+        assert_eq!(
+            "[[72, 101, 108, 108, 111], [], [87, 111, 114, 108, 100]]",
+            format!("{:?}", g)
+        );
+
+        // A single allocation: header, two lengths and all the data, one after another.
+        assert_eq!(1 + 2 + 10, g.heap_size());
+        let base = g.header.as_ptr().cast::<u8>().cast_const();
+        assert_eq!(base.wrapping_add(3), g.get(0).as_ptr());
+        assert_eq!(g.get(0).as_ptr().wrapping_add(5), g.get(2).as_ptr());
+
+        // Separate slices need one more byte of heap (one header each), but three pointers and
+        // two allocations.
+        let separate = [b"Hello", &b""[..], b"World"]
+            .iter()
+            .map(|s| OwnedSlice::<u8>::new(s).unwrap().heap_size())
+            .sum::<usize>();
+        assert_eq!(12, separate);
+        assert!(g.heap_size() + mem::size_of_val(&g) < separate + 3 * mem::size_of_val(&g));
+    }
+
+    #[test]
+    fn empty() {
+        let g: SliceGroup<String, 2> = SliceGroup::new([&[], &[]]).unwrap();
+        assert_eq!(0, g.heap_size());
+        assert!(g.is_sentinel());
+        assert_eq!([&[] as &[String]; 2], g.parts());
+        assert_eq!(g, g.clone());
+        let g: SliceGroup<u8, 0> = SliceGroup::new([]).unwrap();
+        assert_eq!(0, g.total_len());
+    }
+
+    #[test]
+    fn long_parts() {
+        let long = vec![42u64; 1000];
+        let g: SliceGroup<u64, 4> = SliceGroup::new([&[1], &long, &[], &[2, 3]]).unwrap();
+        assert_eq!(&[1], g.get(0));
+        assert_eq!(&long[..], g.get(1));
+        assert_eq!(&[2, 3], g.get(3));
+        assert!(g.get(0).as_ptr().is_aligned());
+        assert_eq!(1003, g.total_len());
+        let single: SliceGroup<u64, 1> = SliceGroup::new([&long]).unwrap();
+        assert_eq!(&long[..], single.get(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        let g: SliceGroup<u8, 2> = SliceGroup::new([b"a", b"b"]).unwrap();
+        g.get(2);
+    }
+
+    #[test]
+    fn drops() {
+        let counter = Rc::new(());
+        let items = vec![Rc::clone(&counter); 3];
+        let g: SliceGroup<Rc<()>, 3> = SliceGroup::new([&items[..1], &[], &items]).unwrap();
+        assert_eq!(8, Rc::strong_count(&counter));
+        let g2 = g.clone();
+        assert_eq!(12, Rc::strong_count(&counter));
+        assert_eq!(g, g2);
+        drop(g);
+        drop(g2);
+        assert_eq!(4, Rc::strong_count(&counter));
+    }
+
+    #[test]
+    fn shared() {
+        let g: SliceGroup<String, 2, RcHeader> =
+            SliceGroup::new([&["a".to_owned()], &["b".to_owned(), "c".to_owned()]]).unwrap();
+        let g2 = g.clone();
+        assert_eq!(g.header, g2.header);
+        drop(g);
+        assert_eq!(&["b", "c"], g2.get(1));
+
+        let a: SliceGroup<u8, 2, ArcHeader> = SliceGroup::new([b"x", b"yz"]).unwrap();
+        std::thread::spawn(move || assert_eq!(b"yz", a.get(1)))
+            .join()
+            .unwrap();
+    }
+
+    /// Uniquely owned groups have the same auto trait bounds as Box.
+    #[test]
+    fn unique_send() {
+        fn assert_send<T: Send>() {}
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send::<SliceGroup<Cell<u8>, 2>>();
+        assert_send_sync::<SliceGroup<String, 2>>();
+        assert_send_sync::<SliceGroup<String, 2, ArcHeader>>();
+    }
+
+    #[test]
+    fn too_long() {
+        SliceGroup::<u8, 2, ShortMockHeader>::new([b"ab", b"cd"]).unwrap();
+        SliceGroup::<u8, 2, ShortMockHeader>::new([b"ab", b"cde"]).unwrap_err();
+    }
+}
@@ -0,0 +1,186 @@
+use core::hint;
+use core::sync::atomic::{fence, AtomicU32, Ordering};
+
+use super::{Header, Shared, TooLong};
+use crate::BoxHeader;
+
+/// Marks the weak count as temporarily locked by [`is_unique`][Header::is_unique].
+const LOCKED: u32 = u32::MAX;
+
+/// The counts don't get incremented past this value.
+const MAX_COUNT: u32 = u32::MAX - 1;
+
+/// A thread-safe reference counted header.
+///
+/// This is the equivalent of [`Arc`][alloc::sync::Arc]. Cloning the
+/// [`OwnedSlice`][crate::OwnedSlice] only increments the reference count and weak references are
+/// supported (see [`OwnedSlice::downgrade`][crate::OwnedSlice::downgrade]).
+///
+/// Both the strong and weak counts are 32bit. If the strong count would overflow, a clone panics
+/// ([`try_clone`][crate::OwnedSlice::try_clone] makes a full copy of the data instead).
+///
+/// The length is encoded the same way as in the [`BoxHeader`].
+pub struct ArcHeader {
+    strong: AtomicU32,
+    weak: AtomicU32,
+    len: BoxHeader,
+}
+
+impl ArcHeader {
+    /// Pretends there are as many owners as the strong count can hold.
+    #[cfg(all(feature = "arc-swap", feature = "std", test))]
+    pub(crate) fn saturate(&self) {
+        self.strong.store(MAX_COUNT, Ordering::Relaxed);
+    }
+}
+
+unsafe impl Header for ArcHeader {
+    type Ownership = Shared;
+    const MAX_LEN: usize = BoxHeader::MAX_LEN;
+    #[inline]
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        BoxHeader::extra_needed(len)
+    }
+    #[inline]
+    unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+        Self {
+            strong: AtomicU32::new(1),
+            // All the strong references together hold one weak.
+            weak: AtomicU32::new(1),
+            len: BoxHeader::encode_len(len, extra),
+        }
+    }
+    #[inline]
+    unsafe fn decode_len(&self, extra: *const u8) -> usize {
+        self.len.decode_len(extra)
+    }
+    #[inline]
+    fn extra_len(&self) -> Option<usize> {
+        self.len.extra_len()
+    }
+    #[inline]
+    fn inc(&self) -> bool {
+        // Relaxed is enough, the new reference is created from an existing one, which is already
+        // properly synchronized.
+        self.strong
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |cnt| {
+                if cnt < MAX_COUNT {
+                    Some(cnt + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+    #[inline]
+    fn dec(&self) -> bool {
+        dec(&self.strong)
+    }
+    fn strong_count(&self) -> Option<usize> {
+        Some(self.strong.load(Ordering::Relaxed) as usize)
+    }
+    fn is_unique(&self) -> bool {
+        // Same trick as the std Arc::get_mut does. We lock the weak count so nobody can create a
+        // new weak reference (from another strong one) in between the two checks. If it is just
+        // the one weak held by the strong ones, nobody can upgrade in the meantime either.
+        if self
+            .weak
+            .compare_exchange(1, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            let unique = self.strong.load(Ordering::Acquire) == 1;
+            self.weak.store(1, Ordering::Release);
+            unique
+        } else {
+            false
+        }
+    }
+    fn inc_weak(&self) -> bool {
+        let mut cnt = self.weak.load(Ordering::Relaxed);
+        loop {
+            if cnt == LOCKED {
+                // Someone is just checking uniqueness, it won't take long.
+                hint::spin_loop();
+                cnt = self.weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if cnt >= MAX_COUNT {
+                return false;
+            }
+            match self.weak.compare_exchange_weak(
+                cnt,
+                cnt + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(old) => cnt = old,
+            }
+        }
+    }
+    #[inline]
+    fn dec_weak(&self) -> bool {
+        dec(&self.weak)
+    }
+    #[inline]
+    fn upgrade(&self) -> bool {
+        self.strong
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |cnt| {
+                if cnt > 0 && cnt < MAX_COUNT {
+                    Some(cnt + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+}
+
+#[inline]
+fn dec(cnt: &AtomicU32) -> bool {
+    if cnt.fetch_sub(1, Ordering::Release) == 1 {
+        // Make sure all the other owners are done with the data before destroying it.
+        fence(Ordering::Acquire);
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use super::*;
+
+    fn header() -> ArcHeader {
+        unsafe { ArcHeader::encode_len(0, [].as_mut_ptr()) }
+    }
+
+    #[test]
+    fn counts() {
+        let h = header();
+        assert!(h.is_unique());
+        assert!(h.inc());
+        assert!(!h.is_unique());
+        assert_eq!(Some(2), h.strong_count());
+        assert!(!h.dec());
+        assert!(h.is_unique());
+
+        assert!(h.inc_weak());
+        assert!(!h.is_unique());
+        assert!(h.dec());
+        assert!(!h.upgrade());
+        assert!(!h.dec_weak());
+        assert!(h.dec_weak());
+    }
+
+    #[test]
+    fn overflow() {
+        let h = header();
+        h.strong.store(MAX_COUNT, Ordering::Relaxed);
+        assert!(!h.inc());
+        assert!(!h.upgrade());
+        assert_eq!(MAX_COUNT, h.strong.load(Ordering::Relaxed));
+        h.weak.store(MAX_COUNT, Ordering::Relaxed);
+        assert!(!h.inc_weak());
+    }
+}
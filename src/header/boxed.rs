@@ -1,57 +1,111 @@
-use core::convert::TryInto;
+use core::convert::{TryFrom, TryInto};
 use core::ptr;
 
-use super::{Header, TooLong};
+use super::{Header, TooLong, Unique};
 
 const EXTRA_MASK: u8 = 0b11;
 const INLINE_BITS: u32 = 6;
 const INLINE_MASK: u8 = 0b111111;
-const MAX_EXTRAS: usize = 4;
+// Only 2 bits are available to store the number of extra bytes.
+const MAX_EXTRAS: usize = EXTRA_MASK as usize;
+// All the inline bits and all the extra bytes set.
+const MAX_LEN_U64: u64 = (1 << (INLINE_BITS as usize + 8 * MAX_EXTRAS)) - 1;
+
+/// Splits the length into the extra bytes (little endian) and the inline bits.
+///
+/// This is the single place deciding the layout of the encoding. Returns the number of extra
+/// bytes, the bytes themselves (only that many at the start are valid) and the inline bits.
+/// `None` is returned if the length can't be encoded.
+fn split_u64(len: u64) -> Option<(usize, [u8; 8], u8)> {
+    if len > MAX_LEN_U64 {
+        return None;
+    }
+    let significant = 64 - len.leading_zeros();
+    // We store 6 bits inline in ourselves, then can have up to 3 extra bytes for length.
+    let extra = significant.saturating_sub(INLINE_BITS).div_ceil(8) as usize;
+    debug_assert!(extra <= MAX_EXTRAS);
+    let bytes = len.to_le_bytes();
+    let inline = bytes[extra];
+    // Everything above the inline bits must be zero, otherwise we would lose them.
+    debug_assert_eq!(inline & !INLINE_MASK, 0);
+    debug_assert!(bytes[extra + 1..].iter().all(|&b| b == 0));
+    Some((extra, bytes, inline))
+}
+
+/// Reads the length from the header byte and the extra bytes.
+///
+/// # Safety
+///
+/// The `extra` must point to as many bytes as the header byte designates.
+unsafe fn decode_u64(header: u8, extra: *const u8) -> u64 {
+    let extra_len = usize::from(header >> INLINE_BITS);
+    let mut buf = [0; 8];
+    ptr::copy_nonoverlapping(extra, buf.as_mut_ptr(), extra_len);
+    buf[extra_len] = header & INLINE_MASK;
+    u64::from_le_bytes(buf)
+}
+
+/// Converts a decoded length into the length type of the platform.
+///
+/// A length that doesn't fit can come only from corrupted data (we never encode one). It
+/// saturates, so it doesn't pass any validation, instead of being silently truncated.
+fn saturating_len<L: TryFrom<u64>>(len: u64, max: L) -> L {
+    L::try_from(len).unwrap_or(max)
+}
 
 /// A header without sharing support.
 ///
-/// The data will be uniquely owned. Lengths up to 64 are encoded inline in this header. It is
-/// possible to get a mutable access to the elements of the slice.
+/// The data will be uniquely owned. Lengths below 64 are encoded inline in this header, longer
+/// ones take up to 3 extra bytes (so the limit is 2^30 elements). It is possible to get a mutable
+/// access to the elements of the slice.
 ///
 /// This is the default [`Header`] implementation if none is set.
+#[derive(Copy, Clone)]
 pub struct BoxHeader(u8);
 
+impl BoxHeader {
+    /// The longest length encoded inline in the header, without any extra bytes (63).
+    pub const MAX_INLINE_LEN: usize = INLINE_MASK as usize;
+}
+
 unsafe impl Header for BoxHeader {
+    type Ownership = Unique;
+    // No allocation can be larger than isize::MAX anyway, but on small platforms (16bit) the
+    // limit of the encoding itself is above it.
+    const MAX_LEN: usize = if MAX_LEN_U64 > isize::MAX as u64 {
+        isize::MAX as usize
+    } else {
+        MAX_LEN_U64 as usize
+    };
     #[inline]
     fn extra_needed(len: usize) -> Result<usize, TooLong> {
-        let len: u64 = len.try_into().map_err(|_| TooLong)?;
-        let zeroes = len.leading_zeros();
-        let significant = 64 - zeroes;
-        // We store 6 bits inline in ourselves, then can have up to 4 extra bytes for length.
-        let extra = ((significant.saturating_sub(INLINE_BITS)) + 7) / 8;
-        let extra = extra as usize;
-
-        if extra <= MAX_EXTRAS {
-            Ok(extra)
-        } else {
-            Err(TooLong)
+        let too_long = TooLong {
+            len,
+            max: Self::MAX_LEN,
+        };
+        if len > isize::MAX as usize {
+            return Err(too_long);
         }
+        let len: u64 = len.try_into().map_err(|_| too_long)?;
+        split_u64(len).map(|(extra, _, _)| extra).ok_or(too_long)
     }
     #[inline]
     unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
-        let extra_len = Self::extra_needed(len).unwrap();
-        let len = len as u64;
-        let bytes = len.to_le_bytes();
+        let (extra_len, bytes, inline) = split_u64(len as u64).unwrap();
 
         ptr::copy_nonoverlapping(bytes.as_ptr(), extra, extra_len);
 
-        let encoded =
-            ((extra_len as u8 & EXTRA_MASK) << INLINE_BITS) | (bytes[extra_len] & INLINE_MASK);
-        Self(encoded)
+        let encoded = Self(((extra_len as u8 & EXTRA_MASK) << INLINE_BITS) | inline);
+        debug_assert_eq!(len, encoded.decode_len(extra));
+        encoded
     }
     #[inline]
     unsafe fn decode_len(&self, extra: *const u8) -> usize {
-        let extra_len = self.0 >> INLINE_BITS;
-        let mut buf = [0; 8];
-        ptr::copy_nonoverlapping(extra, buf.as_mut_ptr(), extra_len as usize);
-        buf[extra_len as usize] = self.0 & INLINE_MASK;
-        let len = u64::from_le_bytes(buf);
-        len as usize
+        saturating_len(decode_u64(self.0, extra), usize::MAX)
+    }
+    #[inline]
+    fn extra_len(&self) -> Option<usize> {
+        Some(usize::from(self.0 >> INLINE_BITS))
     }
     #[inline]
     fn inc(&self) -> bool {
@@ -103,11 +157,115 @@ mod tests {
         }
     }
 
+    /// Lengths around every byte boundary of the encoding (uniformly random lengths almost never
+    /// hit these).
+    #[test]
+    fn boundary_len() {
+        for bits in 0..6 {
+            let boundary = 1i64 << (INLINE_BITS as usize + 8 * bits);
+            for delta in -4..=4 {
+                let len = (boundary + delta) as u64;
+                match split_u64(len) {
+                    Some((extra, _, _)) => {
+                        assert!(len <= MAX_LEN_U64);
+                        let len = len as usize;
+                        assert_eq!(extra, BoxHeader::extra_needed(len).unwrap());
+                        let mut buf = vec![0; extra];
+                        buf.shrink_to_fit();
+                        unsafe {
+                            let h = BoxHeader::encode_len(len, buf.as_mut_ptr());
+                            assert_eq!(len, h.decode_len(buf.as_ptr()));
+                        }
+                    }
+                    None => {
+                        assert!(len > MAX_LEN_U64);
+                        if let Ok(len) = len.try_into() {
+                            assert!(BoxHeader::extra_needed(len).is_err());
+                        }
+                    }
+                }
+            }
+        }
+        assert_eq!(3, BoxHeader::extra_needed((1 << 30) - 1).unwrap());
+        assert!(BoxHeader::extra_needed(1 << 30).is_err());
+    }
+
+    /// The limits of the encoding itself, independent of the platform's `usize`.
+    #[test]
+    fn limits_u64() {
+        for len in [0, 63, 64, 1 << 14, 1 << 22, MAX_LEN_U64] {
+            let (extra, bytes, inline) = split_u64(len).unwrap();
+            let header = ((extra as u8) << INLINE_BITS) | inline;
+            assert_eq!(len, unsafe { decode_u64(header, bytes.as_ptr()) });
+        }
+        for len in [MAX_LEN_U64 + 1, 1 << 37, u64::MAX] {
+            assert!(split_u64(len).is_none());
+        }
+        assert!(BoxHeader::extra_needed(isize::MAX as usize + 1).is_err());
+        assert!(BoxHeader::extra_needed(usize::MAX).is_err());
+    }
+
+    /// The exact top of the range, including the off-by-one.
+    #[test]
+    fn max_len() {
+        assert_eq!(MAX_LEN_U64, (1 << 30) - 1);
+        assert_eq!(MAX_EXTRAS, split_u64(MAX_LEN_U64).unwrap().0);
+        assert!(split_u64(MAX_LEN_U64 + 1).is_none());
+
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!((1 << 30) - 1, BoxHeader::MAX_LEN);
+        assert_eq!(
+            MAX_EXTRAS,
+            BoxHeader::extra_needed(BoxHeader::MAX_LEN).unwrap()
+        );
+        assert!(BoxHeader::extra_needed(BoxHeader::MAX_LEN + 1).is_err());
+
+        let mut buf = [0; MAX_EXTRAS];
+        unsafe {
+            let h = BoxHeader::encode_len(BoxHeader::MAX_LEN, buf.as_mut_ptr());
+            assert_eq!(BoxHeader::MAX_LEN, h.decode_len(buf.as_ptr()));
+        }
+    }
+
+    /// Decoding a length too large for the platform saturates instead of truncating.
+    ///
+    /// All the encodable lengths fit into the `usize` of 32 and 64bit platforms, so this simulates
+    /// a 16bit one with crafted extra bytes.
+    #[test]
+    fn decode_saturates() {
+        // 0x1_0005 would truncate to 5
+        let header = 0b1000_0001;
+        let extra = [0x05, 0x00];
+        let len = unsafe { decode_u64(header, extra.as_ptr()) };
+        assert_eq!(0x1_0005, len);
+        assert_eq!(u16::MAX, saturating_len(len, u16::MAX));
+        assert_eq!(0x1_0005, saturating_len(len, u32::MAX));
+
+        // The largest possible encoded length
+        let extra = [0xff; 3];
+        let len = unsafe { decode_u64(0xff, extra.as_ptr()) };
+        assert_eq!(MAX_LEN_U64, len);
+        assert_eq!(u16::MAX, saturating_len(len, u16::MAX));
+        let h = BoxHeader(0xff);
+        assert_eq!(MAX_LEN_U64 as usize, unsafe {
+            h.decode_len(extra.as_ptr())
+        });
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn limits_32bit() {
+        assert_eq!(MAX_LEN_U64 as usize, BoxHeader::MAX_LEN);
+        assert!(BoxHeader::extra_needed(isize::MAX as usize + 1).is_err());
+        assert!(BoxHeader::extra_needed(usize::MAX).is_err());
+        assert_eq!(3, BoxHeader::extra_needed((1 << 30) - 1).unwrap());
+    }
+
     proptest! {
         #[test]
         fn random_len(len: usize) {
             if let Ok(extra) = BoxHeader::extra_needed(len) {
-                prop_assert!(extra <= 4);
+                prop_assert!(extra <= MAX_EXTRAS);
                 let mut buf = vec![0; extra];
                 // make sure there's no extra space and any kind of overflow would get detected
                 buf.shrink_to_fit();
@@ -0,0 +1,93 @@
+use super::{Header, TooLong, Unique};
+
+/// A header storing the length in exactly one byte.
+///
+/// Only lengths up to 255 can be encoded, but there are never any extra bytes and decoding the
+/// length is a single byte read with no branching. This suits structures holding huge numbers of
+/// tiny slices (for example nodes of a trie). Longer slices are rejected with [`TooLong`].
+///
+/// Like the [`BoxHeader`][crate::BoxHeader], the data are uniquely owned.
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::{ByteHeader, Str};
+///
+/// let s: Str<ByteHeader> = Str::new("Hello").unwrap();
+/// assert_eq!("Hello", &s as &str);
+/// assert!(Str::<ByteHeader>::new(&"x".repeat(256)).is_err());
+/// ```
+#[derive(Copy, Clone)]
+pub struct ByteHeader(u8);
+
+unsafe impl Header for ByteHeader {
+    type Ownership = Unique;
+    const MAX_LEN: usize = u8::MAX as usize;
+    #[inline]
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        if len <= Self::MAX_LEN {
+            Ok(0)
+        } else {
+            Err(TooLong {
+                len,
+                max: Self::MAX_LEN,
+            })
+        }
+    }
+    #[inline]
+    unsafe fn encode_len(len: usize, _extra: *mut u8) -> Self {
+        debug_assert!(len <= Self::MAX_LEN);
+        Self(len as u8)
+    }
+    #[inline]
+    unsafe fn decode_len(&self, _extra: *const u8) -> usize {
+        usize::from(self.0)
+    }
+    #[inline]
+    fn extra_len(&self) -> Option<usize> {
+        Some(0)
+    }
+    #[inline]
+    fn inc(&self) -> bool {
+        false
+    }
+    #[inline]
+    fn dec(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{OwnedSlice, Str};
+
+    #[test]
+    fn too_long() {
+        assert_eq!(0, ByteHeader::extra_needed(255).unwrap());
+        ByteHeader::extra_needed(256).unwrap_err();
+        assert!(OwnedSlice::<u8, ByteHeader>::new(&[0; 256]).is_err());
+    }
+
+    #[test]
+    fn layout() {
+        let s: Str<ByteHeader> = Str::new("Hello").unwrap();
+        assert_eq!(1 + 5, s.0.heap_size());
+        let s = OwnedSlice::<u8, ByteHeader>::new(&[42; 255]).unwrap();
+        assert_eq!(1 + 255, s.heap_size());
+        assert_eq!(&[42; 255][..], &s[..]);
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(len in 0..=255usize) {
+            prop_assert_eq!(0, ByteHeader::extra_needed(len).unwrap());
+            unsafe {
+                let h = ByteHeader::encode_len(len, [].as_mut_ptr());
+                prop_assert_eq!(len, h.decode_len([].as_ptr()));
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Mock headers for our own tests.
+//!
+//! They don't aim for efficiency, only to exercise some behaviour of the slices the real headers
+//! don't have (yet).
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+use crate::{BoxHeader, Header, OverflowPolicy, Ownership, Shared, TooLong, Unique};
+
+/// A single-threaded reference counted header.
+///
+/// The length encoding is borrowed from the [`BoxHeader`].
+pub(crate) struct RcMockHeader {
+    count: Cell<usize>,
+    len: BoxHeader,
+}
+
+unsafe impl Header for RcMockHeader {
+    type Ownership = Shared;
+    const MAX_LEN: usize = BoxHeader::MAX_LEN;
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        BoxHeader::extra_needed(len)
+    }
+    unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+        Self {
+            count: Cell::new(1),
+            len: BoxHeader::encode_len(len, extra),
+        }
+    }
+    unsafe fn decode_len(&self, extra: *const u8) -> usize {
+        self.len.decode_len(extra)
+    }
+    fn inc(&self) -> bool {
+        self.count.set(self.count.get() + 1);
+        true
+    }
+    fn dec(&self) -> bool {
+        let count = self.count.get() - 1;
+        self.count.set(count);
+        count == 0
+    }
+    fn is_unique(&self) -> bool {
+        self.count.get() == 1
+    }
+}
+
+/// A reference counted header with only a 3-bit counter, so it overflows at 7 owners.
+///
+/// The `POLICY` picks the [`OverflowPolicy`]: 0 is `DeepClone`, 1 is `Panic` and anything else is
+/// `Abort`. The `O` is the [`Ownership`], either [`Shared`] or
+/// [`CopyOnOverflow`][crate::CopyOnOverflow].
+pub(crate) struct TinyRcMockHeader<const POLICY: u8, O = Shared> {
+    count: Cell<u8>,
+    len: BoxHeader,
+    _ownership: PhantomData<O>,
+}
+
+unsafe impl<const POLICY: u8, O> Header for TinyRcMockHeader<POLICY, O>
+where
+    O: Ownership,
+{
+    type Ownership = O;
+    const MAX_LEN: usize = BoxHeader::MAX_LEN;
+
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        BoxHeader::extra_needed(len)
+    }
+    unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+        Self {
+            count: Cell::new(1),
+            len: BoxHeader::encode_len(len, extra),
+            _ownership: PhantomData,
+        }
+    }
+    unsafe fn decode_len(&self, extra: *const u8) -> usize {
+        self.len.decode_len(extra)
+    }
+    fn inc(&self) -> bool {
+        let count = self.count.get();
+        if count < 0b111 {
+            self.count.set(count + 1);
+            true
+        } else {
+            false
+        }
+    }
+    fn on_overflow() -> OverflowPolicy {
+        match POLICY {
+            0 => OverflowPolicy::DeepClone,
+            1 => OverflowPolicy::Panic,
+            _ => OverflowPolicy::Abort,
+        }
+    }
+    fn strong_count(&self) -> Option<usize> {
+        Some(self.count.get().into())
+    }
+    fn dec(&self) -> bool {
+        let count = self.count.get() - 1;
+        self.count.set(count);
+        count == 0
+    }
+    fn is_unique(&self) -> bool {
+        self.count.get() == 1
+    }
+}
+
+/// A reference counted header with saturated reference count.
+///
+/// It never allows incrementing the reference count, so all clones are full copies.
+pub(crate) struct SaturatedMockHeader(BoxHeader);
+
+unsafe impl Header for SaturatedMockHeader {
+    type Ownership = Unique;
+    const MAX_LEN: usize = BoxHeader::MAX_LEN;
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        BoxHeader::extra_needed(len)
+    }
+    unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+        Self(BoxHeader::encode_len(len, extra))
+    }
+    unsafe fn decode_len(&self, extra: *const u8) -> usize {
+        self.0.decode_len(extra)
+    }
+    fn inc(&self) -> bool {
+        false
+    }
+    fn dec(&self) -> bool {
+        true
+    }
+    fn is_unique(&self) -> bool {
+        true
+    }
+}
+
+/// A header that can encode only lengths up to 4.
+///
+/// Allows exercising the [`TooLong`] error paths without huge allocations.
+pub(crate) struct ShortMockHeader(BoxHeader);
+
+unsafe impl Header for ShortMockHeader {
+    type Ownership = Unique;
+    const MAX_LEN: usize = 4;
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        if len <= Self::MAX_LEN {
+            BoxHeader::extra_needed(len)
+        } else {
+            Err(TooLong {
+                len,
+                max: Self::MAX_LEN,
+            })
+        }
+    }
+    unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+        Self(BoxHeader::encode_len(len, extra))
+    }
+    unsafe fn decode_len(&self, extra: *const u8) -> usize {
+        self.0.decode_len(extra)
+    }
+    fn inc(&self) -> bool {
+        false
+    }
+    fn dec(&self) -> bool {
+        true
+    }
+    fn is_unique(&self) -> bool {
+        true
+    }
+}
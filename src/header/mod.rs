@@ -1,8 +1,17 @@
 use alloc::fmt::{Display, Formatter, Result as FmtResult};
+use core::error::Error;
+use core::marker::PhantomData;
+use core::str::Utf8Error;
 #[cfg(feature = "std")]
-use std::error::Error;
+use std::io::Error as IoError;
 
+#[cfg(target_has_atomic = "32")]
+pub(crate) mod arc;
 pub(crate) mod boxed;
+pub(crate) mod byte;
+#[cfg(all(feature = "std", test))]
+pub(crate) mod mock;
+pub(crate) mod rc;
 
 /// An error returned when the slice or string is longer than the header is able to encode.
 ///
@@ -13,18 +22,353 @@ pub(crate) mod boxed;
 /// Note that the limits are usually above practical usability limits and if strings of over 4GB
 /// are actually needed, the usefulness of this library is questionable (it optimizes for many
 /// small strings/slices, the overhead is negligible on these large behemoths).
-#[derive(Copy, Clone, Debug)]
-pub struct TooLong;
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::{BoxHeader, Header, TooLong};
+///
+/// let err = BoxHeader::extra_needed(usize::MAX).unwrap_err();
+/// assert_eq!(TooLong { len: usize::MAX, max: BoxHeader::MAX_LEN }, err);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TooLong {
+    /// The length that was asked for.
+    ///
+    /// If the length doesn't even fit into `usize` (for example when concatenating many slices),
+    /// this is `usize::MAX`.
+    pub len: usize,
+    /// The longest length the header can encode (its [`MAX_LEN`][Header::MAX_LEN]).
+    ///
+    /// If the elements are so large that even a shorter slice wouldn't fit into the address space,
+    /// this is an estimate of the longest one that would.
+    pub max: usize,
+}
+
+impl TooLong {
+    /// The error for a length overflowing `usize`, for the header `H`.
+    pub(crate) fn overflow<H: Header>() -> Self {
+        TooLong {
+            len: usize::MAX,
+            max: H::MAX_LEN,
+        }
+    }
+}
 
 impl Display for TooLong {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        write!(fmt, "Too long")
+        write!(
+            fmt,
+            "slice of length {} exceeds maximum {}",
+            self.len, self.max
+        )
     }
 }
 
-#[cfg(feature = "std")]
 impl Error for TooLong {}
 
+/// An error returned when the allocator fails to provide the memory.
+#[derive(Copy, Clone, Debug)]
+pub struct AllocError;
+
+impl Display for AllocError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "Memory allocation failed")
+    }
+}
+
+impl Error for AllocError {}
+
+/// An error of the fallible allocation constructors.
+///
+/// See [`OwnedSlice::new_no_abort`][crate::OwnedSlice::new_no_abort].
+#[derive(Copy, Clone, Debug)]
+pub enum AllocOrTooLong {
+    /// The slice is too long for the header.
+    TooLong(TooLong),
+    /// The allocator failed to provide the memory.
+    Alloc(AllocError),
+}
+
+impl Display for AllocOrTooLong {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            AllocOrTooLong::TooLong(e) => Display::fmt(e, fmt),
+            AllocOrTooLong::Alloc(e) => Display::fmt(e, fmt),
+        }
+    }
+}
+
+impl Error for AllocOrTooLong {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AllocOrTooLong::TooLong(e) => Some(e),
+            AllocOrTooLong::Alloc(e) => Some(e),
+        }
+    }
+}
+
+impl From<TooLong> for AllocOrTooLong {
+    fn from(e: TooLong) -> Self {
+        AllocOrTooLong::TooLong(e)
+    }
+}
+
+impl From<AllocError> for AllocOrTooLong {
+    fn from(e: AllocError) -> Self {
+        AllocOrTooLong::Alloc(e)
+    }
+}
+
+/// An error when reconstructing a slice from its raw heap bytes.
+///
+/// See [`OwnedSlice::from_raw_heap_bytes`][crate::OwnedSlice::from_raw_heap_bytes].
+#[derive(Clone, Debug)]
+pub enum DecodeError {
+    /// The bytes end before the header, the extra bytes or the data does.
+    Truncated,
+    /// The header doesn't form a valid encoding of the length, or the length doesn't match the
+    /// number of bytes.
+    Corrupted,
+    /// The data is not valid UTF-8 (when decoding a [`Str`][crate::Str]).
+    Utf8(Utf8Error),
+    /// The bytes are not aligned for the element type (when borrowing the data in place).
+    Misaligned,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            DecodeError::Truncated => write!(fmt, "Truncated data"),
+            DecodeError::Corrupted => write!(fmt, "Corrupted header"),
+            DecodeError::Utf8(e) => Display::fmt(e, fmt),
+            DecodeError::Misaligned => write!(fmt, "Misaligned data"),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Utf8(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Utf8Error> for DecodeError {
+    fn from(e: Utf8Error) -> Self {
+        DecodeError::Utf8(e)
+    }
+}
+
+/// An error when reading a slice directly from a reader.
+///
+/// See [`OwnedSlice::copy_from_reader`][crate::OwnedSlice::copy_from_reader].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum ReadError {
+    /// The requested length is too long for the header.
+    TooLong(TooLong),
+    /// Reading failed, including the case of the reader running out of data prematurely.
+    Io(IoError),
+}
+
+#[cfg(feature = "std")]
+impl Display for ReadError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            ReadError::TooLong(e) => Display::fmt(e, fmt),
+            ReadError::Io(e) => Display::fmt(e, fmt),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::TooLong(e) => Some(e),
+            ReadError::Io(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<TooLong> for ReadError {
+    fn from(e: TooLong) -> Self {
+        ReadError::TooLong(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<IoError> for ReadError {
+    fn from(e: IoError) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+/// The ownership model of a [`Header`].
+///
+/// This decides how the slices are cloned (see [`CloneStrategy`]) and under which conditions they
+/// are [`Send`] and [`Sync`]. Uniquely owned data ([`Unique`]) behave like a
+/// [`Box`][alloc::boxed::Box] ‒ the slice is [`Send`] if the elements are [`Send`] and [`Sync`] if
+/// they are [`Sync`]. Shared data ([`Shared`], [`CopyOnOverflow`]) behave like an
+/// [`Arc`][alloc::sync::Arc] and need the elements to be both [`Send`] and [`Sync`] for either.
+///
+/// # Safety
+///
+/// The [`Marker`][Ownership::Marker] must not be [`Send`] or [`Sync`] under weaker conditions than
+/// the ownership model allows. It's best to use one of the provided implementations.
+pub unsafe trait Ownership {
+    /// A type standing for the elements in the [`Send`] and [`Sync`] bounds of the slices.
+    type Marker<T>;
+
+    /// Whether the headers may share the data between multiple owners.
+    fn is_shared() -> bool;
+}
+
+/// The [`Ownership`] of headers that never share the data.
+pub enum Unique {}
+
+unsafe impl Ownership for Unique {
+    type Marker<T> = PhantomData<T>;
+
+    #[inline]
+    fn is_shared() -> bool {
+        false
+    }
+}
+
+/// The [`Ownership::Marker`] of the sharing ownership models.
+///
+/// It is both [`Send`] and [`Sync`] only if `T` is both [`Send`] and [`Sync`].
+pub struct SharedMarker<T>(PhantomData<T>);
+
+unsafe impl<T> Send for SharedMarker<T> where T: Send + Sync {}
+
+unsafe impl<T> Sync for SharedMarker<T> where T: Send + Sync {}
+
+/// The [`Ownership`] of headers that may share the data between multiple owners.
+///
+/// The slices are [`Clone`] even if the elements are not. Therefore, they can't make a full copy
+/// when the reference count overflows and [`OverflowPolicy::DeepClone`] panics in [`Clone`].
+pub enum Shared {}
+
+unsafe impl Ownership for Shared {
+    type Marker<T> = SharedMarker<T>;
+
+    #[inline]
+    fn is_shared() -> bool {
+        true
+    }
+}
+
+/// The [`Ownership`] of sharing headers that make a full copy when the reference count overflows.
+///
+/// Unlike with [`Shared`], the slices are [`Clone`] only if the elements are, but then [`Clone`]
+/// honours [`OverflowPolicy::DeepClone`]. Useful for headers with small counters.
+pub enum CopyOnOverflow {}
+
+unsafe impl Ownership for CopyOnOverflow {
+    type Marker<T> = SharedMarker<T>;
+
+    #[inline]
+    fn is_shared() -> bool {
+        true
+    }
+}
+
+/// How the slices get cloned, decided by the [`Ownership`] of their header.
+///
+/// Uniquely owned slices ([`Unique`]) are cloned element by element, so the elements need to be
+/// [`Clone`]. Shared slices ([`Shared`]) only create another owner of the same data and don't
+/// need the elements to be [`Clone`] at all, just like [`Arc`][alloc::sync::Arc] doesn't.
+///
+/// If the reference count of a shared slice is saturated, the header's
+/// [`on_overflow`][Header::on_overflow] policy decides what happens. As [`Shared`] slices can't
+/// make a full copy without the elements being [`Clone`], they panic under
+/// [`OverflowPolicy::DeepClone`] (the [`try_clone`][crate::OwnedSlice::try_clone] method makes
+/// the full copy in that case). Headers wanting the copy from [`Clone`] use [`CopyOnOverflow`].
+///
+/// This trait is implemented for the provided ownership models and is not meant to be used
+/// directly.
+pub trait CloneStrategy<T>: Ownership {
+    /// Clones the slice.
+    fn clone_slice<H>(slice: &crate::OwnedSlice<T, H>) -> crate::OwnedSlice<T, H>
+    where
+        H: Header<Ownership = Self>;
+}
+
+/// How the slices get converted to a different header, decided by the [`Ownership`] of their
+/// original header.
+///
+/// Uniquely owned slices ([`Unique`]) always move their elements, so they don't need to be
+/// [`Clone`]. Slices that may be shared ([`Shared`], [`CopyOnOverflow`]) clone the elements if
+/// there are other owners, so the elements need to be [`Clone`].
+///
+/// See [`convert`][crate::OwnedSlice::convert]. This trait is implemented for the provided
+/// ownership models and is not meant to be used directly.
+pub trait ConvertStrategy<T>: Ownership {
+    /// Converts the slice to the header `H2`.
+    fn convert_slice<H, H2>(
+        slice: crate::OwnedSlice<T, H>,
+    ) -> Result<crate::OwnedSlice<T, H2>, TooLong>
+    where
+        H: Header<Ownership = Self>,
+        H2: Header;
+}
+
+/// What happens when cloning a shared slice whose reference count can't go any higher.
+///
+/// Chosen by the header's [`on_overflow`][Header::on_overflow].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum OverflowPolicy {
+    /// Make a full copy of the data, with a fresh reference count.
+    ///
+    /// This needs the elements to be [`Clone`]; the plain [`Clone`] of [`Shared`] slices (which
+    /// doesn't) panics instead.
+    DeepClone,
+    /// Panic, leaving the reference count untouched.
+    Panic,
+    /// Abort the whole process, the same way the std [`Arc`][alloc::sync::Arc] does.
+    Abort,
+}
+
+impl OverflowPolicy {
+    /// Handles a failed reference count increment.
+    ///
+    /// Returns only for [`DeepClone`][OverflowPolicy::DeepClone], the caller is then expected to
+    /// make the copy.
+    pub(crate) fn overflowed(self) {
+        match self {
+            OverflowPolicy::DeepClone => (),
+            OverflowPolicy::Panic => panic!("Reference count overflow"),
+            OverflowPolicy::Abort => abort(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn abort() -> ! {
+    std::process::abort()
+}
+
+/// Without std, a panic while already panicking is the way to abort.
+#[cfg(not(feature = "std"))]
+pub(crate) fn abort() -> ! {
+    struct Bomb;
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            panic!("Reference count overflow");
+        }
+    }
+
+    let _bomb = Bomb;
+    panic!("Reference count overflow");
+}
+
 /// Description of the header encoding a length.
 ///
 /// This is responsible to hold both a reference count (if applicable) and the length of the slice.
@@ -38,12 +382,41 @@ impl Error for TooLong {}
 ///
 /// The trait must correctly decode the same length as was encoded.
 ///
+/// If the header reports the [`extra_len`][Header::extra_len], it must be the same number as the
+/// [`extra_needed`][Header::extra_needed] returned for the encoded length.
+///
 /// The reference counting must properly "pair" ‒ it must not ask for destruction while someone
 /// still holds a reference count.
+///
+/// If the header supports weak references, the weak count must not ask for freeing the memory
+/// while any strong or weak reference exists and [`upgrade`][Header::upgrade] must not succeed
+/// once the strong count dropped to 0.
+///
+/// Headers claiming [`Unique`] [`Ownership`] must never share the data ‒ the
+/// [`inc`][Header::inc] always returns false and weak references are not supported.
+///
+/// Implementors should run `squash::testkit::check_header` in their tests (available with the
+/// `testkit` feature), which catches the common violations of this contract.
 pub unsafe trait Header {
+    /// Whether the data are uniquely owned or may be shared.
+    ///
+    /// This decides the bounds for the slices to be [`Clone`], [`Send`] and [`Sync`].
+    type Ownership: Ownership;
+
+    /// The longest length the header is able to encode.
+    ///
+    /// The [`extra_needed`][Header::extra_needed] succeeds for all lengths up to and including
+    /// this one and returns [`TooLong`] for all longer ones. This allows rejecting too long inputs
+    /// up front, before doing any work.
+    ///
+    /// Note that constructing a slice of this length may still fail for other reasons (for
+    /// example, if the whole allocation would be too large).
+    const MAX_LEN: usize;
+
     /// How many extra bytes are needed for encoding this length.
     ///
     /// Returns the amount of bytes needed, or signals that the length is too long for encoding.
+    /// The error carries the length and [`MAX_LEN`][Header::MAX_LEN].
     fn extra_needed(len: usize) -> Result<usize, TooLong>;
 
     /// Creates a new header and encodes the length.
@@ -70,15 +443,107 @@ pub unsafe trait Header {
     /// [`encode_len`][Header::encode_len].
     unsafe fn decode_len(&self, extra: *const u8) -> usize;
 
+    /// Reports how many extra bytes the (already encoded) header uses.
+    ///
+    /// This allows validating encoded data from untrusted sources before reading the extra bytes.
+    /// Headers that can't tell return `None` (which is the default).
+    #[inline]
+    fn extra_len(&self) -> Option<usize> {
+        None
+    }
+
     /// Increment the reference count.
     ///
     /// Returns a success flag. If the reference count exceeds what the header can hold, a false is
     /// returned to signal that it was *not* incremented. In that case, the
-    /// [`OwnedSlice`][crate::OwnedSlice] gets fully cloned instead.
+    /// [`on_overflow`][Header::on_overflow] decides what happens (by default, the
+    /// [`OwnedSlice`][crate::OwnedSlice] gets fully cloned instead).
     fn inc(&self) -> bool;
 
+    /// What to do when [`inc`][Header::inc] of a [`Shared`] header fails.
+    ///
+    /// The default is [`OverflowPolicy::DeepClone`]. Headers with small counters may prefer
+    /// failing loudly instead of silently multiplying the memory. [`Unique`] headers should keep
+    /// the default, as copying is the only way to clone them.
+    #[inline]
+    fn on_overflow() -> OverflowPolicy {
+        OverflowPolicy::DeepClone
+    }
+
+    /// Reports the current strong reference count.
+    ///
+    /// Meant for debugging and tests, the value may be outdated by the time it is looked at if
+    /// other threads hold references too. Headers without a reference count return `None` (which
+    /// is the default).
+    #[inline]
+    fn strong_count(&self) -> Option<usize> {
+        None
+    }
+
     /// Decrements a reference count.
     ///
     /// Returns if the reference count dropped to 0 and the slice should be destroyed.
+    ///
+    /// After destroying the data, the memory itself is freed only if
+    /// [`dec_weak`][Header::dec_weak] returns true (all the strong references together hold one
+    /// weak reference).
     fn dec(&self) -> bool;
+
+    /// Increments the weak reference count.
+    ///
+    /// Returns a success flag. Headers without support for weak references return false (which
+    /// is the default).
+    #[inline]
+    fn inc_weak(&self) -> bool {
+        false
+    }
+
+    /// Decrements the weak reference count.
+    ///
+    /// Returns if the count dropped to 0 and the memory should be freed. The default, for headers
+    /// without weak references, always returns true.
+    #[inline]
+    fn dec_weak(&self) -> bool {
+        true
+    }
+
+    /// Increments the strong reference count, unless it already dropped to 0.
+    ///
+    /// This is used to turn a weak reference into a strong one. Returns a success flag. Headers
+    /// without weak references return false (which is the default).
+    #[inline]
+    fn upgrade(&self) -> bool {
+        false
+    }
+
+    /// Checks if there's exactly one owner of the slice.
+    ///
+    /// If this returns true, it is safe to hand out mutable access to the data. Headers without
+    /// sharing support always return true, reference counted ones return true when the count is
+    /// exactly 1.
+    ///
+    /// The default returns true for the [`Unique`] ownership and false otherwise (which is always
+    /// correct, but never allows mutable access to shared slices).
+    #[inline]
+    fn is_unique(&self) -> bool {
+        !Self::Ownership::is_shared()
+    }
+
+    /// Whether the slices with this header may live in memory of the global allocator.
+    ///
+    /// Headers of slices living in memory owned by something else (like an arena) set this to
+    /// false. The constructors allocating from the global allocator then panic instead of leaking
+    /// the memory.
+    const GLOBAL_ALLOC: bool = true;
+}
+
+/// Panics if the slices with the header can't live in memory of the global allocator.
+///
+/// Called before allocating such memory for them (see [`Header::GLOBAL_ALLOC`]).
+#[inline]
+pub(crate) fn assert_global_alloc<H: Header>() {
+    assert!(
+        H::GLOBAL_ALLOC,
+        "The header doesn't support memory of the global allocator"
+    );
 }
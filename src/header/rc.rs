@@ -0,0 +1,129 @@
+use core::cell::Cell;
+
+use super::{Header, Shared, TooLong};
+use crate::BoxHeader;
+
+/// A single-threaded reference counted header.
+///
+/// This is the equivalent of [`Rc`][alloc::rc::Rc]. Cloning the [`OwnedSlice`][crate::OwnedSlice]
+/// only increments the reference count and weak references are supported (see
+/// [`OwnedSlice::downgrade`][crate::OwnedSlice::downgrade]). Slices with this header can't be
+/// sent to other threads, use the [`ArcHeader`][crate::ArcHeader] for that.
+///
+/// Both the strong and weak counts are 32bit. If the strong count would overflow, a clone panics
+/// ([`try_clone`][crate::OwnedSlice::try_clone] makes a full copy of the data instead).
+///
+/// The length is encoded the same way as in the [`BoxHeader`].
+pub struct RcHeader {
+    strong: Cell<u32>,
+    weak: Cell<u32>,
+    len: BoxHeader,
+}
+
+unsafe impl Header for RcHeader {
+    type Ownership = Shared;
+    const MAX_LEN: usize = BoxHeader::MAX_LEN;
+    #[inline]
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        BoxHeader::extra_needed(len)
+    }
+    #[inline]
+    unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+        Self {
+            strong: Cell::new(1),
+            // All the strong references together hold one weak.
+            weak: Cell::new(1),
+            len: BoxHeader::encode_len(len, extra),
+        }
+    }
+    #[inline]
+    unsafe fn decode_len(&self, extra: *const u8) -> usize {
+        self.len.decode_len(extra)
+    }
+    #[inline]
+    fn extra_len(&self) -> Option<usize> {
+        self.len.extra_len()
+    }
+    #[inline]
+    fn inc(&self) -> bool {
+        inc(&self.strong)
+    }
+    #[inline]
+    fn dec(&self) -> bool {
+        dec(&self.strong)
+    }
+    #[inline]
+    fn strong_count(&self) -> Option<usize> {
+        Some(self.strong.get() as usize)
+    }
+    #[inline]
+    fn is_unique(&self) -> bool {
+        self.strong.get() == 1 && self.weak.get() == 1
+    }
+    #[inline]
+    fn inc_weak(&self) -> bool {
+        inc(&self.weak)
+    }
+    #[inline]
+    fn dec_weak(&self) -> bool {
+        dec(&self.weak)
+    }
+    #[inline]
+    fn upgrade(&self) -> bool {
+        self.strong.get() > 0 && inc(&self.strong)
+    }
+}
+
+#[inline]
+fn inc(cnt: &Cell<u32>) -> bool {
+    match cnt.get().checked_add(1) {
+        Some(new) => {
+            cnt.set(new);
+            true
+        }
+        None => false,
+    }
+}
+
+#[inline]
+fn dec(cnt: &Cell<u32>) -> bool {
+    let new = cnt.get() - 1;
+    cnt.set(new);
+    new == 0
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use super::*;
+
+    fn header() -> RcHeader {
+        unsafe { RcHeader::encode_len(0, [].as_mut_ptr()) }
+    }
+
+    #[test]
+    fn counts() {
+        let h = header();
+        assert!(h.is_unique());
+        assert!(h.inc());
+        assert!(!h.is_unique());
+        assert_eq!(Some(2), h.strong_count());
+        assert!(!h.dec());
+        assert!(h.is_unique());
+
+        assert!(h.inc_weak());
+        assert!(!h.is_unique());
+        assert!(h.dec());
+        assert!(!h.upgrade());
+        assert!(!h.dec_weak());
+        assert!(h.dec_weak());
+    }
+
+    #[test]
+    fn overflow() {
+        let h = header();
+        h.strong.set(u32::MAX);
+        assert!(!h.inc());
+        assert!(!h.upgrade());
+        assert_eq!(u32::MAX, h.strong.get());
+    }
+}
@@ -4,7 +4,11 @@
 //! on the stack (on 64bit platform). That's a lot of overhead. One can use `Box<str>` instead,
 //! that uses only 16 bytes on the stack. With this library, `6` bytes are on the heap and 8 on the
 //! stack (no, this is not *the* short string optimization ‒ that one stops being useful at very
-//! short strings).
+//! short strings). If many of the strings are really that short, the opt-in [`InlineStr`] keeps
+//! strings of up to 7 bytes directly in the 8 bytes on the stack. Large sorted sets of strings
+//! sharing prefixes can be packed together into a `PrefixTable` (with the `prefix` feature).
+//! Very large slices can be split into chunks with [`ChunkedSlice`][rope::ChunkedSlice], to avoid
+//! single huge allocations.
 //!
 //! Also, this library works for other arrays/slices not just strings.
 //!
@@ -12,43 +16,86 @@
 //! [`OwnedSlice<T>`][OwnedSlice]) and empty slice/string doesn't allocate.
 //!
 //! The downside is, they can't change their length like [`String`] or [`Vec`]. Therefore, this is
-//! suited for storing large amounts of smallish strings.
+//! suited for storing large amounts of smallish strings. When the length is not known up front,
+//! the slice can be built in a growable [`SliceBuf`] or [`StrBuf`] first.
 //!
 //! # How does it work
 //!
 //! The length is stored as a header on the heap, followed by the actual data. The length is
 //! variable length encoded ‒ short strings take only 1 byte header, longer ones take 2 bytes...
-//! There's a limit at how large the string can be (current limit is 2^38 characters).
+//! There's a limit at how large the string can be (current limit is 2^30 elements, see
+//! [`Header::MAX_LEN`]). For really tiny slices, the [`ByteHeader`] always uses just the single
+//! byte, but is limited to 255 elements.
 //!
-//! # Future plans
+//! # Sharing
+//!
+//! The datastructures are parametrized by a [`Header`]. Apart from the default [`BoxHeader`]
+//! (unique ownership, like [`Box`]), there are the [`RcHeader`] and
+//! [`ArcHeader`] headers. These provide a limited [`Rc`][alloc::rc::Rc] or
+//! [`Arc`][alloc::sync::Arc] builtin functionality ‒ single string/slice can be shared between
+//! multiple owners, including weak references ([`WeakSlice`]). They are still sized one word on
+//! the stack.
 //!
-//! The datastructures are parametrized by a [`Header`]. The future versions will have a limited
-//! [`Arc`][std::sync::Arc] or [`Rc`][std::rc::Rc] builtin functionality ‒ it'll be possible to
-//! share single string/slice between multiple owners. They'll still be sized one word on the
-//! stack.
+//! # Grouping
 //!
-//! Also, there's a plan to be able to put multiple these variable length slices/strings inside a
-//! single allocationd behind a single pointer. Then it'll be possible to save even more on
-//! structures holding multiple shortish strings. But how the API will look like is still unknown.
+//! Multiple variable length slices can be put inside a single allocation behind a single pointer
+//! with the [`SliceGroup`][group::SliceGroup]. This saves even more on structures holding
+//! multiple shortish slices.
 //!
-//! Support for integrating with other libraries (`serde`, `heapsize`) will be added behind feature
-//! flags.
+//! # Future plans
 //!
-//! Support for allocating from an arena (eg. [`bumpalo`](https://crates.io/crates/bumpalo) to cut
-//! down on the allocator overhead might also come.
+//! Support for integrating with other libraries (`serde`) will be added behind feature flags.
 //!
 //! # Features
 //!
-//! * The `std` feature (on by default) adds some little convenience details (eg. the [`TooLong`]
-//!   implements [`std::error::Error`]). By opting out of this feature, the library needs only
-//!   [`alloc`].
+//! * The `std` feature (on by default) adds the [`OwnedOsStr`] and [`OwnedPath`] wrappers. By
+//!   opting out of this feature, the library needs only [`alloc`]. The error types implement
+//!   [`core::error::Error`] either way.
+//! * The `arbitrary` feature implements the [`Arbitrary`][arbitrary::Arbitrary] trait, for
+//!   generating the data structures in fuzzing.
+//! * The `arc-swap` feature allows storing slices and strings with the [`ArcHeader`] directly
+//!   in an [`ArcSwapAny`][arc_swap::ArcSwapAny], without wrapping them in another
+//!   [`Arc`][alloc::sync::Arc].
+//! * The `bumpalo` feature adds the `BumpHeader`, for allocating the slices inside a
+//!   [`bumpalo`](https://crates.io/crates/bumpalo) arena (to cut down on the allocator overhead).
+//! * The `bytemuck` feature allows casting the data of slices of
+//!   [`bytemuck`](https://crates.io/crates/bytemuck) plain old data types to slices of other
+//!   such types (`OwnedSlice::cast_slice`), creating zeroed slices and borrowing slices in place
+//!   from bytes (`SliceRef`).
+//! * The `bytes` feature adds the `BufOwnedSlice` cursor implementing the `Buf` trait of the
+//!   [`bytes`](https://crates.io/crates/bytes) crate and zero-copy conversion of byte slices into
+//!   `Bytes`.
+//! * The `deepsize` feature implements the [`DeepSizeOf`][deepsize::DeepSizeOf] trait, to allow
+//!   measuring the heap usage of the data structures.
+//! * The `hashbrown` feature adds the `collections` module with the `SquashSet`, a hash set of
+//!   squashed byte strings built on [`hashbrown`](https://crates.io/crates/hashbrown).
+//! * The `proptest` feature adds strategies for generating the data structures in
+//!   [`proptest`](https://crates.io/crates/proptest) tests (see the [`proptest`][mod@proptest]
+//!   module).
+//! * The `prefix` feature adds the `prefix` module, with the `PrefixTable` of sorted strings
+//!   stored with prefix compression.
+//! * The `quickcheck` feature implements the [`Arbitrary`][quickcheck::Arbitrary] trait of
+//!   [`quickcheck`](https://crates.io/crates/quickcheck), including shrinking.
+//! * The `rayon` feature allows iterating the slices in parallel and constructing them from
+//!   parallel iterators of the [`rayon`](https://crates.io/crates/rayon) crate
+//!   (`OwnedSlice::from_par_iter`). This implies the `std` feature.
+//! * The `rkyv` feature adds support for the [`rkyv`](https://crates.io/crates/rkyv) zero-copy
+//!   serialization. The archived forms are the same as of [`Vec`] and [`String`].
+//! * The `stats` feature counts the allocations made by the library (see the [`stats`] module).
+//!   It needs a target with atomic pointer-sized integers.
+//! * The `testkit` feature adds the [`testkit`] module, for checking custom [`Header`]
+//!   implementations.
+//! * The `zerocopy` feature allows viewing slices of
+//!   [`zerocopy`](https://crates.io/crates/zerocopy) compatible types as bytes and creating them
+//!   by reinterpreting bytes (`OwnedSlice::as_bytes` and `OwnedSlice::from_bytes`).
 //!
 //! # Current quirks
 //!
 //! (Some of it may be lifted in future versions)
 //!
 //! The structures dereference to slice/`str`, but explicit dereferencing may be necessary at
-//! times.
+//! times. The most common methods (like `len`, `get`, `iter` or indexing) are available directly
+//! to lower the friction.
 //!
 //! Sometimes it is needed to hint the type resolution with the right type (as in the example
 //! below).
@@ -83,18 +130,55 @@
 
 extern crate alloc;
 
-// TODO: ArcSwap support? Is it possible?
 // TODO: Serde support
-// TODO: HeapSize support
-// TODO: Bumpalo support
 // TODO: make_mut or similar APIs?
 // TODO: as_raw and similar?
 
+mod buf;
+#[cfg(feature = "hashbrown")]
+pub mod collections;
+mod ext;
+pub mod group;
 mod header;
+#[cfg(feature = "prefix")]
+pub mod prefix;
+pub mod rope;
 mod slice;
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+pub mod stats;
+#[cfg(not(all(feature = "stats", target_has_atomic = "ptr")))]
+mod stats;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
 mod wrapper;
 
+pub use buf::{SliceBuf, StrBuf};
+#[cfg(feature = "bumpalo")]
+pub use ext::bumpalo::BumpHeader;
+#[cfg(feature = "bytemuck")]
+pub use ext::bytemuck::{CastError, SliceRef};
+#[cfg(feature = "bytes")]
+pub use ext::bytes::BufOwnedSlice;
+#[cfg(feature = "proptest")]
+pub use ext::proptest;
+#[cfg(feature = "zerocopy")]
+pub use ext::zerocopy::FromBytesError;
+#[cfg(target_has_atomic = "32")]
+pub use header::arc::ArcHeader;
 pub use header::boxed::BoxHeader;
-pub use header::{Header, TooLong};
-pub use slice::OwnedSlice;
-pub use wrapper::str::Str;
+pub use header::byte::ByteHeader;
+pub use header::rc::RcHeader;
+#[cfg(feature = "std")]
+pub use header::ReadError;
+pub use header::{
+    AllocError, AllocOrTooLong, CloneStrategy, ConvertStrategy, CopyOnOverflow, DecodeError,
+    Header, OverflowPolicy, Ownership, Shared, SharedMarker, TooLong, Unique,
+};
+pub use slice::{OwnedSlice, WeakSlice};
+pub use wrapper::cstr::{CStrError, OwnedCStr};
+pub use wrapper::inline::InlineStr;
+#[cfg(feature = "std")]
+pub use wrapper::os_str::OwnedOsStr;
+#[cfg(feature = "std")]
+pub use wrapper::path::OwnedPath;
+pub use wrapper::str::{SameLenError, Str};
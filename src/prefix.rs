@@ -0,0 +1,500 @@
+//! A sorted table of strings with prefix compression.
+//!
+//! When storing a lot of sorted strings (like the keys of an autocomplete index), consecutive
+//! strings often share long prefixes. The [`PrefixTable`] stores each string only as the length
+//! of the prefix shared with the previous one and the rest of the bytes, all inside a single
+//! allocation.
+//!
+//! To keep the lookups fast, every [`RESTART_INTERVAL`]-th string is stored in full. Access to
+//! an entry needs to reconstruct only the strings since the last such restart point, and
+//! [`binary_search`][PrefixTable::binary_search] can bisect the restart points without any
+//! reconstruction at all.
+//!
+//! As the entries are reconstructed on access, they are returned through a buffer instead of
+//! directly as `&str` borrowed from the table.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use squash::prefix::PrefixTable;
+//!
+//! let table: PrefixTable = PrefixTable::from_sorted(["apple", "applet", "apply"]).unwrap();
+//! let mut buf = String::new();
+//! assert_eq!(Some("applet"), table.get(1, &mut buf));
+//! assert_eq!(Ok(2), table.binary_search("apply"));
+//! assert_eq!(Err(1), table.binary_search("applesauce"));
+//! ```
+
+use alloc::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::{self, Ordering};
+use core::error::Error;
+use core::str;
+
+use crate::{BoxHeader, Header, OwnedSlice, TooLong};
+
+/// Every this many entries, the string is stored in full.
+pub const RESTART_INTERVAL: usize = 16;
+
+/// An error when building the [`PrefixTable`].
+#[derive(Copy, Clone, Debug)]
+pub enum PrefixError {
+    /// The strings are not sorted, the one at the given index is smaller than the previous one.
+    Unsorted(usize),
+    /// The table is too long for the header.
+    TooLong(TooLong),
+}
+
+impl Display for PrefixError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            PrefixError::Unsorted(idx) => write!(fmt, "String at index {} is out of order", idx),
+            PrefixError::TooLong(e) => Display::fmt(e, fmt),
+        }
+    }
+}
+
+impl Error for PrefixError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PrefixError::Unsorted(_) => None,
+            PrefixError::TooLong(e) => Some(e),
+        }
+    }
+}
+
+impl From<TooLong> for PrefixError {
+    fn from(e: TooLong) -> Self {
+        PrefixError::TooLong(e)
+    }
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        data.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*pos];
+        *pos += 1;
+        value |= usize::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// Length of the common prefix, backed off to a char boundary.
+fn common_prefix(a: &str, b: &str) -> usize {
+    let mut len = a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count();
+    while !a.is_char_boundary(len) {
+        len -= 1;
+    }
+    len
+}
+
+/// A builder of the [`PrefixTable`].
+///
+/// The strings are pushed one by one in sorted order (equal consecutive strings are allowed).
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::prefix::{PrefixBuilder, PrefixTable};
+///
+/// let mut builder = PrefixBuilder::new();
+/// builder.push("hello").unwrap();
+/// builder.push("help").unwrap();
+/// assert!(builder.push("hell").is_err());
+/// let table: PrefixTable = builder.finish().unwrap();
+/// assert_eq!(2, table.len());
+/// ```
+#[derive(Debug, Default)]
+pub struct PrefixBuilder {
+    data: Vec<u8>,
+    restarts: Vec<usize>,
+    last: String,
+    len: usize,
+}
+
+impl PrefixBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends another string.
+    ///
+    /// # Errors
+    ///
+    /// If the string is smaller than the previous one, [`PrefixError::Unsorted`] is returned and
+    /// the builder is left unchanged.
+    pub fn push(&mut self, s: &str) -> Result<(), PrefixError> {
+        if self.len > 0 && s < self.last.as_str() {
+            return Err(PrefixError::Unsorted(self.len));
+        }
+
+        let prefix = if self.len.is_multiple_of(RESTART_INTERVAL) {
+            self.restarts.push(self.data.len());
+            0
+        } else {
+            common_prefix(&self.last, s)
+        };
+        let suffix = &s[prefix..];
+        write_varint(&mut self.data, prefix);
+        write_varint(&mut self.data, suffix.len());
+        self.data.extend_from_slice(suffix.as_bytes());
+        self.last.truncate(prefix);
+        self.last.push_str(suffix);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Turns the builder into the final table.
+    ///
+    /// # Errors
+    ///
+    /// If the data is too long for the header, [`TooLong`] is returned.
+    pub fn finish<H>(self) -> Result<PrefixTable<H>, TooLong>
+    where
+        H: Header,
+    {
+        Ok(PrefixTable {
+            data: OwnedSlice::new(&self.data)?,
+            restarts: OwnedSlice::new(&self.restarts)?,
+            len: self.len,
+        })
+    }
+}
+
+/// A sorted table of strings with prefix compression.
+///
+/// See the [module documentation][self] for details.
+pub struct PrefixTable<H: Header = BoxHeader> {
+    data: OwnedSlice<u8, H>,
+    restarts: OwnedSlice<usize, H>,
+    len: usize,
+}
+
+impl<H> PrefixTable<H>
+where
+    H: Header,
+{
+    /// Builds the table from sorted strings.
+    ///
+    /// # Errors
+    ///
+    /// If the strings are not sorted, [`PrefixError::Unsorted`] is returned. If the data is too
+    /// long for the header, [`PrefixError::TooLong`] is.
+    pub fn from_sorted<'a, I>(iter: I) -> Result<Self, PrefixError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut builder = PrefixBuilder::new();
+        for s in iter {
+            builder.push(s)?;
+        }
+        builder.finish().map_err(PrefixError::from)
+    }
+
+    /// Number of strings in the table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the table contains no strings.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many bytes the heap allocations take (not counting any allocator overhead).
+    pub fn heap_bytes(&self) -> usize {
+        self.data.heap_size() + self.restarts.heap_size()
+    }
+
+    /// Decodes the entry at the position, returning the shared prefix length and the suffix.
+    ///
+    /// The position is moved to the next entry.
+    fn entry(&self, pos: &mut usize) -> (usize, &str) {
+        let prefix = read_varint(&self.data, pos);
+        let len = read_varint(&self.data, pos);
+        let suffix = &self.data[*pos..*pos + len];
+        *pos += len;
+        // We've written it from a str, split at a char boundary.
+        (prefix, unsafe { str::from_utf8_unchecked(suffix) })
+    }
+
+    /// Reconstructs the string at the given index into the buffer.
+    ///
+    /// Returns `None` if the index is out of bounds (the buffer is left in unspecified state
+    /// otherwise).
+    pub fn get<'b>(&self, idx: usize, buf: &'b mut String) -> Option<&'b str> {
+        if idx >= self.len {
+            return None;
+        }
+
+        let restart = idx / RESTART_INTERVAL;
+        let mut pos = self.restarts[restart];
+        buf.clear();
+        for _ in restart * RESTART_INTERVAL..=idx {
+            let (prefix, suffix) = self.entry(&mut pos);
+            buf.truncate(prefix);
+            buf.push_str(suffix);
+        }
+
+        Some(buf.as_str())
+    }
+
+    /// Searches for the string.
+    ///
+    /// The semantics are the same as of [`binary_search`][slice::binary_search] on a slice. If
+    /// the string is present, `Ok` with its index is returned (any of them if it is present
+    /// multiple times). Otherwise, `Err` with the index where it could be inserted is.
+    pub fn binary_search(&self, key: &str) -> Result<usize, usize> {
+        // The restart entries are stored in full, so they can be compared directly.
+        let block = match self
+            .restarts
+            .binary_search_by(|&pos| self.entry(&mut { pos }).1.cmp(key))
+        {
+            Ok(restart) => return Ok(restart * RESTART_INTERVAL),
+            Err(0) => return Err(0),
+            Err(restart) => restart - 1,
+        };
+
+        let start = block * RESTART_INTERVAL;
+        let end = cmp::min(start + RESTART_INTERVAL, self.len);
+        let mut pos = self.restarts[block];
+        let mut buf = String::new();
+        for idx in start..end {
+            let (prefix, suffix) = self.entry(&mut pos);
+            buf.truncate(prefix);
+            buf.push_str(suffix);
+            match buf.as_str().cmp(key) {
+                Ordering::Less => (),
+                Ordering::Equal => return Ok(idx),
+                Ordering::Greater => return Err(idx),
+            }
+        }
+
+        Err(end)
+    }
+
+    /// Iterates through the strings, in order.
+    pub fn iter(&self) -> Iter<'_, H> {
+        Iter {
+            table: self,
+            pos: 0,
+            remaining: self.len,
+            buf: String::new(),
+        }
+    }
+}
+
+impl<H> Clone for PrefixTable<H>
+where
+    H: Header,
+{
+    fn clone(&self) -> Self {
+        Self {
+            data: self
+                .data
+                .try_clone()
+                .expect("Already have layout for this size"),
+            restarts: self
+                .restarts
+                .try_clone()
+                .expect("Already have layout for this size"),
+            len: self.len,
+        }
+    }
+}
+
+impl<H> Debug for PrefixTable<H>
+where
+    H: Header,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let mut list = fmt.debug_list();
+        let mut iter = self.iter();
+        while let Some(s) = iter.next() {
+            list.entry(&s);
+        }
+        list.finish()
+    }
+}
+
+/// An iterator through the [`PrefixTable`].
+///
+/// The strings are reconstructed into an internal buffer, therefore each one is valid only until
+/// the next call. Because of that, this doesn't implement the [`Iterator`] trait and needs to be
+/// driven by a `while let` loop.
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::prefix::PrefixTable;
+///
+/// let table: PrefixTable = PrefixTable::from_sorted(["a", "ab", "abc"]).unwrap();
+/// let mut iter = table.iter();
+/// let mut lens = Vec::new();
+/// while let Some(s) = iter.next() {
+///     lens.push(s.len());
+/// }
+/// assert_eq!(vec![1, 2, 3], lens);
+/// ```
+pub struct Iter<'a, H: Header> {
+    table: &'a PrefixTable<H>,
+    pos: usize,
+    remaining: usize,
+    buf: String,
+}
+
+impl<H> Iter<'_, H>
+where
+    H: Header,
+{
+    /// Returns the next string, or `None` at the end.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&str> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (prefix, suffix) = self.table.entry(&mut self.pos);
+        self.buf.truncate(prefix);
+        self.buf.push_str(suffix);
+        self.remaining -= 1;
+        Some(self.buf.as_str())
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use super::*;
+    use crate::{ArcHeader, Str};
+
+    fn collect<H: Header>(table: &PrefixTable<H>) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut iter = table.iter();
+        while let Some(s) = iter.next() {
+            result.push(s.to_owned());
+        }
+        result
+    }
+
+    /// Checks all the access paths against the plain sorted strings.
+    fn check(strings: &[&str]) {
+        let table: PrefixTable = PrefixTable::from_sorted(strings.iter().copied()).unwrap();
+        assert_eq!(strings.len(), table.len());
+        assert_eq!(strings, collect(&table).as_slice());
+        let mut buf = String::new();
+        for (idx, s) in strings.iter().enumerate() {
+            assert_eq!(Some(*s), table.get(idx, &mut buf));
+            let found = table.binary_search(s).unwrap();
+            assert_eq!(*s, strings[found]);
+        }
+        assert_eq!(None, table.get(strings.len(), &mut buf));
+    }
+
+    #[test]
+    fn empty() {
+        let table: PrefixTable = PrefixTable::from_sorted([]).unwrap();
+        assert!(table.is_empty());
+        assert_eq!(0, table.heap_bytes());
+        assert_eq!(Err(0), table.binary_search("hello"));
+        assert!(table.iter().next().is_none());
+        assert_eq!("[]", format!("{:?}", table));
+    }
+
+    #[test]
+    fn identical() {
+        let strings = vec!["same"; 40];
+        check(&strings);
+    }
+
+    #[test]
+    fn empty_strings() {
+        check(&["", "", "a", "a", "ab"]);
+        let table: PrefixTable = PrefixTable::from_sorted(["", "b"]).unwrap();
+        assert_eq!(Ok(0), table.binary_search(""));
+        assert_eq!(Err(1), table.binary_search("a"));
+        assert_eq!(Err(2), table.binary_search("c"));
+    }
+
+    #[test]
+    fn suffix_byte() {
+        let strings = (0..50u8)
+            .map(|i| format!("long shared prefix {}", char::from(b'0' + i)))
+            .collect::<Vec<_>>();
+        let strings = strings.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        check(&strings);
+    }
+
+    #[test]
+    fn unicode() {
+        // Share the first byte of the multi-byte chars, but not the whole char.
+        check(&["aé", "aê", "aê", "aêb", "ž"]);
+    }
+
+    #[test]
+    fn absent() {
+        let strings = (0..100)
+            .map(|i| format!("{:04}", i * 2))
+            .collect::<Vec<_>>();
+        let table: PrefixTable<ArcHeader> =
+            PrefixTable::from_sorted(strings.iter().map(|s| s.as_str())).unwrap();
+        for i in 0..100 {
+            assert_eq!(Ok(i), table.binary_search(&format!("{:04}", i * 2)));
+            assert_eq!(
+                Err(i + 1),
+                table.binary_search(&format!("{:04}", i * 2 + 1))
+            );
+            assert_eq!(Err(i + 1), table.binary_search(&format!("{:04}x", i * 2)));
+        }
+        assert_eq!(Err(0), table.binary_search(""));
+        assert_eq!(Err(100), table.binary_search("z"));
+        assert_eq!(collect(&table), collect(&table.clone()));
+    }
+
+    #[test]
+    fn unsorted() {
+        match PrefixTable::<BoxHeader>::from_sorted(["a", "c", "b"]) {
+            Err(PrefixError::Unsorted(2)) => (),
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let mut builder = PrefixBuilder::new();
+        builder.push("b").unwrap();
+        builder.push("a").unwrap_err();
+        builder.push("c").unwrap();
+        let table: PrefixTable = builder.finish().unwrap();
+        assert_eq!(vec!["b", "c"], collect(&table));
+    }
+
+    /// Shared prefixes make it smaller than separately allocated strings.
+    #[test]
+    fn saves_memory() {
+        let strings = (0..1000)
+            .map(|i| format!("https://example.com/some/path/{:05}", i))
+            .collect::<Vec<_>>();
+        let table: PrefixTable =
+            PrefixTable::from_sorted(strings.iter().map(|s| s.as_str())).unwrap();
+        let separate = strings
+            .iter()
+            .map(|s| Str::<BoxHeader>::new(s).unwrap())
+            .collect::<Vec<_>>();
+        let separate_bytes = separate.len() * core::mem::size_of::<Str>()
+            + separate.iter().map(|s| s.0.heap_size()).sum::<usize>();
+        assert!(table.heap_bytes() * 4 < separate_bytes);
+    }
+}
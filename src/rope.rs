@@ -0,0 +1,372 @@
+//! Large slices split into fixed-size chunks.
+//!
+//! An [`OwnedSlice`] always lives in a single allocation. For slices of hundreds of megabytes
+//! that may be a problem for the allocator (it fragments the memory in long-running processes).
+//! The [`ChunkedSlice`] stores the data in chunks of [`CHUNK_BYTES`] bytes of data each, with only
+//! a small table of pointers to the chunks in another allocation. It is still only one pointer on
+//! the stack.
+//!
+//! Both the table and the chunks are [`OwnedSlice`]s, so they encode their lengths the same way
+//! and have the same limits.
+//!
+//! As the data is not contiguous, the [`ChunkedSlice`] can't dereference to a slice. The elements
+//! are accessed through [`get`][ChunkedSlice::get], [`iter`][ChunkedSlice::iter] or chunk by chunk
+//! with [`chunks`][ChunkedSlice::chunks].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use squash::rope::ChunkedSlice;
+//!
+//! let data = vec![42u8; 100_000];
+//! let s: ChunkedSlice<u8> = ChunkedSlice::new(&data).unwrap();
+//! assert_eq!(100_000, s.len());
+//! assert_eq!(Some(&42), s.get(99_999));
+//! assert_eq!(2, s.chunks().count());
+//! assert_eq!(data, s.copy_to_vec());
+//! ```
+
+use alloc::fmt::{Debug, Formatter, Result as FmtResult};
+use alloc::vec::Vec;
+use core::iter::{FlatMap, FusedIterator};
+use core::mem;
+use core::slice;
+
+use crate::{BoxHeader, CloneStrategy, Header, OwnedSlice, TooLong};
+
+/// Size of the data in a single chunk, in bytes.
+///
+/// Elements are not split across chunks, so a chunk holds `CHUNK_BYTES / size_of::<T>()` of them
+/// (but at least one, even if the element is larger than this).
+///
+/// This is the size of the elements only. Each chunk is an [`OwnedSlice`], so its allocation is
+/// slightly larger because of the header, the extra length bytes and the padding (see
+/// [`heap_bytes`][ChunkedSlice::heap_bytes]).
+pub const CHUNK_BYTES: usize = 64 * 1024;
+
+/// A slice stored in fixed-size chunks.
+///
+/// See the [module documentation][self] for details.
+pub struct ChunkedSlice<T, H = BoxHeader>
+where
+    H: Header,
+{
+    chunks: OwnedSlice<OwnedSlice<T, H>, H>,
+}
+
+impl<T, H> ChunkedSlice<T, H>
+where
+    H: Header,
+{
+    /// Number of elements in each chunk (except for the last one, which may be shorter).
+    pub const CHUNK_LEN: usize = match mem::size_of::<T>() {
+        // Zero-sized types take no memory, no reason to split them.
+        0 => usize::MAX,
+        size if size > CHUNK_BYTES => 1,
+        size => CHUNK_BYTES / size,
+    };
+
+    /// Creates the chunked slice by cloning the elements.
+    ///
+    /// # Errors
+    ///
+    /// If the chunk table or a chunk is too long for the header, [`TooLong`] is returned. With the
+    /// built-in headers this happens only with way too many chunks or with zero-sized types.
+    pub fn new(src: &[T]) -> Result<Self, TooLong>
+    where
+        T: Clone,
+    {
+        let count = src.len().div_ceil(Self::CHUNK_LEN);
+        let chunks = src.chunks(Self::CHUNK_LEN).map(OwnedSlice::new);
+        Self::from_chunks(count, chunks)
+    }
+
+    /// Creates the chunked slice from an iterator.
+    ///
+    /// Unlike with [`Vec`], the final data is never copied in one large buffer, so the iterator
+    /// doesn't need to know its length in advance. Only a single chunk is buffered at a time.
+    ///
+    /// # Errors
+    ///
+    /// If the chunk table or a chunk is too long for the header, [`TooLong`] is returned.
+    pub fn from_iter<I>(iter: I) -> Result<Self, TooLong>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iter.into_iter();
+        let mut buf = Vec::new();
+        let mut chunks = Vec::new();
+        loop {
+            buf.extend(iter.by_ref().take(Self::CHUNK_LEN));
+            if buf.is_empty() {
+                break;
+            }
+            // Don't buffer chunks the table couldn't hold anyway, the iterator may be endless.
+            if chunks.len() >= H::MAX_LEN {
+                return Err(TooLong {
+                    len: chunks.len() + 1,
+                    max: H::MAX_LEN,
+                });
+            }
+            let full = buf.len() == Self::CHUNK_LEN;
+            chunks.push(OwnedSlice::from_iter_len(buf.len(), buf.drain(..))?);
+            if !full {
+                break;
+            }
+        }
+
+        Self::from_chunks(chunks.len(), chunks.into_iter().map(Ok))
+    }
+
+    fn from_chunks<I>(count: usize, chunks: I) -> Result<Self, TooLong>
+    where
+        I: Iterator<Item = Result<OwnedSlice<T, H>, TooLong>>,
+    {
+        // Make sure the table can hold the chunks before allocating any of them.
+        OwnedSlice::<OwnedSlice<T, H>, H>::layout_for_len(count)?;
+        let chunks = chunks.collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            chunks: OwnedSlice::from_iter_len(count, chunks)?,
+        })
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        match self.chunks.last() {
+            Some(last) => (self.chunks.len() - 1) * Self::CHUNK_LEN + last.len(),
+            None => 0,
+        }
+    }
+
+    /// Returns `true` if there are no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Returns the element at the index, or `None` if it is out of bounds.
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        self.chunks
+            .get(idx / Self::CHUNK_LEN)?
+            .get(idx % Self::CHUNK_LEN)
+    }
+
+    /// Iterates through the elements.
+    pub fn iter(&self) -> Iter<'_, T, H> {
+        Iter(self.chunks.iter().flat_map(OwnedSlice::iter))
+    }
+
+    /// Iterates through the chunks.
+    ///
+    /// All the chunks are [`CHUNK_LEN`][Self::CHUNK_LEN] long, except for the last one which
+    /// may be shorter. No chunk is empty.
+    pub fn chunks(&self) -> impl ExactSizeIterator<Item = &[T]> + DoubleEndedIterator + '_ {
+        self.chunks.iter().map(|chunk| &chunk[..])
+    }
+
+    /// Copies all the elements into a single [`Vec`].
+    pub fn copy_to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let mut result = Vec::with_capacity(self.len());
+        for chunk in self.chunks() {
+            result.extend_from_slice(chunk);
+        }
+        result
+    }
+
+    /// How many bytes the heap allocations take (not counting any allocator overhead).
+    pub fn heap_bytes(&self) -> usize {
+        self.chunks.heap_size() + self.chunks.iter().map(OwnedSlice::heap_size).sum::<usize>()
+    }
+}
+
+impl<T, H> Clone for ChunkedSlice<T, H>
+where
+    H: Header,
+    H::Ownership: CloneStrategy<T> + CloneStrategy<OwnedSlice<T, H>>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+        }
+    }
+}
+
+impl<T, H> Default for ChunkedSlice<T, H>
+where
+    H: Header,
+{
+    fn default() -> Self {
+        Self {
+            chunks: OwnedSlice::default(),
+        }
+    }
+}
+
+impl<T, H> Debug for ChunkedSlice<T, H>
+where
+    T: Debug,
+    H: Header,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, H> IntoIterator for &'a ChunkedSlice<T, H>
+where
+    H: Header,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+type ChunkIter<'a, T, H> = FlatMap<
+    slice::Iter<'a, OwnedSlice<T, H>>,
+    slice::Iter<'a, T>,
+    fn(&'a OwnedSlice<T, H>) -> slice::Iter<'a, T>,
+>;
+
+/// An iterator through the elements of [`ChunkedSlice`].
+///
+/// See [`ChunkedSlice::iter`].
+pub struct Iter<'a, T, H>(ChunkIter<'a, T, H>)
+where
+    H: Header;
+
+impl<'a, T, H> Iterator for Iter<'a, T, H>
+where
+    H: Header,
+{
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        self.0.next()
+    }
+}
+
+impl<T, H> DoubleEndedIterator for Iter<'_, T, H>
+where
+    H: Header,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+impl<T, H> FusedIterator for Iter<'_, T, H> where H: Header {}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use std::iter;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::header::mock::ShortMockHeader;
+    use crate::RcHeader;
+
+    const LEN: usize = ChunkedSlice::<u64>::CHUNK_LEN;
+
+    fn check(data: &[u64]) {
+        let s: ChunkedSlice<u64> = ChunkedSlice::new(data).unwrap();
+        assert_eq!(data.len(), s.len());
+        assert_eq!(data.is_empty(), s.is_empty());
+        assert_eq!(data, s.copy_to_vec());
+        assert!(data.iter().eq(s.iter()));
+        assert!(data.iter().rev().eq(s.iter().rev()));
+        assert!(data.chunks(LEN).eq(s.chunks()));
+        for (idx, val) in data.iter().enumerate() {
+            assert_eq!(Some(val), s.get(idx));
+        }
+        assert_eq!(None, s.get(data.len()));
+
+        let s2: ChunkedSlice<u64> = ChunkedSlice::from_iter(data.iter().copied()).unwrap();
+        assert_eq!(data, s2.copy_to_vec());
+        assert!(data.chunks(LEN).eq(s2.chunks()));
+    }
+
+    #[test]
+    fn empty() {
+        check(&[]);
+        let s = ChunkedSlice::<u64>::default();
+        assert_eq!(0, s.chunks().count());
+        assert_eq!(0, s.heap_bytes());
+    }
+
+    #[test]
+    fn chunk_boundaries() {
+        assert_eq!(CHUNK_BYTES / 8, LEN);
+        for len in [1, LEN - 1, LEN, LEN + 1, 2 * LEN, 3 * LEN - 1] {
+            let data = (0..len as u64).collect::<Vec<_>>();
+            check(&data);
+        }
+
+        // The data fill the chunk exactly, the header and padding come on top of that.
+        let full: ChunkedSlice<u64> = ChunkedSlice::new(&vec![0; LEN]).unwrap();
+        assert_eq!(CHUNK_BYTES, mem::size_of_val(full.chunks().next().unwrap()));
+        assert!(full.heap_bytes() > CHUNK_BYTES);
+    }
+
+    #[test]
+    fn element_sizes() {
+        assert_eq!(1, ChunkedSlice::<[u8; CHUNK_BYTES + 1]>::CHUNK_LEN);
+        assert_eq!(usize::MAX, ChunkedSlice::<()>::CHUNK_LEN);
+        let s: ChunkedSlice<()> = ChunkedSlice::new(&[(); 1000]).unwrap();
+        assert_eq!(1, s.chunks().count());
+        assert_eq!(1000, s.len());
+    }
+
+    /// Every element gets dropped exactly once, in all the chunks.
+    #[test]
+    fn drop_all() {
+        let counter = Rc::new(());
+        let data = vec![Rc::clone(&counter); LEN * 2 + 3];
+        let s: ChunkedSlice<Rc<()>> = ChunkedSlice::new(&data).unwrap();
+        assert_eq!(2 * data.len() + 1, Rc::strong_count(&counter));
+        let s2: ChunkedSlice<Rc<()>> = ChunkedSlice::from_iter(data).unwrap();
+        assert_eq!(2 * (LEN * 2 + 3) + 1, Rc::strong_count(&counter));
+        drop(s);
+        drop(s2);
+        assert_eq!(1, Rc::strong_count(&counter));
+    }
+
+    #[test]
+    fn shared() {
+        let data = vec![String::from("hello"); LEN + 1];
+        let s: ChunkedSlice<String, RcHeader> = ChunkedSlice::new(&data).unwrap();
+        let s2 = s.clone();
+        assert!(OwnedSlice::ptr_eq(&s.chunks, &s2.chunks));
+        drop(s);
+        assert_eq!(data, s2.copy_to_vec());
+    }
+
+    #[test]
+    fn too_many_chunks() {
+        // One element per chunk, but the header can encode only 4 of them.
+        type Big = [u8; CHUNK_BYTES];
+        assert_eq!(1, ChunkedSlice::<Big, ShortMockHeader>::CHUNK_LEN);
+        let data = vec![[0u8; CHUNK_BYTES]; 5];
+        ChunkedSlice::<Big, ShortMockHeader>::new(&data[..4]).unwrap();
+        ChunkedSlice::<Big, ShortMockHeader>::new(&data).unwrap_err();
+        ChunkedSlice::<Big, ShortMockHeader>::from_iter(data).unwrap_err();
+    }
+
+    /// The iterator is abandoned as soon as the chunks no longer fit, even if it never ends.
+    #[test]
+    fn from_iter_endless() {
+        type Big = [u8; CHUNK_BYTES];
+        let mut taken = 0;
+        let endless = iter::repeat([0u8; CHUNK_BYTES]).inspect(|_| taken += 1);
+        let err = ChunkedSlice::<Big, ShortMockHeader>::from_iter(endless).unwrap_err();
+        assert_eq!(ShortMockHeader::MAX_LEN, err.max);
+        assert_eq!(5, taken);
+    }
+}
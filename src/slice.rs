@@ -1,13 +1,30 @@
-use alloc::alloc::{alloc as mem_alloc, dealloc as mem_dealloc, handle_alloc_error, Layout};
-use alloc::fmt::{Debug, Formatter, Result as FmtResult};
+use alloc::alloc::{handle_alloc_error, Layout};
+use alloc::boxed::Box;
+use alloc::fmt::{
+    Alignment, Debug, Display, Formatter, LowerHex, Result as FmtResult, UpperHex, Write as _,
+};
+use alloc::vec;
+use alloc::vec::Vec;
 use core::cell::Cell;
+use core::cmp::{self, Ordering};
+use core::convert::{Infallible, TryFrom, TryInto};
+use core::ffi::c_void;
 use core::marker::PhantomData;
-use core::mem;
-use core::ops::{Deref, DerefMut};
+use core::mem::{self, ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut, Index, IndexMut};
 use core::ptr::{self, NonNull};
-use core::slice;
+use core::slice::{self, SliceIndex};
+#[cfg(feature = "std")]
+use std::io::Read;
 
-use crate::{BoxHeader, Header, TooLong};
+use crate::header::assert_global_alloc;
+use crate::stats::{self, mem_alloc, mem_alloc_zeroed, mem_dealloc, mem_realloc};
+#[cfg(feature = "std")]
+use crate::ReadError;
+use crate::{
+    AllocError, AllocOrTooLong, BoxHeader, CloneStrategy, ConvertStrategy, CopyOnOverflow,
+    DecodeError, Header, Ownership, Shared, TooLong, Unique,
+};
 
 // We want to have the null pointer optimisation but we also don't want to allocate for empty
 // slices. That means we need some pointer that denotes an empty slice that we recognize and won't
@@ -35,6 +52,12 @@ static ZERO_SENTINEL: u8 = 0;
 ///
 /// The heap layout is the header, followed by exactly the number of extra bytes the header needed
 /// to encode the length, followed by the actual slice data, with alignments taken into account.
+///
+/// The slice itself is guaranteed to have the same layout as a single non-null pointer. That is,
+/// it has the size of `usize` and `Option<OwnedSlice<T, H>>` has the same size too (the null
+/// pointer niche is available). For passing it through FFI as an opaque handle, see
+/// [`into_opaque_ptr`][OwnedSlice::into_opaque_ptr].
+#[repr(transparent)]
 pub struct OwnedSlice<T, H = BoxHeader>
 where
     H: Header,
@@ -43,39 +66,153 @@ where
     _data: PhantomData<T>,
 }
 
+// The layout guarantees, checked at build time.
+const _: () = assert!(mem::size_of::<OwnedSlice<u8>>() == mem::size_of::<usize>());
+const _: () = assert!(mem::size_of::<Option<OwnedSlice<u8>>>() == mem::size_of::<usize>());
+const _: () =
+    assert!(mem::size_of::<OwnedSlice<u32, crate::RcHeader>>() == mem::size_of::<usize>());
+const _: () = assert!(mem::align_of::<OwnedSlice<u64>>() == mem::align_of::<usize>());
+
 impl<T, H> OwnedSlice<T, H>
 where
     H: Header,
 {
+    /// The longest slice the header is able to encode.
+    ///
+    /// See [`Header::MAX_LEN`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{BoxHeader, Header, OwnedSlice};
+    /// assert_eq!(BoxHeader::MAX_LEN, OwnedSlice::<u8>::MAX_LEN);
+    /// assert!(OwnedSlice::<u8>::layout_for_len(OwnedSlice::<u8>::MAX_LEN + 1).is_err());
+    /// ```
+    pub const MAX_LEN: usize = H::MAX_LEN;
+
+    /// Returns the number of elements in the slice.
+    ///
+    /// This is the same as the [`len`][slice::len] of the slice it dereferences to, but callable
+    /// without the dereference.
     #[inline]
-    fn len(&self) -> usize {
+    pub fn len(&self) -> usize {
         if self.is_sentinel() {
             return 0;
         }
 
-        unsafe {
-            let header = &*self.header.as_ref();
-            let len_data = self.header.as_ptr().cast::<u8>().add(Self::len_offset());
-            header.decode_len(len_data)
-        }
+        unsafe { Self::decode_len(self.header) }
+    }
+
+    /// Drops the current content and makes the slice empty.
+    ///
+    /// This is the same as assigning [`OwnedSlice::default()`][Default::default], which doesn't
+    /// allocate. If the data is shared, only this reference to it is released.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let mut s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// s.clear();
+    /// assert!(s.is_empty());
+    /// ```
+    #[inline]
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns `true` if the slice has no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the first element, or `None` if the slice is empty.
+    #[inline]
+    pub fn first(&self) -> Option<&T> {
+        self.deref().first()
+    }
+
+    /// Returns the last element, or `None` if the slice is empty.
+    #[inline]
+    pub fn last(&self) -> Option<&T> {
+        self.deref().last()
+    }
+
+    /// Returns an iterator over the elements.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<char> = OwnedSlice::new(&['a', 'b']).unwrap();
+    /// let mut it = s.iter();
+    /// assert_eq!(Some(&'a'), it.next());
+    /// assert_eq!(Some(&'b'), it.next());
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.deref().iter()
+    }
+
+    /// Returns an element or a subslice, or `None` if out of bounds.
+    ///
+    /// The same as [`get`][slice::get] on the slice. The mutable counterpart is available on the
+    /// dereferenced slice only (`s[..].get_mut(idx)`), as the
+    /// [`get_mut`][OwnedSlice::get_mut] name is taken by the access to the whole slice.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// assert_eq!(Some(&2), s.get(1));
+    /// assert_eq!(Some(&[2, 3][..]), s.get(1..));
+    /// assert_eq!(None, s.get(3));
+    /// ```
+    #[inline]
+    pub fn get<I>(&self, index: I) -> Option<&I::Output>
+    where
+        I: SliceIndex<[T]>,
+    {
+        self.deref().get(index)
+    }
+
+    /// Decodes the length from a (non-sentinel) header.
+    #[inline]
+    unsafe fn decode_len(header: NonNull<H>) -> usize {
+        let len_data = header.as_ptr().cast::<u8>().add(Self::len_offset());
+        header.as_ref().decode_len(len_data)
     }
 
     #[inline]
     fn len_offset() -> usize {
-        Layout::new::<H>()
-            .extend(Layout::array::<u8>(0).unwrap())
-            .unwrap()
-            .1
+        // The length bytes have alignment of 1, so they come right after the header.
+        let offset = mem::size_of::<H>();
+        debug_assert_eq!(offset, Self::layout_and_offsets(0).unwrap().1);
+        offset
     }
 
     #[inline]
-    fn layout_and_offsets(len: usize) -> Result<(Layout, usize, usize), TooLong> {
+    pub(crate) fn layout_and_offsets(len: usize) -> Result<(Layout, usize, usize), TooLong> {
         let extra = H::extra_needed(len)?;
+        // The header can encode the length, but the elements may still not fit into the address
+        // space if they are large.
+        let too_long = |_| TooLong {
+            len,
+            max: cmp::min(
+                H::MAX_LEN,
+                isize::MAX as usize / cmp::max(mem::size_of::<T>(), 1),
+            ),
+        };
         let (l1, len_off) = Layout::new::<H>()
-            .extend(Layout::array::<u8>(extra).expect("Insanely large stuff"))
-            .expect("Insanely large stuff");
-        let data_layout = Layout::array::<T>(len).expect("Insanely large stuff");
-        let (layout, data_off) = l1.extend(data_layout).expect("Insanely large stuff");
+            .extend(Layout::array::<u8>(extra).map_err(too_long)?)
+            .map_err(too_long)?;
+        let data_layout = Layout::array::<T>(len).map_err(too_long)?;
+        let (layout, data_off) = l1.extend(data_layout).map_err(too_long)?;
+        // The allocation is aligned to at least align_of::<T>, so this makes the data aligned too.
+        debug_assert!(data_off.is_multiple_of(mem::align_of::<T>()));
         Ok((layout, len_off, data_off))
     }
 
@@ -86,22 +223,133 @@ where
 
     #[inline]
     fn data_offset(len: usize) -> usize {
-        Self::layout_and_offsets(len).unwrap().2
+        // The extra bytes have no alignment, so they follow right after the header and the data
+        // is padded to its own alignment. That's what the layout computation does too, but this
+        // is much cheaper than building the whole layout on every access.
+        let extra = H::extra_needed(len).expect("Already encoded this length");
+        let offset = (mem::size_of::<H>() + extra).next_multiple_of(mem::align_of::<T>());
+        debug_assert_eq!(offset, Self::layout_and_offsets(len).unwrap().2);
+        offset
     }
 
     #[inline]
     fn data(&self, len: usize) -> *mut T {
         let offset = Self::data_offset(len);
-        unsafe { self.header.as_ptr().cast::<u8>().add(offset).cast::<T>() }
+        let data = unsafe { self.header.as_ptr().cast::<u8>().add(offset).cast::<T>() };
+        debug_assert!(data.is_aligned());
+        data
+    }
+
+    /// How many bytes the heap allocation takes (not counting any allocator overhead).
+    pub(crate) fn heap_size(&self) -> usize {
+        if self.is_sentinel() {
+            0
+        } else {
+            Self::layout(self.len()).size()
+        }
     }
 
     #[inline]
-    fn is_sentinel(&self) -> bool {
+    pub(crate) fn is_sentinel(&self) -> bool {
         ptr::eq(self.header.as_ptr().cast::<u8>(), &ZERO_SENTINEL)
     }
 
+    /// Computes the layout of the heap allocation needed for a slice of the given length.
+    ///
+    /// This is useful together with [`init_in_place`][OwnedSlice::init_in_place], for placing the
+    /// slice into memory obtained from somewhere else than the global allocator.
+    ///
+    /// # Errors
+    ///
+    /// If the length is bigger than the header can encode or the allocation would be larger than
+    /// the address space allows, [`TooLong`] is returned.
+    pub fn layout_for_len(len: usize) -> Result<Layout, TooLong> {
+        Self::layout_and_offsets(len).map(|(layout, _, _)| layout)
+    }
+
+    /// Creates a new owned slice inside caller-provided memory, by cloning the content of the
+    /// passed slice.
+    ///
+    /// The header, length and the data are written into the memory at `ptr`. The result is
+    /// wrapped in [`ManuallyDrop`], because it must not be dropped normally ‒ that would hand the
+    /// memory to the global allocator. It can be used (and cloned) as usual otherwise. Note that
+    /// clones with headers that don't share the data (like [`BoxHeader`]) are allocated by the
+    /// global allocator and can be dropped normally.
+    ///
+    /// The destructors of the elements are not run. If that is needed, it can be done by
+    /// [`ptr::drop_in_place`] on the data once the slice is no longer in use.
+    ///
+    /// An empty slice doesn't use the memory at all.
+    ///
+    /// # Errors
+    ///
+    /// If the slice is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Safety
+    ///
+    /// * The `ptr` must be valid for writes of at least
+    ///   [`layout_for_len(src.len())`][OwnedSlice::layout_for_len]`.size()` bytes and aligned to
+    ///   its `align()`.
+    /// * The memory must stay valid and must not be accessed by other means for as long as the
+    ///   returned slice or anything sharing its data (clones, [`WeakSlice`]s) is alive.
+    /// * The returned slice must not be dropped (eg. by [`ManuallyDrop::into_inner`] or
+    ///   [`ManuallyDrop::drop`]).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::mem::ManuallyDrop;
+    /// use std::ptr::NonNull;
+    ///
+    /// use squash::OwnedSlice;
+    ///
+    /// let layout = OwnedSlice::<u16>::layout_for_len(3).unwrap();
+    /// // u64 has big enough alignment
+    /// assert!(layout.align() <= 8);
+    /// let mut buffer = vec![0u64; (layout.size() + 7) / 8];
+    /// let ptr = NonNull::new(buffer.as_mut_ptr()).unwrap().cast();
+    /// let s: ManuallyDrop<OwnedSlice<u16>> = unsafe {
+    ///     OwnedSlice::init_in_place(ptr, &[1, 2, 3]).unwrap()
+    /// };
+    /// assert_eq!(&[1, 2, 3], &s[..]);
+    /// ```
+    pub unsafe fn init_in_place(ptr: NonNull<u8>, src: &[T]) -> Result<ManuallyDrop<Self>, TooLong>
+    where
+        T: Clone,
+    {
+        let mut builder = Builder::new_in(src.len(), |_| ptr, false)?;
+        for v in src {
+            builder.push(v.clone());
+        }
+        Ok(ManuallyDrop::new(builder.finish()))
+    }
+
+    /// Returns a raw pointer to the first element of the data.
+    ///
+    /// Unlike going through [`Deref`], this goes directly to the data, which is handy when passing
+    /// them to FFI. The pointer is valid for reading as long as the slice is alive. For an empty
+    /// slice, it is a dangling (but non-null and aligned) pointer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// assert_eq!(1, unsafe { *s.as_ptr() });
+    /// ```
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        if self.is_sentinel() {
+            NonNull::dangling().as_ptr()
+        } else {
+            self.data(self.len())
+        }
+    }
+
     /// Creates a new owned slice by cloning a content of the passed one.
     ///
+    /// For [`Copy`] types, [`copy_from_slice`][OwnedSlice::copy_from_slice] is faster.
+    ///
     /// # Errors
     ///
     /// If the slice is bigger than the header can encode, this is signalized by the [`TooLong`]
@@ -111,6 +359,7 @@ where
     where
         T: Clone,
     {
+        assert_global_alloc::<H>();
         if src.is_empty() {
             // Use the sentinel thing
             return Ok(Self::default());
@@ -134,6 +383,7 @@ where
 
             // Initialize everything
             ptr::write(hdr, H::encode_len(len, len_ptr));
+            // Number of elements already written (the ones at [0, initialized)).
             let initialized = Cell::new(0);
 
             // Deal with possibly panicking during the initialization (clone is about the only
@@ -147,7 +397,7 @@ where
             impl<T> Drop for CleanupGuard<'_, T> {
                 fn drop(&mut self) {
                     unsafe {
-                        for i in 0..=self.initialized.get() {
+                        for i in 0..self.initialized.get() {
                             ptr::drop_in_place(self.data_ptr.add(i));
                         }
                         mem_dealloc(self.ptr, self.layout);
@@ -163,7 +413,8 @@ where
 
             for (idx, src) in src.iter().enumerate() {
                 ptr::write(data_ptr.add(idx), src.clone());
-                initialized.set(idx);
+                // Only after it is actually written
+                initialized.set(idx + 1);
             }
 
             // Confirm we are done and disarm the guard (it contains no allocation, so this doesn't
@@ -177,200 +428,4493 @@ where
         }
     }
 
-    // TODO: Some more constructors? Something without cloning?
-}
+    /// Creates a new owned slice by copying a content of the passed one.
+    ///
+    /// This is the same as [`new`][OwnedSlice::new], but for [`Copy`] types the whole data is
+    /// copied at once instead of cloning element by element.
+    ///
+    /// # Errors
+    ///
+    /// If the slice is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u64> = OwnedSlice::copy_from_slice(&[1, 2, 3]).unwrap();
+    /// assert_eq!(&[1, 2, 3], &s[..]);
+    /// ```
+    pub fn copy_from_slice(src: &[T]) -> Result<Self, TooLong>
+    where
+        T: Copy,
+    {
+        let mut builder = Builder::new(src.len())?;
+        builder.extend_copied(src);
+        Ok(builder.finish())
+    }
 
-impl<T, H> Drop for OwnedSlice<T, H>
-where
-    H: Header,
-{
-    fn drop(&mut self) {
-        if self.is_sentinel() {
-            return;
+    /// Creates a new owned slice from as many elements of `src` as the header can encode.
+    ///
+    /// Returns the slice and the number of elements kept. This is an infallible alternative to
+    /// [`new`][OwnedSlice::new] for cases where losing the tail is preferable to handling the
+    /// [`TooLong`] error. Note that the truncation happens only for absurdly long inputs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let (s, kept) = OwnedSlice::<u16>::new_truncated(&[1, 2, 3]);
+    /// assert_eq!(3, kept);
+    /// assert_eq!(&[1, 2, 3], &s[..]);
+    /// ```
+    pub fn new_truncated(src: &[T]) -> (Self, usize)
+    where
+        T: Clone,
+    {
+        let len = cmp::min(src.len(), H::MAX_LEN);
+        let result = Self::new(&src[..len]).expect("MAX_LEN is always encodable");
+        (result, len)
+    }
+
+    /// Creates a new owned slice by cloning a content of the passed one, without aborting on
+    /// allocation failure.
+    ///
+    /// This is the same as [`new`][OwnedSlice::new], but if the allocator fails to provide the
+    /// memory, an error is returned instead of calling the
+    /// [`handle_alloc_error`][alloc::alloc::handle_alloc_error] (which aborts by default).
+    ///
+    /// Note that the `try_` methods (like [`try_clone`][OwnedSlice::try_clone]) only report the
+    /// slice being too long, they still abort on allocation failure.
+    ///
+    /// # Errors
+    ///
+    /// [`AllocOrTooLong::TooLong`] if the slice is bigger than the header can encode and
+    /// [`AllocOrTooLong::Alloc`] if the allocation fails.
+    pub fn new_no_abort(src: &[T]) -> Result<Self, AllocOrTooLong>
+    where
+        T: Clone,
+    {
+        let mut builder = Builder::new_no_abort(src.len())?;
+        for v in src {
+            builder.push(v.clone());
         }
 
-        unsafe {
-            if self.header.as_ref().dec() {
-                let len = self.len();
-                let layout = Self::layout(len);
-                if mem::needs_drop::<T>() {
-                    let data = self.data(len);
+        Ok(builder.finish())
+    }
 
-                    for i in 0..len {
-                        ptr::drop_in_place(data.add(i));
-                    }
-                }
+    /// Creates a new owned slice by concatenating several slices together.
+    ///
+    /// This is similar to [`concat`][slice::concat] on slices, but without the intermediate
+    /// [`Vec`][alloc::vec::Vec].
+    ///
+    /// # Errors
+    ///
+    /// If the resulting length is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::concat(&[&[1, 2], &[], &[3]]).unwrap();
+    /// assert_eq!(&[1, 2, 3], &s[..]);
+    /// ```
+    pub fn concat(parts: &[&[T]]) -> Result<Self, TooLong>
+    where
+        T: Clone,
+    {
+        let len = parts
+            .iter()
+            .try_fold(0usize, |acc, part| acc.checked_add(part.len()))
+            .ok_or_else(TooLong::overflow::<H>)?;
+        Self::from_iter_len(len, parts.iter().flat_map(|part| part.iter().cloned()))
+    }
 
-                mem_dealloc(self.header.as_ptr().cast::<u8>(), layout);
-            }
-        }
+    /// Creates a new, longer owned slice with the `extra` elements appended.
+    ///
+    /// The slice has a fixed length, so this makes a new allocation and clones both the elements
+    /// of `self` and the `extra` ones into it. `self` is left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// If the resulting length is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2]).unwrap();
+    /// let longer = s.extend_from_slice(&[3]).unwrap();
+    /// assert_eq!(&[1, 2, 3], &longer[..]);
+    /// ```
+    pub fn extend_from_slice(&self, extra: &[T]) -> Result<Self, TooLong>
+    where
+        T: Clone,
+    {
+        Self::concat(&[self, extra])
     }
-}
 
-impl<T, H> Clone for OwnedSlice<T, H>
-where
-    H: Header,
-    T: Clone,
-{
-    fn clone(&self) -> Self {
-        if !self.is_sentinel() && unsafe { self.header.as_ref().inc() } {
-            Self {
-                header: self.header,
-                _data: PhantomData,
+    /// Creates a new owned slice of given length, with all elements set to the given value.
+    ///
+    /// The value is cloned into all the positions except the last one, where it is moved.
+    ///
+    /// # Errors
+    ///
+    /// If the length is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<String> = OwnedSlice::filled(2, "Hi".to_owned()).unwrap();
+    /// assert_eq!(&["Hi", "Hi"], &s[..]);
+    /// ```
+    pub fn filled(len: usize, value: T) -> Result<Self, TooLong>
+    where
+        T: Clone,
+    {
+        let mut builder = Builder::new(len)?;
+        if len > 0 {
+            for _ in 1..len {
+                builder.push(value.clone());
             }
-        } else {
-            Self::new(self.deref()).expect("Already have layout for this size")
+            builder.push(value);
         }
-    }
-}
 
-impl<T, H> Deref for OwnedSlice<T, H>
-where
-    H: Header,
-{
-    type Target = [T];
+        Ok(builder.finish())
+    }
 
-    #[inline]
-    fn deref(&self) -> &[T] {
-        if self.is_sentinel() {
-            return &[];
+    /// Creates a new owned slice of given length, with all elements set to the given value.
+    ///
+    /// This is like [`filled`][OwnedSlice::filled], but for [`Copy`] types it avoids calling
+    /// `clone`.
+    ///
+    /// # Errors
+    ///
+    /// If the length is bigger than the header can encode, [`TooLong`] is returned.
+    pub fn splat(len: usize, value: T) -> Result<Self, TooLong>
+    where
+        T: Copy,
+    {
+        let mut builder = Builder::new(len)?;
+        for _ in 0..len {
+            builder.push(value);
         }
 
-        let len = self.len();
-        unsafe { slice::from_raw_parts(self.data(len), len) }
+        Ok(builder.finish())
     }
-}
-
-impl<T> DerefMut for OwnedSlice<T, BoxHeader> {
-    #[inline]
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        if self.is_sentinel() {
-            return &mut [];
-        }
 
-        let len = self.len();
-        unsafe { slice::from_raw_parts_mut(self.data(len), len) }
+    /// Creates a new owned slice by repeating the passed one `n` times.
+    ///
+    /// This is similar to [`repeat`][slice::repeat] on slices, but without the intermediate
+    /// [`Vec`][alloc::vec::Vec]. Repeating an empty slice or repeating it 0 times results in an
+    /// empty slice (which doesn't allocate).
+    ///
+    /// # Errors
+    ///
+    /// If the resulting length is bigger than the header can encode (or doesn't even fit into
+    /// `usize`), [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::repeat(&[1, 2], 3).unwrap();
+    /// assert_eq!(&[1, 2, 1, 2, 1, 2], &s[..]);
+    /// ```
+    pub fn repeat(src: &[T], n: usize) -> Result<Self, TooLong>
+    where
+        T: Clone,
+    {
+        let len = src
+            .len()
+            .checked_mul(n)
+            .ok_or_else(TooLong::overflow::<H>)?;
+        Self::from_iter_len(len, (0..n).flat_map(|_| src.iter().cloned()))
     }
-}
 
-impl<T, H> Debug for OwnedSlice<T, H>
-where
-    H: Header,
-    T: Debug,
-{
-    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        self.deref().fmt(fmt)
+    /// Creates a new owned slice by cloning the passed one and sorting the result.
+    ///
+    /// The sorting happens in place in the new allocation, there's no intermediate
+    /// [`Vec`][alloc::vec::Vec]. This is meant for building a sorted slice once and then
+    /// searching it many times by the [`binary_search`][slice::binary_search] (and related
+    /// methods) of the slice it dereferences to.
+    ///
+    /// If either the cloning or the comparison panics, the already created elements are dropped
+    /// and the memory is freed.
+    ///
+    /// # Errors
+    ///
+    /// If the slice is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::sorted(&[3, 1, 2]).unwrap();
+    /// assert_eq!(&[1, 2, 3], &s[..]);
+    /// assert_eq!(Ok(1), s.binary_search(&2));
+    /// ```
+    pub fn sorted(src: &[T]) -> Result<Self, TooLong>
+    where
+        T: Clone + Ord,
+    {
+        Self::sorted_by(src, T::cmp)
     }
-}
 
-impl<T, H> Default for OwnedSlice<T, H>
-where
-    H: Header,
-{
-    fn default() -> Self {
-        Self {
-            header: NonNull::new((&ZERO_SENTINEL as *const u8 as *mut u8).cast()).unwrap(),
-            _data: PhantomData,
+    /// Like [`sorted`][OwnedSlice::sorted], but with a comparator function.
+    pub fn sorted_by<F>(src: &[T], compare: F) -> Result<Self, TooLong>
+    where
+        T: Clone,
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let result = Self::from_iter_len(src.len(), src.iter().cloned())?;
+        if !result.is_sentinel() {
+            let len = result.len();
+            // We've just created it, nobody else has access to it.
+            let data = unsafe { slice::from_raw_parts_mut(result.data(len), len) };
+            data.sort_by(compare);
         }
+        Ok(result)
     }
-}
-
-// With some headers, we do Arc-like sharing of stuff. Therefore we need to be conservative about
-// these and require both Send + Sync as the bounds, just like Arc.
-unsafe impl<T, H> Send for OwnedSlice<T, H>
-where
-    H: Header + Send + Sync,
-    T: Send + Sync,
-{
-}
 
-unsafe impl<T, H> Sync for OwnedSlice<T, H>
-where
-    H: Header + Send + Sync,
-    T: Send + Sync,
-{
-}
+    /// Like [`sorted`][OwnedSlice::sorted], but with a key extraction function.
+    pub fn sorted_by_key<K, F>(src: &[T], mut f: F) -> Result<Self, TooLong>
+    where
+        T: Clone,
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        Self::sorted_by(src, |a, b| f(a).cmp(&f(b)))
+    }
 
-#[cfg(all(feature = "std", test))]
+    /// Checks if the elements are sorted.
+    #[inline]
+    pub fn is_sorted(&self) -> bool
+    where
+        T: PartialOrd,
+    {
+        self.deref().is_sorted()
+    }
+
+    /// Creates a new owned slice of given length, computing each element from its index.
+    ///
+    /// The elements are written directly into the final allocation. If the closure panics, the
+    /// already created elements are dropped and the memory is freed.
+    ///
+    /// # Errors
+    ///
+    /// If the length is bigger than the header can encode, [`TooLong`] is returned (without
+    /// calling the closure at all).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<usize> = OwnedSlice::from_fn(4, |i| i * i).unwrap();
+    /// assert_eq!(&[0, 1, 4, 9], &s[..]);
+    /// ```
+    pub fn from_fn<F>(len: usize, f: F) -> Result<Self, TooLong>
+    where
+        F: FnMut(usize) -> T,
+    {
+        Self::from_iter_len(len, (0..len).map(f))
+    }
+
+    /// Like [`from_fn`][OwnedSlice::from_fn], but without aborting on allocation failure.
+    ///
+    /// See [`new_no_abort`][OwnedSlice::new_no_abort].
+    ///
+    /// # Errors
+    ///
+    /// [`AllocOrTooLong::TooLong`] if the length is bigger than the header can encode and
+    /// [`AllocOrTooLong::Alloc`] if the allocation fails (in both cases without calling the
+    /// closure at all).
+    pub fn from_fn_no_abort<F>(len: usize, f: F) -> Result<Self, AllocOrTooLong>
+    where
+        F: FnMut(usize) -> T,
+    {
+        Self::from_iter_exact_no_abort((0..len).map(f))
+    }
+
+    /// Creates the slice from an iterator that knows its length.
+    ///
+    /// The length reported by the iterator is used to allocate the exact memory once and the
+    /// elements are written directly into it, without collecting them into a [`Vec`] first.
+    ///
+    /// An iterator reporting a wrong length is a bug, but it is not undefined behaviour. If it
+    /// yields fewer elements, the slice is shrunk to them. If it has more, only the reported
+    /// number is taken (and in debug builds, this panics).
+    ///
+    /// # Errors
+    ///
+    /// If the reported length is bigger than the header can encode, [`TooLong`] is returned
+    /// (without taking any elements).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u32> = OwnedSlice::from_iter_exact((1..4).map(|i| i * 10)).unwrap();
+    /// assert_eq!(&[10, 20, 30], &s[..]);
+    /// ```
+    pub fn from_iter_exact<I>(iter: I) -> Result<Self, TooLong>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        let builder = Builder::new(iter.len())?;
+        Ok(Self::fill_exact(builder, iter))
+    }
+
+    /// Like [`from_iter_exact`][OwnedSlice::from_iter_exact], but without aborting on allocation
+    /// failure.
+    ///
+    /// See [`new_no_abort`][OwnedSlice::new_no_abort].
+    ///
+    /// # Errors
+    ///
+    /// [`AllocOrTooLong::TooLong`] if the reported length is bigger than the header can encode
+    /// and [`AllocOrTooLong::Alloc`] if the allocation fails (in both cases without taking any
+    /// elements).
+    pub fn from_iter_exact_no_abort<I>(iter: I) -> Result<Self, AllocOrTooLong>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        let builder = Builder::new_no_abort(iter.len())?;
+        Ok(Self::fill_exact(builder, iter))
+    }
+
+    /// Fills the builder of the length reported by the iterator.
+    fn fill_exact<I>(mut builder: Builder<T, H>, mut iter: I) -> Self
+    where
+        I: ExactSizeIterator<Item = T>,
+    {
+        let len = iter.len();
+        for elem in iter.by_ref().take(len) {
+            builder.push(elem);
+        }
+        debug_assert!(
+            iter.next().is_none(),
+            "Iterator longer than its reported length"
+        );
+        builder.truncate_to_initialized();
+
+        builder.finish()
+    }
+
+    /// A fallible version of [`from_fn`][OwnedSlice::from_fn].
+    ///
+    /// The first error returned by the closure terminates the construction and is returned. The
+    /// error type needs to be able to hold the [`TooLong`] error too.
+    pub fn try_from_fn<E, F>(len: usize, mut f: F) -> Result<Self, E>
+    where
+        F: FnMut(usize) -> Result<T, E>,
+        E: From<TooLong>,
+    {
+        let mut builder = Builder::new(len)?;
+        for idx in 0..len {
+            builder.push(f(idx)?);
+        }
+
+        Ok(builder.finish())
+    }
+
+    /// Creates a new owned slice of given length with uninitialized content.
+    ///
+    /// This allows filling the data from some external source without initializing it first.
+    /// Once filled, the slice can be turned into the initialized one by
+    /// [`assume_init`][OwnedSlice::assume_init].
+    ///
+    /// # Errors
+    ///
+    /// If the length is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::mem::MaybeUninit;
+    /// use squash::OwnedSlice;
+    ///
+    /// let mut s = OwnedSlice::<u32>::try_new_uninit(3).unwrap();
+    /// for (i, v) in s.iter_mut().enumerate() {
+    ///     *v = MaybeUninit::new(i as u32);
+    /// }
+    /// let s = unsafe { s.assume_init() };
+    /// assert_eq!(&[0, 1, 2], &s[..]);
+    /// ```
+    pub fn try_new_uninit(len: usize) -> Result<OwnedSlice<MaybeUninit<T>, H>, TooLong> {
+        let mut builder = Builder::new(len)?;
+        for _ in 0..len {
+            // Doesn't actually write anything
+            builder.push(MaybeUninit::uninit());
+        }
+
+        Ok(builder.finish())
+    }
+
+    /// Clones the slice, reporting errors instead of panicking.
+    ///
+    /// If the header supports sharing, only the reference count is incremented. Otherwise, a new
+    /// copy of the data is made. If the reference count can't be incremented any more, the
+    /// header's [`on_overflow`][Header::on_overflow] decides (by default, a copy is made). The
+    /// copy clones element by element; for [`Copy`] types,
+    /// [`try_copy_clone`][OwnedSlice::try_copy_clone] copies the whole data at once instead.
+    ///
+    /// The [`Clone`] implementation of slices that are not [`Shared`] uses this and panics on
+    /// errors (see [`CloneStrategy`]).
+    ///
+    /// # Errors
+    ///
+    /// Currently, all headers provided by this library are able to encode the same length again,
+    /// therefore this doesn't fail in practice.
+    ///
+    /// # Panics
+    ///
+    /// On reference count overflow, if the header asks for
+    /// [`OverflowPolicy::Panic`][crate::OverflowPolicy::Panic].
+    pub fn try_clone(&self) -> Result<Self, TooLong>
+    where
+        T: Clone,
+    {
+        match self.share() {
+            Some(shared) => Ok(shared),
+            None => Self::new(self.deref()),
+        }
+    }
+
+    /// Clones the slice of [`Copy`] elements.
+    ///
+    /// This is the same as [`try_clone`][OwnedSlice::try_clone], but if a new copy of the data
+    /// needs to be made, it is done by [`copy_from_slice`][OwnedSlice::copy_from_slice] (a single
+    /// memory copy) instead of cloning element by element. Without specialization, the [`Clone`]
+    /// implementation can't do this on its own.
+    ///
+    /// # Errors
+    ///
+    /// Same as with [`try_clone`][OwnedSlice::try_clone].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u64> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let s2 = s.try_copy_clone().unwrap();
+    /// assert_eq!(s, s2);
+    /// ```
+    pub fn try_copy_clone(&self) -> Result<Self, TooLong>
+    where
+        T: Copy,
+    {
+        match self.share() {
+            Some(shared) => Ok(shared),
+            None => Self::copy_from_slice(self.deref()),
+        }
+    }
+
+    /// Creates another owner of the same data, if the header supports it.
+    ///
+    /// Applies the header's [`OverflowPolicy`][crate::OverflowPolicy] if the reference count is
+    /// saturated.
+    fn share(&self) -> Option<Self> {
+        if self.is_sentinel() {
+            None
+        } else if unsafe { self.header.as_ref().inc() } {
+            Some(Self {
+                header: self.header,
+                _data: PhantomData,
+            })
+        } else {
+            H::on_overflow().overflowed();
+            None
+        }
+    }
+
+    /// Returns the number of strong owners of the data.
+    ///
+    /// This is `None` if the header doesn't count references (for example the [`BoxHeader`]) and
+    /// for empty slices, which don't have a header allocated. Mostly useful for debugging and
+    /// tests; with other threads holding references, the count may change at any time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{OwnedSlice, RcHeader};
+    ///
+    /// let s = OwnedSlice::<u8, RcHeader>::new(b"Hello").unwrap();
+    /// assert_eq!(Some(1), OwnedSlice::strong_count(&s));
+    /// let s2 = s.clone();
+    /// assert_eq!(Some(2), OwnedSlice::strong_count(&s));
+    /// drop(s2);
+    /// assert_eq!(Some(1), OwnedSlice::strong_count(&s));
+    ///
+    /// assert_eq!(None, OwnedSlice::strong_count(&OwnedSlice::<u8>::new(b"Hello").unwrap()));
+    /// ```
+    pub fn strong_count(this: &Self) -> Option<usize> {
+        if this.is_sentinel() {
+            None
+        } else {
+            unsafe { this.header.as_ref().strong_count() }
+        }
+    }
+
+    /// Creates a weak reference to the slice.
+    ///
+    /// The weak reference doesn't keep the data alive, but can be turned back into the slice by
+    /// [`WeakSlice::upgrade`] if there are still some strong references to it. This is the
+    /// equivalent of [`Arc::downgrade`][alloc::sync::Arc::downgrade].
+    ///
+    /// # Panics
+    ///
+    /// If the header doesn't support weak references (for example [`BoxHeader`]) or if the weak
+    /// count overflows.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{ArcHeader, OwnedSlice};
+    /// let s: OwnedSlice<u16, ArcHeader> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let weak = OwnedSlice::downgrade(&s);
+    /// assert_eq!(&[1, 2, 3], &weak.upgrade().unwrap()[..]);
+    /// drop(s);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn downgrade(this: &Self) -> WeakSlice<T, H> {
+        if !this.is_sentinel() {
+            let supported = unsafe { this.header.as_ref().inc_weak() };
+            assert!(
+                supported,
+                "The header doesn't support weak references or the count overflowed"
+            );
+        }
+
+        WeakSlice {
+            header: this.header,
+            _data: PhantomData,
+        }
+    }
+
+    /// Converts the slice to use a different header.
+    ///
+    /// This allows, for example, to turn a uniquely owned slice into a shared one once it is
+    /// fully built.
+    ///
+    /// If the slice is not shared and both headers result in the same memory layout, only the
+    /// header is rewritten in place. Otherwise, a new allocation is made. The elements are moved
+    /// into it if the slice is not shared and cloned if it is. Therefore, the elements need to be
+    /// [`Clone`] only if the original header may share them (see [`ConvertStrategy`]).
+    ///
+    /// # Errors
+    ///
+    /// If the length can't be encoded by the new header, [`TooLong`] is returned. In that case
+    /// the original slice is dropped.
+    ///
+    /// # Panics
+    ///
+    /// If the new header doesn't support memory of the global allocator (see
+    /// [`Header::GLOBAL_ALLOC`]).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{BoxHeader, OwnedSlice};
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let s: OwnedSlice<u16, BoxHeader> = s.convert().unwrap();
+    /// assert_eq!(&[1, 2, 3], &s[..]);
+    /// ```
+    pub fn convert<H2>(self) -> Result<OwnedSlice<T, H2>, TooLong>
+    where
+        H2: Header,
+        H::Ownership: ConvertStrategy<T>,
+    {
+        H::Ownership::convert_slice(self)
+    }
+
+    /// Converts the slice to use a different header, cloning the elements if it is shared.
+    pub(crate) fn convert_or_clone<H2>(self) -> Result<OwnedSlice<T, H2>, TooLong>
+    where
+        H2: Header,
+        T: Clone,
+    {
+        if !self.is_sentinel() && !unsafe { self.header.as_ref().is_unique() } {
+            return OwnedSlice::new(&self);
+        }
+        self.convert_unique()
+    }
+
+    /// Converts the slice to use a different header, moving the elements.
+    ///
+    /// The slice must not be shared (but may be empty).
+    fn convert_unique<H2>(self) -> Result<OwnedSlice<T, H2>, TooLong>
+    where
+        H2: Header,
+    {
+        assert_global_alloc::<H2>();
+        if self.is_sentinel() {
+            return Ok(OwnedSlice::default());
+        }
+
+        debug_assert!(unsafe { self.header.as_ref().is_unique() });
+        let len = self.len();
+        let new_layout = OwnedSlice::<T, H2>::layout_and_offsets(len)?;
+        // Memory not coming from the global allocator can't be handed over to a header that frees
+        // it there.
+        if H::GLOBAL_ALLOC && new_layout == Self::layout_and_offsets(len).unwrap() {
+            // We are the only owner and everything stays at the same place. Therefore it is
+            // enough to replace the header.
+            let (_, len_off, _) = new_layout;
+            let this = ManuallyDrop::new(self);
+            unsafe {
+                let ptr = this.header.as_ptr().cast::<u8>();
+                let hdr = ptr.cast::<H2>();
+                ptr::write(hdr, H2::encode_len(len, ptr.add(len_off)));
+                Ok(OwnedSlice {
+                    header: NonNull::new(hdr).unwrap(),
+                    _data: PhantomData,
+                })
+            }
+        } else {
+            OwnedSlice::from_iter_len(len, MoveOut::new(self))
+        }
+    }
+
+    /// Creates the slice from an iterator yielding exactly `len` elements.
+    ///
+    /// Panics if the iterator yields a different number of elements.
+    pub(crate) fn from_iter_len<I>(len: usize, iter: I) -> Result<Self, TooLong>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut builder = Builder::new(len)?;
+        for elem in iter {
+            builder.push(elem);
+        }
+
+        Ok(builder.finish())
+    }
+
+    // TODO: Some more constructors? Something without cloning?
+
+    /// Provides mutable access to the data, if it is not shared.
+    ///
+    /// This is similar to [`Arc::get_mut`][alloc::sync::Arc::get_mut]. If this is the only owner
+    /// of the data (which is always the case for non-sharing headers like [`BoxHeader`]), the
+    /// mutable slice is returned. If the data is shared with other owners, `None` is returned.
+    ///
+    /// Like with the [`Arc`][alloc::sync::Arc], this is an associated function, to not clash with
+    /// the methods of the slice it dereferences to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let mut s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// OwnedSlice::get_mut(&mut s).unwrap()[0] = 42;
+    /// assert_eq!(&[42, 2, 3], &s[..]);
+    /// ```
+    pub fn get_mut(this: &mut Self) -> Option<&mut [T]> {
+        if this.is_sentinel() {
+            return Some(&mut []);
+        }
+
+        if unsafe { this.header.as_ref().is_unique() } {
+            let len = this.len();
+            Some(unsafe { slice::from_raw_parts_mut(this.data(len), len) })
+        } else {
+            None
+        }
+    }
+
+    /// Copies the elements into a [`Vec`].
+    ///
+    /// See [`slice::to_vec`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u8> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let mut v = s.to_vec();
+    /// v.push(4);
+    /// assert_eq!(vec![1, 2, 3, 4], v);
+    /// ```
+    #[inline]
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.deref().to_vec()
+    }
+
+    /// Views the data as an array, if the length matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u8> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// assert_eq!(Some(&[1, 2, 3]), s.try_as_array::<3>());
+    /// assert_eq!(None, s.try_as_array::<4>());
+    /// ```
+    pub fn try_as_array<const N: usize>(&self) -> Option<&[T; N]> {
+        self.deref().try_into().ok()
+    }
+
+    /// Checks if the two slices share the same allocation.
+    ///
+    /// This is the equivalent of [`Arc::ptr_eq`][alloc::sync::Arc::ptr_eq]. Clones of slices with
+    /// sharing headers (like [`ArcHeader`][crate::ArcHeader]) compare equal, while slices with
+    /// the [`BoxHeader`] own their data and are never equal to a different instance. Two empty
+    /// slices always compare equal, as they don't allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{ArcHeader, OwnedSlice};
+    /// let s: OwnedSlice<u16, ArcHeader> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// assert!(OwnedSlice::ptr_eq(&s, &s.clone()));
+    /// assert!(!OwnedSlice::ptr_eq(&s, &OwnedSlice::new(&s).unwrap()));
+    /// ```
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.header == other.header
+    }
+
+    /// Splits the slice into two new ones at the given index.
+    ///
+    /// The first one contains the elements `[0, mid)`, the second one `[mid, len)`. The elements
+    /// are cloned into new allocations, except for the cases when one of the parts is empty ‒ then
+    /// the whole slice is cloned (which is cheap for sharing headers) and the other part doesn't
+    /// allocate.
+    ///
+    /// This is an associated function, to not clash with [`split_at`][slice::split_at] of the
+    /// slice it dereferences to.
+    ///
+    /// # Panics
+    ///
+    /// If `mid > len`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let (a, b) = OwnedSlice::split_at(&s, 1);
+    /// assert_eq!(&[1], &a[..]);
+    /// assert_eq!(&[2, 3], &b[..]);
+    /// ```
+    pub fn split_at(this: &Self, mid: usize) -> (Self, Self)
+    where
+        T: Clone,
+    {
+        let len = this.len();
+        assert!(mid <= len, "Split index {} out of bounds {}", mid, len);
+        let whole = || this.try_clone().expect("Already have layout for this size");
+        if mid == 0 {
+            (Self::default(), whole())
+        } else if mid == len {
+            (whole(), Self::default())
+        } else {
+            let (a, b) = this.deref().split_at(mid);
+            let a = Self::new(a).expect("Shorter than the original");
+            let b = Self::new(b).expect("Shorter than the original");
+            (a, b)
+        }
+    }
+
+    /// Splits the slice into new slices of `chunk_size` elements.
+    ///
+    /// This is like [`chunks`][slice::chunks], but the chunks are cloned into new owned slices,
+    /// each allocated with its exact size. The last one is shorter if `chunk_size` doesn't divide
+    /// the length and an empty slice has no chunks. If there's only one chunk, it is a clone of
+    /// the whole slice (which is cheap for sharing headers).
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3, 4, 5]).unwrap();
+    /// let chunks = s.chunks_collect(2);
+    /// assert_eq!(3, chunks.len());
+    /// assert_eq!(&[1, 2], &chunks[0][..]);
+    /// assert_eq!(&[5], &chunks[2][..]);
+    /// ```
+    pub fn chunks_collect(&self, chunk_size: usize) -> Vec<Self>
+    where
+        T: Clone,
+    {
+        assert!(chunk_size != 0, "Chunk size must be non-zero");
+        if !self.is_empty() && self.len() <= chunk_size {
+            let whole = self.try_clone().expect("Already have layout for this size");
+            return vec![whole];
+        }
+        self.chunks(chunk_size)
+            .map(|chunk| Self::new(chunk).expect("Shorter than the original"))
+            .collect()
+    }
+
+    /// Leaks the slice, returning a reference to its data that lives as long as needed.
+    ///
+    /// This is the equivalent of [`Box::leak`][alloc::boxed::Box::leak] for sharing headers. As
+    /// the data are shared, this is possible only if this is the only owner. Otherwise, the slice
+    /// is returned back. The empty slice doesn't allocate and always succeeds.
+    ///
+    /// The whole allocation, including the header, is leaked and never freed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{ArcHeader, OwnedSlice};
+    /// let s: OwnedSlice<u16, ArcHeader> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let s2 = s.clone();
+    /// let s = OwnedSlice::try_leak(s).unwrap_err();
+    /// drop(s2);
+    /// let leaked: &'static [u16] = OwnedSlice::try_leak(s).unwrap();
+    /// assert_eq!(&[1, 2, 3], leaked);
+    /// ```
+    pub fn try_leak<'a>(this: Self) -> Result<&'a [T], Self>
+    where
+        T: 'a,
+    {
+        if this.is_sentinel() {
+            return Ok(&[]);
+        }
+
+        if unsafe { this.header.as_ref().is_unique() } {
+            let this = ManuallyDrop::new(this);
+            let len = this.len();
+            Ok(unsafe { slice::from_raw_parts(this.data(len), len) })
+        } else {
+            Err(this)
+        }
+    }
+
+    /// Creates a new owned slice with clones of the elements matching the predicate.
+    ///
+    /// This is like [`Vec::retain`][alloc::vec::Vec::retain], but the original is left untouched
+    /// and the matching elements end up in a new slice. The result is never longer than the
+    /// original, so this can't fail on the length.
+    ///
+    /// The predicate is called twice for each element, in order. The first pass counts the
+    /// matches, so the result can be allocated once with the exact length, the second pass clones
+    /// them into it.
+    ///
+    /// # Panics
+    ///
+    /// If the predicate doesn't give the same answers in both passes.
+    ///
+    /// See [`filter_into`][OwnedSlice::filter_into] for the consuming variant, that doesn't need
+    /// to clone.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3, 4]).unwrap();
+    /// let even = s.filtered(|v| v % 2 == 0);
+    /// assert_eq!(&[2, 4], &even[..]);
+    /// ```
+    pub fn filtered<F>(&self, mut pred: F) -> Self
+    where
+        T: Clone,
+        F: FnMut(&T) -> bool,
+    {
+        let count = self.iter().filter(|v| pred(v)).count();
+        let mut builder = Builder::new(count).expect("Shorter than the original");
+        for v in self.iter().filter(|v| pred(v)) {
+            builder.push(v.clone());
+        }
+        builder.finish()
+    }
+
+    /// Transforms each element of the slice, producing a new slice.
+    ///
+    /// The elements are moved out of the original slice, passed through the closure and the
+    /// results are written into a slice of the same length. If `U` has the same size and
+    /// alignment as `T`, the results are written in place and the allocation is reused.
+    /// Otherwise, a new slice is allocated and the original allocation is freed.
+    ///
+    /// If the slice is shared with other owners, it is first copied (like with
+    /// [`convert`][OwnedSlice::convert]), therefore the elements need to be [`Clone`] only if the
+    /// header may share them (see [`ConvertStrategy`]).
+    ///
+    /// If the closure panics, both the already transformed and the not yet processed elements are
+    /// dropped exactly once and the memory is freed.
+    ///
+    /// # Panics
+    ///
+    /// If the header doesn't support memory of the global allocator and a new allocation is
+    /// needed (see [`Header::GLOBAL_ALLOC`]).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let s = s.map(|i| i * 2);
+    /// assert_eq!(&[2, 4, 6], &s[..]);
+    /// ```
+    pub fn map<U, F>(self, mut f: F) -> OwnedSlice<U, H>
+    where
+        F: FnMut(T) -> U,
+        H::Ownership: ConvertStrategy<T>,
+    {
+        match self.try_map(|v| Ok::<_, Infallible>(f(v))) {
+            Ok(result) => result,
+            Err(e) => match e {},
+        }
+    }
+
+    /// A fallible version of [`map`][OwnedSlice::map].
+    ///
+    /// If the closure returns an error, the processing is terminated and the error is returned.
+    /// Both the already transformed elements and the not yet processed elements of the original
+    /// are dropped.
+    pub fn try_map<U, E, F>(self, mut f: F) -> Result<OwnedSlice<U, H>, E>
+    where
+        F: FnMut(T) -> Result<U, E>,
+        H::Ownership: ConvertStrategy<T>,
+    {
+        let this = if self.is_sentinel() || unsafe { self.header.as_ref().is_unique() } {
+            self
+        } else {
+            // The fresh copy is ours only, so the elements can be moved out of it.
+            self.convert::<H>().expect("Same header as the original")
+        };
+
+        let same_layout = mem::size_of::<T>() == mem::size_of::<U>()
+            && mem::align_of::<T>() == mem::align_of::<U>();
+        if same_layout && !this.is_sentinel() {
+            return this.try_map_in_place(f);
+        }
+
+        let src = MoveOut::new(this);
+        let mut dst = Builder::new(src.len).expect("Already have layout for this size");
+        for v in src {
+            dst.push(f(v)?);
+        }
+
+        Ok(dst.finish())
+    }
+
+    /// Maps the elements one by one, writing the results over the originals.
+    ///
+    /// `U` must have the same size and alignment as `T` (therefore also the same layout of the
+    /// whole allocation) and the slice must be uniquely owned (not the sentinel).
+    fn try_map_in_place<U, E, F>(self, mut f: F) -> Result<OwnedSlice<U, H>, E>
+    where
+        F: FnMut(T) -> Result<U, E>,
+    {
+        let len = self.len();
+        let data = self.data(len);
+        let mut guard = MapInPlace::<T, U, H> {
+            header: Self::into_raw(self),
+            data,
+            len,
+            mapped: 0,
+            taken: 0,
+            _result: PhantomData,
+        };
+        for i in 0..len {
+            let v = unsafe { ptr::read(data.add(i)) };
+            // Moved out, the closure is now responsible for dropping it.
+            guard.taken = i + 1;
+            let mapped = f(v)?;
+            unsafe { ptr::write(data.add(i).cast::<U>(), mapped) };
+            guard.mapped = i + 1;
+        }
+        let header = guard.header;
+        mem::forget(guard);
+        Ok(unsafe { OwnedSlice::from_raw(header) })
+    }
+
+    /// Creates a new slice by transforming each element by reference.
+    ///
+    /// The new slice has the same length and header type and is allocated once. The original is
+    /// left untouched. See [`map`][OwnedSlice::map] for the consuming variant.
+    ///
+    /// If the closure panics, the already transformed elements are dropped and the memory is
+    /// freed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{OwnedSlice, RcHeader};
+    /// let s: OwnedSlice<String, RcHeader> =
+    ///     OwnedSlice::new(&["a".to_owned(), "bc".to_owned()]).unwrap();
+    /// let lens = s.map_ref(String::len);
+    /// assert_eq!(&[1, 2], &lens[..]);
+    /// ```
+    pub fn map_ref<U, F>(&self, f: F) -> OwnedSlice<U, H>
+    where
+        F: FnMut(&T) -> U,
+    {
+        let mut builder = Builder::new(self.len()).expect("Same length as the original");
+        for v in self.iter().map(f) {
+            builder.push(v);
+        }
+        builder.finish()
+    }
+
+    /// Splits the slice into two new slices, by cloning the elements.
+    ///
+    /// The first contains the elements matching the predicate, the second the rest, both in the
+    /// original order. Like with [`filtered`][OwnedSlice::filtered], the predicate is called twice
+    /// for each element, so both results are allocated with their exact lengths (and an empty one
+    /// doesn't allocate).
+    ///
+    /// # Panics
+    ///
+    /// If the predicate doesn't give the same answers in both passes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3, 4, 5]).unwrap();
+    /// let (even, odd) = s.partition(|v| v % 2 == 0);
+    /// assert_eq!(&[2, 4], &even[..]);
+    /// assert_eq!(&[1, 3, 5], &odd[..]);
+    /// ```
+    pub fn partition<F>(&self, mut pred: F) -> (Self, Self)
+    where
+        T: Clone,
+        F: FnMut(&T) -> bool,
+    {
+        let count = self.iter().filter(|v| pred(v)).count();
+        let mut yes = Builder::new(count).expect("Shorter than the original");
+        let mut no = Builder::new(self.len() - count).expect("Shorter than the original");
+        for v in self.iter() {
+            if pred(v) {
+                yes.push(v.clone());
+            } else {
+                no.push(v.clone());
+            }
+        }
+        (yes.finish(), no.finish())
+    }
+
+    /// Provides the opaque pointer representing the slice, without giving up the ownership.
+    ///
+    /// The pointer is never null and is the same as the one returned by
+    /// [`into_opaque_ptr`][OwnedSlice::into_opaque_ptr]. It is meant to be passed to foreign code
+    /// as a handle, it doesn't point to the data (see [`as_ptr`][OwnedSlice::as_ptr] for that).
+    #[inline]
+    pub fn as_opaque_ptr(this: &Self) -> *const c_void {
+        this.header.as_ptr().cast()
+    }
+
+    /// Returns the pointer the slice is represented by, for identity checks and debugging.
+    ///
+    /// The pointer must not be dereferenced, it points to the internal header (or to a shared
+    /// sentinel for empty slices), not to the data. Two slices return the same pointer exactly
+    /// when [`ptr_eq`][OwnedSlice::ptr_eq] holds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{OwnedSlice, RcHeader};
+    /// let s: OwnedSlice<u16, RcHeader> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// assert_eq!(OwnedSlice::as_non_null(&s), OwnedSlice::as_non_null(&s.clone()));
+    /// ```
+    #[inline]
+    pub fn as_non_null(this: &Self) -> NonNull<()> {
+        this.header.cast()
+    }
+
+    /// Turns the slice into an opaque pointer, for passing through FFI.
+    ///
+    /// The slice is not dropped, the ownership is passed to the pointer. It can be turned back by
+    /// [`from_opaque_ptr`][OwnedSlice::from_opaque_ptr], otherwise the slice is leaked.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let ptr = OwnedSlice::into_opaque_ptr(s);
+    /// // Pass it to C and get it back...
+    /// let s = unsafe { OwnedSlice::<u16>::from_opaque_ptr(ptr) };
+    /// assert_eq!(&[1, 2, 3], &s[..]);
+    /// ```
+    #[inline]
+    pub fn into_opaque_ptr(this: Self) -> *mut c_void {
+        Self::into_raw(this).as_ptr().cast()
+    }
+
+    /// Reconstructs the slice from an opaque pointer.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have been returned by [`into_opaque_ptr`][OwnedSlice::into_opaque_ptr]
+    /// of a slice of the same type (including the header). Each such pointer may be turned back
+    /// only once (in the same way as [`Box::from_raw`][alloc::boxed::Box::from_raw]).
+    #[inline]
+    pub unsafe fn from_opaque_ptr(ptr: *mut c_void) -> Self {
+        Self::from_raw(NonNull::new_unchecked(ptr.cast()))
+    }
+
+    /// Turns the slice into the pointer to its header, without dropping it.
+    #[inline]
+    pub(crate) fn into_raw(this: Self) -> NonNull<H> {
+        ManuallyDrop::new(this).header
+    }
+
+    /// Reconstructs the slice from a pointer previously returned by
+    /// [`into_raw`][OwnedSlice::into_raw].
+    #[inline]
+    pub(crate) unsafe fn from_raw(header: NonNull<H>) -> Self {
+        Self {
+            header,
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<H> OwnedSlice<u8, H>
+where
+    H: Header,
+{
+    /// Creates a new owned slice of given length by reading it from a reader.
+    ///
+    /// The final allocation is made up front and the data are read directly into it, without any
+    /// intermediate buffer. This is handy for reading length-prefixed frames.
+    ///
+    /// # Errors
+    ///
+    /// If the length is bigger than the header can encode, or if the reader fails or runs out of
+    /// data before the whole length is read, [`ReadError`] is returned. The allocation is freed in
+    /// such case.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let mut input: &[u8] = b"hello world";
+    /// let s: OwnedSlice<u8> = OwnedSlice::copy_from_reader(5, &mut input).unwrap();
+    /// assert_eq!(b"hello", &s[..]);
+    /// assert_eq!(b" world", input);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn copy_from_reader<R>(len: usize, reader: &mut R) -> Result<Self, ReadError>
+    where
+        R: Read + ?Sized,
+    {
+        Self::copy_from_fill(len, |buf| reader.read_exact(buf).map_err(ReadError::Io))
+    }
+
+    /// Creates a new owned slice of given length, filled by a callback.
+    ///
+    /// The callback is given the whole data region of the final allocation (zeroed) to fill. This
+    /// is the building block of [`copy_from_reader`][OwnedSlice::copy_from_reader] that is not
+    /// tied to any particular I/O interface (for example an async one).
+    ///
+    /// The error returned by the callback terminates the construction and is returned. The error
+    /// type needs to be able to hold the [`TooLong`] error too.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{OwnedSlice, TooLong};
+    /// let s = OwnedSlice::<u8>::copy_from_fill(3, |buf| {
+    ///     buf.copy_from_slice(b"abc");
+    ///     Ok::<_, TooLong>(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(b"abc", &s[..]);
+    /// ```
+    pub fn copy_from_fill<E, F>(len: usize, mut fill: F) -> Result<Self, E>
+    where
+        F: FnMut(&mut [u8]) -> Result<(), E>,
+        E: From<TooLong>,
+    {
+        let alloc = |layout| {
+            let ptr = unsafe { mem_alloc_zeroed(layout) };
+            NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(layout))
+        };
+        let mut builder = Builder::<u8, H>::new_in(len, alloc, true)?;
+        // The memory is zeroed, so these are valid bytes. But they are marked as initialized only
+        // after the callback succeeds, so a failure only frees the memory.
+        let data = unsafe { slice::from_raw_parts_mut(builder.data, len) };
+        fill(data)?;
+        unsafe { builder.assume_initialized() };
+
+        Ok(builder.finish())
+    }
+}
+
+impl OwnedSlice<u8, BoxHeader> {
+    /// Exposes the whole heap allocation (header, extra length bytes and the data) as bytes.
+    ///
+    /// This allows persisting the slice verbatim and later reconstructing it with
+    /// [`from_raw_heap_bytes`][OwnedSlice::from_raw_heap_bytes], without re-encoding the length.
+    /// The empty slice doesn't allocate, so this returns an empty slice for it.
+    ///
+    /// This is available only for the [`BoxHeader`], other headers contain reference counts
+    /// (which are both changing and meaningless when persisted).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u8> = OwnedSlice::new(b"hello").unwrap();
+    /// // 1 byte of header and the data
+    /// assert_eq!(b"\x05hello", s.raw_heap_bytes());
+    /// ```
+    pub fn raw_heap_bytes(&self) -> &[u8] {
+        if self.is_sentinel() {
+            return &[];
+        }
+
+        let size = Self::layout(self.len()).size();
+        unsafe { slice::from_raw_parts(self.header.as_ptr().cast::<u8>(), size) }
+    }
+
+    /// Reconstructs the slice from bytes previously returned by
+    /// [`raw_heap_bytes`][OwnedSlice::raw_heap_bytes].
+    ///
+    /// The bytes are validated (the length encoded in the header must match the number of
+    /// bytes) and copied into a new allocation.
+    ///
+    /// # Errors
+    ///
+    /// If the bytes don't form a valid encoding of a slice, [`DecodeError`] is returned.
+    pub fn from_raw_heap_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let len_off = Self::len_offset();
+        if bytes.len() < len_off {
+            return Err(DecodeError::Truncated);
+        }
+        let header = unsafe { ptr::read_unaligned(bytes.as_ptr().cast::<BoxHeader>()) };
+        let extra = header.extra_len().ok_or(DecodeError::Corrupted)?;
+        if bytes.len() < len_off + extra {
+            return Err(DecodeError::Truncated);
+        }
+        let len = unsafe { header.decode_len(bytes.as_ptr().add(len_off)) };
+        // Only the shortest encoding is valid
+        if BoxHeader::extra_needed(len).ok() != Some(extra) {
+            return Err(DecodeError::Corrupted);
+        }
+        let layout = Self::layout(len);
+        if bytes.len() < layout.size() {
+            return Err(DecodeError::Truncated);
+        } else if bytes.len() > layout.size() {
+            return Err(DecodeError::Corrupted);
+        }
+        if len == 0 {
+            return Ok(Self::default());
+        }
+
+        unsafe {
+            let ptr = mem_alloc(layout);
+            if ptr.is_null() {
+                handle_alloc_error(layout);
+            }
+            ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+            Ok(Self {
+                header: NonNull::new_unchecked(ptr.cast()),
+                _data: PhantomData,
+            })
+        }
+    }
+}
+
+impl<T> OwnedSlice<T, BoxHeader> {
+    /// Converts the slice into a boxed slice.
+    ///
+    /// The elements are moved (not cloned) into a new allocation of the [`Box`] and the original
+    /// allocation is freed. This is the way back to the standard types, for example to get a
+    /// growable [`Vec`] by [`into_vec`][slice::into_vec].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<String> = OwnedSlice::new(&["a".to_owned(), "b".to_owned()]).unwrap();
+    /// let mut v = s.into_boxed_slice().into_vec();
+    /// v.push("c".to_owned());
+    /// assert_eq!(vec!["a", "b", "c"], v);
+    /// ```
+    pub fn into_boxed_slice(self) -> Box<[T]> {
+        if self.is_sentinel() {
+            return Box::new([]);
+        }
+        let len = self.len();
+        let mut result = Vec::with_capacity(len);
+        let this = ManuallyDrop::new(self);
+        unsafe {
+            ptr::copy_nonoverlapping(this.data(len), result.as_mut_ptr(), len);
+            result.set_len(len);
+            // The elements are moved out, only the memory is freed.
+            mem_dealloc(this.header.as_ptr().cast(), Self::layout(len));
+        }
+        result.into_boxed_slice()
+    }
+
+    /// Leaks the slice, returning a reference to its data that lives as long as needed.
+    ///
+    /// This is the equivalent of [`Box::leak`][alloc::boxed::Box::leak], handy for data created
+    /// once and then used for the rest of the program. The whole allocation, including the
+    /// header, is leaked and never freed. The empty slice doesn't allocate, so nothing is leaked
+    /// in its case.
+    ///
+    /// This is an associated function, like the [`Box::leak`][alloc::boxed::Box::leak].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// let leaked: &'static mut [u16] = OwnedSlice::leak(s);
+    /// leaked[0] = 42;
+    /// assert_eq!(&[42, 2, 3], leaked);
+    /// ```
+    pub fn leak<'a>(this: Self) -> &'a mut [T]
+    where
+        T: 'a,
+    {
+        let mut this = ManuallyDrop::new(this);
+        let data = this.deref_mut();
+        // The allocation is never freed, so the data live as long as anyone wants.
+        unsafe { slice::from_raw_parts_mut(data.as_mut_ptr(), data.len()) }
+    }
+
+    /// Returns an iterator allowing to modify the elements.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let mut s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// for v in s.iter_mut() {
+    ///     *v *= 2;
+    /// }
+    /// assert_eq!(&[2, 4, 6], &s[..]);
+    /// ```
+    #[inline]
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.deref_mut().iter_mut()
+    }
+
+    /// Returns a mutable reference to the first element, or `None` if the slice is empty.
+    #[inline]
+    pub fn first_mut(&mut self) -> Option<&mut T> {
+        self.deref_mut().first_mut()
+    }
+
+    /// Returns a mutable reference to the last element, or `None` if the slice is empty.
+    #[inline]
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.deref_mut().last_mut()
+    }
+
+    /// Reverses the order of elements in place.
+    ///
+    /// The same as [`reverse`][slice::reverse] on the slice, but returns the owned slice back to
+    /// allow chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let mut s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// s.reverse().rotate_left(1);
+    /// assert_eq!(&[2, 1, 3], &s[..]);
+    /// ```
+    pub fn reverse(&mut self) -> &mut Self {
+        if !self.is_sentinel() {
+            self.deref_mut().reverse();
+        }
+        self
+    }
+
+    /// Swaps two elements in place.
+    ///
+    /// The same as [`swap`][slice::swap] on the slice, but returns the owned slice back to allow
+    /// chaining.
+    ///
+    /// # Panics
+    ///
+    /// If either of the indices is out of bounds (this includes any index on an empty slice).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let mut s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// s.swap(0, 2);
+    /// assert_eq!(&[3, 2, 1], &s[..]);
+    /// ```
+    pub fn swap(&mut self, a: usize, b: usize) -> &mut Self {
+        self.deref_mut().swap(a, b);
+        self
+    }
+
+    /// Swaps two elements in place, without checking the bounds.
+    ///
+    /// See [`swap`][OwnedSlice::swap] for the checked version.
+    ///
+    /// # Safety
+    ///
+    /// Both indices must be smaller than the length. Calling this on an empty slice is always
+    /// undefined behaviour. The indices may be equal.
+    pub unsafe fn swap_unchecked(&mut self, a: usize, b: usize) -> &mut Self {
+        let len = self.len();
+        debug_assert!(a < len && b < len, "Swap index out of bounds");
+        let data = self.data(len);
+        // Not ptr::swap_nonoverlapping, the indices may be the same
+        ptr::swap(data.add(a), data.add(b));
+        self
+    }
+
+    /// Sorts the elements in place.
+    ///
+    /// The same as [`sort`][slice::sort] on the slice, but returns the owned slice back to allow
+    /// chaining.
+    pub fn sort(&mut self) -> &mut Self
+    where
+        T: Ord,
+    {
+        if !self.is_sentinel() {
+            self.deref_mut().sort();
+        }
+        self
+    }
+
+    /// Rotates the elements in place, so the first `mid` elements move to the end.
+    ///
+    /// See [`rotate_left`][slice::rotate_left] on the slice.
+    ///
+    /// # Panics
+    ///
+    /// If `mid` is larger than the length.
+    pub fn rotate_left(&mut self, mid: usize) -> &mut Self {
+        if self.is_sentinel() {
+            assert_eq!(0, mid, "Rotating an empty slice");
+        } else {
+            self.deref_mut().rotate_left(mid);
+        }
+        self
+    }
+
+    /// Rotates the elements in place, so the last `k` elements move to the front.
+    ///
+    /// See [`rotate_right`][slice::rotate_right] on the slice.
+    ///
+    /// # Panics
+    ///
+    /// If `k` is larger than the length.
+    pub fn rotate_right(&mut self, k: usize) -> &mut Self {
+        if self.is_sentinel() {
+            assert_eq!(0, k, "Rotating an empty slice");
+        } else {
+            self.deref_mut().rotate_right(k);
+        }
+        self
+    }
+
+    /// Returns a raw mutable pointer to the first element of the data.
+    ///
+    /// Available only for the [`BoxHeader`], which is always the unique owner of the data. The
+    /// pointer is valid for reading and writing as long as the slice is alive and not accessed by
+    /// other means. For an empty slice, it is a dangling (but non-null and aligned) pointer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let mut s: OwnedSlice<u16> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+    /// unsafe { *s.as_mut_ptr() = 42 };
+    /// assert_eq!(&[42, 2, 3], &s[..]);
+    /// ```
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.as_ptr() as *mut T
+    }
+
+    /// Keeps only the elements matching the predicate, dropping the rest.
+    ///
+    /// This is the consuming variant of [`filtered`][OwnedSlice::filtered]. The predicate is
+    /// called exactly once for each element, in order. The kept elements are moved to the front
+    /// of the existing allocation and the header is rewritten for the new length (the allocation
+    /// is shrunk, which may move it). If all the elements are kept, the slice is returned as it is
+    /// and if none is, the memory is freed.
+    ///
+    /// If the predicate panics, all the elements (kept, rejected and not yet examined ones) are
+    /// dropped exactly once and the memory is freed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::OwnedSlice;
+    /// let s: OwnedSlice<String> =
+    ///     OwnedSlice::new(&["a".to_owned(), "".to_owned(), "b".to_owned()]).unwrap();
+    /// let s = s.filter_into(|v| !v.is_empty());
+    /// assert_eq!(&["a", "b"], &s[..]);
+    /// ```
+    pub fn filter_into<F>(self, mut pred: F) -> Self
+    where
+        F: FnMut(&T) -> bool,
+    {
+        if self.is_sentinel() {
+            return self;
+        }
+
+        let len = self.len();
+        let mut retain = Retain {
+            data: self.data(len),
+            header: Self::into_raw(self),
+            len,
+            processed: 0,
+            kept: 0,
+        };
+        while retain.processed < len {
+            let current = unsafe { retain.data.add(retain.processed) };
+            if pred(unsafe { &*current }) {
+                if retain.kept != retain.processed {
+                    unsafe { ptr::copy_nonoverlapping(current, retain.data.add(retain.kept), 1) };
+                }
+                retain.kept += 1;
+                retain.processed += 1;
+            } else {
+                // Account for it before dropping, in case the drop panics.
+                retain.processed += 1;
+                unsafe { ptr::drop_in_place(current) };
+            }
+        }
+
+        let (header, new_len) = (retain.header, retain.kept);
+        mem::forget(retain);
+        if new_len == len {
+            return unsafe { Self::from_raw(header) };
+        }
+
+        let ptr = header.as_ptr().cast::<u8>();
+        let old_layout = Self::layout(len);
+        if new_len == 0 {
+            unsafe { mem_dealloc(ptr, old_layout) };
+            return Self::default();
+        }
+
+        let (new_layout, len_off, data_off) =
+            Self::layout_and_offsets(new_len).expect("Shorter than the original");
+        unsafe {
+            // Fewer extra bytes may be needed for the shorter length, moving the data forward.
+            let old_data_off = Self::data_offset(len);
+            if data_off != old_data_off {
+                ptr::copy(
+                    ptr.add(old_data_off).cast::<T>(),
+                    ptr.add(data_off).cast::<T>(),
+                    new_len,
+                );
+            }
+            ptr::write(
+                ptr.cast::<BoxHeader>(),
+                BoxHeader::encode_len(new_len, ptr.add(len_off)),
+            );
+            let ptr = mem_realloc(ptr, old_layout, new_layout.size());
+            if ptr.is_null() {
+                handle_alloc_error(new_layout);
+            }
+            Self::from_raw(NonNull::new_unchecked(ptr.cast()))
+        }
+    }
+}
+
+impl<T, H> OwnedSlice<MaybeUninit<T>, H>
+where
+    H: Header,
+{
+    /// Converts the slice created by [`try_new_uninit`][OwnedSlice::try_new_uninit] into the
+    /// initialized one.
+    ///
+    /// This doesn't copy the data, the same allocation is reused.
+    ///
+    /// # Safety
+    ///
+    /// All the elements must be initialized. If the data is shared (with sharing headers), all
+    /// the owners need to be treated as if they were converted too.
+    pub unsafe fn assume_init(self) -> OwnedSlice<T, H> {
+        let this = ManuallyDrop::new(self);
+        // MaybeUninit<T> has the same layout as T, therefore the whole allocation is the same.
+        OwnedSlice {
+            header: this.header,
+            _data: PhantomData,
+        }
+    }
+}
+
+/// An owned slice under construction.
+///
+/// It holds the allocation with the header already written and keeps track of how many elements
+/// were initialized. If it is dropped before being finished (because of a panic or an early error
+/// return), the initialized prefix is dropped and the memory freed.
+pub(crate) struct Builder<T, H>
+where
+    H: Header,
+{
+    header: NonNull<H>,
+    data: *mut T,
+    /// The layout to free the memory with, if the memory is ours to free.
+    layout: Option<Layout>,
+    len: usize,
+    initialized: usize,
+}
+
+impl<T, H> Builder<T, H>
+where
+    H: Header,
+{
+    pub(crate) fn new(len: usize) -> Result<Self, TooLong> {
+        let alloc = |layout| {
+            let ptr = unsafe { mem_alloc(layout) };
+            NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(layout))
+        };
+        Self::new_in(len, alloc, true)
+    }
+
+    /// Creates the builder in memory provided by the `alloc` callback.
+    ///
+    /// The callback is called with the layout of the whole slice and must return memory fitting
+    /// it. It is not called for empty slices. If `owned` is set, the memory is freed by the
+    /// global allocator if the construction fails.
+    pub(crate) fn new_in<A>(len: usize, alloc: A, owned: bool) -> Result<Self, TooLong>
+    where
+        A: FnOnce(Layout) -> NonNull<u8>,
+    {
+        Self::new_in_fallible(len, |layout| Ok(alloc(layout)), owned)
+    }
+
+    /// Creates the builder, reporting allocation failures instead of aborting.
+    pub(crate) fn new_no_abort(len: usize) -> Result<Self, AllocOrTooLong> {
+        let alloc = |layout| {
+            NonNull::new(unsafe { mem_alloc(layout) }).ok_or(AllocOrTooLong::Alloc(AllocError))
+        };
+        Self::new_in_fallible(len, alloc, true)
+    }
+
+    /// Like [`new_in`][Builder::new_in], but the `alloc` callback may fail.
+    pub(crate) fn new_in_fallible<A, E>(len: usize, alloc: A, owned: bool) -> Result<Self, E>
+    where
+        A: FnOnce(Layout) -> Result<NonNull<u8>, E>,
+        E: From<TooLong>,
+    {
+        if owned {
+            assert_global_alloc::<H>();
+        }
+        if len == 0 {
+            // Use the sentinel thing, nothing gets allocated.
+            let sentinel = OwnedSlice::<T, H>::default();
+            let header = sentinel.header;
+            mem::forget(sentinel);
+            return Ok(Self {
+                header,
+                data: NonNull::dangling().as_ptr(),
+                layout: None,
+                len,
+                initialized: 0,
+            });
+        }
+
+        let (layout, len_off, data_off) = OwnedSlice::<T, H>::layout_and_offsets(len)?;
+        let ptr = alloc(layout)?.as_ptr();
+        unsafe {
+            let hdr = ptr.cast::<H>();
+            ptr::write(hdr, H::encode_len(len, ptr.add(len_off)));
+
+            Ok(Self {
+                header: NonNull::new(hdr).unwrap(),
+                data: ptr.add(data_off).cast::<T>(),
+                layout: if owned { Some(layout) } else { None },
+                len,
+                initialized: 0,
+            })
+        }
+    }
+
+    #[inline]
+    pub(crate) fn push(&mut self, value: T) {
+        assert!(self.initialized < self.len, "Pushing more than allocated");
+        unsafe { ptr::write(self.data.add(self.initialized), value) };
+        // Only after it is actually written
+        self.initialized += 1;
+    }
+
+    /// Copies the elements at the end of the initialized part.
+    pub(crate) fn extend_copied(&mut self, src: &[T])
+    where
+        T: Copy,
+    {
+        assert!(
+            src.len() <= self.len - self.initialized,
+            "Pushing more than allocated"
+        );
+        unsafe {
+            ptr::copy_nonoverlapping(src.as_ptr(), self.data.add(self.initialized), src.len())
+        };
+        self.initialized += src.len();
+    }
+
+    /// Fills the rest of the slice by repeating the already initialized part.
+    ///
+    /// The length needs to be a multiple of the initialized part. It doubles the initialized part
+    /// in each step, so it needs only logarithmic number of copies.
+    pub(crate) fn fill_repeated(&mut self)
+    where
+        T: Copy,
+    {
+        assert!(
+            self.len == 0 || (self.initialized > 0 && self.len.is_multiple_of(self.initialized)),
+            "Length is not a multiple of the pattern"
+        );
+        while self.initialized < self.len {
+            let chunk = cmp::min(self.initialized, self.len - self.initialized);
+            unsafe { ptr::copy_nonoverlapping(self.data, self.data.add(self.initialized), chunk) };
+            self.initialized += chunk;
+        }
+    }
+
+    /// Shrinks the slice to the already initialized elements.
+    ///
+    /// The header is rewritten, the data moved if the shorter length needs fewer extra bytes and
+    /// the allocation shrunk. The memory must be owned by the builder.
+    pub(crate) fn truncate_to_initialized(&mut self) {
+        let len = self.initialized;
+        if len == self.len {
+            return;
+        }
+        let layout = self.layout.expect("Can't shrink borrowed memory");
+        let ptr = self.header.as_ptr().cast::<u8>();
+        if len == 0 {
+            unsafe { mem_dealloc(ptr, layout) };
+            let sentinel = OwnedSlice::<T, H>::default();
+            self.header = sentinel.header;
+            mem::forget(sentinel);
+            self.data = NonNull::dangling().as_ptr();
+            self.layout = None;
+            self.len = 0;
+            return;
+        }
+
+        let (new_layout, len_off, data_off) =
+            OwnedSlice::<T, H>::layout_and_offsets(len).expect("Shorter than the original");
+        unsafe {
+            let new_data = ptr.add(data_off).cast::<T>();
+            if new_data != self.data {
+                ptr::copy(self.data, new_data, len);
+            }
+            // Nobody references the header yet, it can be simply overwritten.
+            ptr::write(ptr.cast::<H>(), H::encode_len(len, ptr.add(len_off)));
+            let ptr = mem_realloc(ptr, layout, new_layout.size());
+            if ptr.is_null() {
+                handle_alloc_error(new_layout);
+            }
+            self.header = NonNull::new_unchecked(ptr.cast());
+            self.data = ptr.add(data_off).cast();
+        }
+        self.layout = Some(new_layout);
+        self.len = len;
+    }
+
+    /// The pointer to the (possibly uninitialized) data.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn data_ptr(&self) -> *mut T {
+        self.data
+    }
+
+    /// Marks all the elements as initialized.
+    ///
+    /// # Safety
+    ///
+    /// All the elements must have been written through the data pointer.
+    pub(crate) unsafe fn assume_initialized(&mut self) {
+        self.initialized = self.len;
+    }
+
+    pub(crate) fn finish(self) -> OwnedSlice<T, H> {
+        assert_eq!(self.initialized, self.len, "Not all elements initialized");
+        let result = OwnedSlice {
+            header: self.header,
+            _data: PhantomData,
+        };
+        // Disarm the guard, the ownership is passed to the result.
+        mem::forget(self);
+        result
+    }
+}
+
+impl<T, H> Drop for Builder<T, H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        unsafe {
+            for i in 0..self.initialized {
+                ptr::drop_in_place(self.data.add(i));
+            }
+            if let Some(layout) = self.layout {
+                mem_dealloc(self.header.as_ptr().cast::<u8>(), layout);
+            }
+        }
+    }
+}
+
+/// Moves elements out of a uniquely owned slice, one by one.
+///
+/// Once dropped, the elements not yet moved out are dropped and the memory is freed.
+struct MoveOut<T, H>
+where
+    H: Header,
+{
+    slice: ManuallyDrop<OwnedSlice<T, H>>,
+    data: *mut T,
+    len: usize,
+    taken: usize,
+}
+
+impl<T, H> MoveOut<T, H>
+where
+    H: Header,
+{
+    fn new(slice: OwnedSlice<T, H>) -> Self {
+        debug_assert!(slice.is_sentinel() || unsafe { slice.header.as_ref().is_unique() });
+        let len = slice.len();
+        let data = if slice.is_sentinel() {
+            NonNull::dangling().as_ptr()
+        } else {
+            slice.data(len)
+        };
+        Self {
+            slice: ManuallyDrop::new(slice),
+            data,
+            len,
+            taken: 0,
+        }
+    }
+}
+
+impl<T, H> Iterator for MoveOut<T, H>
+where
+    H: Header,
+{
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.taken < self.len {
+            let value = unsafe { ptr::read(self.data.add(self.taken)) };
+            self.taken += 1;
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, H> Drop for MoveOut<T, H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        if self.slice.is_sentinel() {
+            return;
+        }
+
+        unsafe {
+            for i in self.taken..self.len {
+                ptr::drop_in_place(self.data.add(i));
+            }
+            let layout = OwnedSlice::<T, H>::layout(self.len);
+            mem_dealloc(self.slice.header.as_ptr().cast::<u8>(), layout);
+        }
+    }
+}
+
+/// Guard of [`try_map_in_place`][OwnedSlice::try_map_in_place].
+///
+/// The elements before `mapped` are already of the type `U`, the ones from `taken` on are still
+/// the original `T` (the one between was moved out to the closure).
+struct MapInPlace<T, U, H>
+where
+    H: Header,
+{
+    header: NonNull<H>,
+    data: *mut T,
+    len: usize,
+    mapped: usize,
+    taken: usize,
+    _result: PhantomData<U>,
+}
+
+impl<T, U, H> Drop for MapInPlace<T, U, H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        unsafe {
+            for i in 0..self.mapped {
+                ptr::drop_in_place(self.data.add(i).cast::<U>());
+            }
+            for i in self.taken..self.len {
+                ptr::drop_in_place(self.data.add(i));
+            }
+            let layout = OwnedSlice::<T, H>::layout(self.len);
+            mem_dealloc(self.header.as_ptr().cast::<u8>(), layout);
+        }
+    }
+}
+
+/// Compacts a uniquely owned slice in place, keeping only some elements.
+///
+/// The elements `[0, kept)` are the kept ones, `[kept, processed)` are moved out or dropped and
+/// `[processed, len)` are not examined yet. If dropped (because of a panic), both the kept and
+/// not examined elements are dropped and the memory is freed.
+struct Retain<T, H>
+where
+    H: Header,
+{
+    header: NonNull<H>,
+    data: *mut T,
+    len: usize,
+    processed: usize,
+    kept: usize,
+}
+
+impl<T, H> Drop for Retain<T, H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        unsafe {
+            for i in (0..self.kept).chain(self.processed..self.len) {
+                ptr::drop_in_place(self.data.add(i));
+            }
+            let layout = OwnedSlice::<T, H>::layout(self.len);
+            mem_dealloc(self.header.as_ptr().cast::<u8>(), layout);
+        }
+    }
+}
+
+impl<T, H> Drop for OwnedSlice<T, H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        if self.is_sentinel() {
+            return;
+        }
+
+        unsafe {
+            if self.header.as_ref().dec() {
+                let len = self.len();
+                let layout = Self::layout(len);
+                if mem::needs_drop::<T>() {
+                    let data = self.data(len);
+
+                    for i in 0..len {
+                        ptr::drop_in_place(data.add(i));
+                    }
+                }
+
+                // The strong references together hold one weak reference, the memory is freed
+                // once there are no weak ones either.
+                if self.header.as_ref().dec_weak() {
+                    mem_dealloc(self.header.as_ptr().cast::<u8>(), layout);
+                }
+            }
+        }
+    }
+}
+
+/// Clones the slice, see [`CloneStrategy`] for the bounds.
+impl<T, H> Clone for OwnedSlice<T, H>
+where
+    H: Header,
+    H::Ownership: CloneStrategy<T>,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        H::Ownership::clone_slice(self)
+    }
+}
+
+impl<T> CloneStrategy<T> for Unique
+where
+    T: Clone,
+{
+    fn clone_slice<H>(slice: &OwnedSlice<T, H>) -> OwnedSlice<T, H>
+    where
+        H: Header<Ownership = Self>,
+    {
+        slice
+            .try_clone()
+            .expect("Already have layout for this size")
+    }
+}
+
+impl<T> CloneStrategy<T> for CopyOnOverflow
+where
+    T: Clone,
+{
+    fn clone_slice<H>(slice: &OwnedSlice<T, H>) -> OwnedSlice<T, H>
+    where
+        H: Header<Ownership = Self>,
+    {
+        slice
+            .try_clone()
+            .expect("Already have layout for this size")
+    }
+}
+
+impl<T> CloneStrategy<T> for Shared {
+    fn clone_slice<H>(slice: &OwnedSlice<T, H>) -> OwnedSlice<T, H>
+    where
+        H: Header<Ownership = Self>,
+    {
+        if slice.is_sentinel() {
+            return OwnedSlice::default();
+        }
+        slice.share().expect("Reference count overflow")
+    }
+}
+
+impl<T> ConvertStrategy<T> for Unique {
+    fn convert_slice<H, H2>(slice: OwnedSlice<T, H>) -> Result<OwnedSlice<T, H2>, TooLong>
+    where
+        H: Header<Ownership = Self>,
+        H2: Header,
+    {
+        slice.convert_unique()
+    }
+}
+
+impl<T> ConvertStrategy<T> for CopyOnOverflow
+where
+    T: Clone,
+{
+    fn convert_slice<H, H2>(slice: OwnedSlice<T, H>) -> Result<OwnedSlice<T, H2>, TooLong>
+    where
+        H: Header<Ownership = Self>,
+        H2: Header,
+    {
+        slice.convert_or_clone()
+    }
+}
+
+impl<T> ConvertStrategy<T> for Shared
+where
+    T: Clone,
+{
+    fn convert_slice<H, H2>(slice: OwnedSlice<T, H>) -> Result<OwnedSlice<T, H2>, TooLong>
+    where
+        H: Header<Ownership = Self>,
+        H2: Header,
+    {
+        slice.convert_or_clone()
+    }
+}
+
+impl<T, H> Deref for OwnedSlice<T, H>
+where
+    H: Header,
+{
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        if self.is_sentinel() {
+            return &[];
+        }
+
+        let len = self.len();
+        unsafe { slice::from_raw_parts(self.data(len), len) }
+    }
+}
+
+impl<T> DerefMut for OwnedSlice<T, BoxHeader> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if self.is_sentinel() {
+            return &mut [];
+        }
+
+        let len = self.len();
+        unsafe { slice::from_raw_parts_mut(self.data(len), len) }
+    }
+}
+
+impl<T, H, I> Index<I> for OwnedSlice<T, H>
+where
+    H: Header,
+    I: SliceIndex<[T]>,
+{
+    type Output = I::Output;
+
+    #[inline]
+    fn index(&self, index: I) -> &I::Output {
+        self.deref().index(index)
+    }
+}
+
+impl<T, I> IndexMut<I> for OwnedSlice<T, BoxHeader>
+where
+    I: SliceIndex<[T]>,
+{
+    #[inline]
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        self.deref_mut().index_mut(index)
+    }
+}
+
+/// Creates the slice from the array, moving the elements.
+///
+/// # Panics
+///
+/// If `N` is longer than the header can encode.
+impl<T, H, const N: usize> From<[T; N]> for OwnedSlice<T, H>
+where
+    H: Header,
+{
+    fn from(array: [T; N]) -> Self {
+        Self::from_iter_len(N, array).expect("Array too long for the header")
+    }
+}
+
+/// Moves the elements out of the slice into an array.
+///
+/// The slice is returned back if the length doesn't match or if it is shared with other owners
+/// (then the elements can't be moved out).
+impl<T, H, const N: usize> TryFrom<OwnedSlice<T, H>> for [T; N]
+where
+    H: Header,
+{
+    type Error = OwnedSlice<T, H>;
+
+    fn try_from(slice: OwnedSlice<T, H>) -> Result<Self, OwnedSlice<T, H>> {
+        let unique = slice.is_sentinel() || unsafe { slice.header.as_ref().is_unique() };
+        if slice.len() != N || !unique {
+            return Err(slice);
+        }
+        let mut elements = MoveOut::new(slice);
+        Ok(core::array::from_fn(|_| {
+            elements.next().expect("Length already checked")
+        }))
+    }
+}
+
+impl<'a, T, H> IntoIterator for &'a OwnedSlice<T, H>
+where
+    H: Header,
+{
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut OwnedSlice<T, BoxHeader> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Compares the elements.
+///
+/// The slices can have different headers and even different element types, as long as they are
+/// comparable, only the content matters. The lengths are compared first (they are cheap to decode
+/// from the headers), the data are touched only if they match. For bytes and similar types, the
+/// comparison of the data ends up being a `memcmp`.
+impl<T, U, H1, H2> PartialEq<OwnedSlice<U, H2>> for OwnedSlice<T, H1>
+where
+    H1: Header,
+    H2: Header,
+    T: PartialEq<U>,
+{
+    #[inline]
+    fn eq(&self, other: &OwnedSlice<U, H2>) -> bool {
+        self.len() == other.len() && self.deref() == other.deref()
+    }
+}
+
+impl<T, U, H> PartialEq<[U]> for OwnedSlice<T, H>
+where
+    H: Header,
+    T: PartialEq<U>,
+{
+    #[inline]
+    fn eq(&self, other: &[U]) -> bool {
+        self.deref() == other
+    }
+}
+
+impl<T, U, H> PartialEq<&[U]> for OwnedSlice<T, H>
+where
+    H: Header,
+    T: PartialEq<U>,
+{
+    #[inline]
+    fn eq(&self, other: &&[U]) -> bool {
+        self.deref() == *other
+    }
+}
+
+impl<T, U, H, const N: usize> PartialEq<[U; N]> for OwnedSlice<T, H>
+where
+    H: Header,
+    T: PartialEq<U>,
+{
+    #[inline]
+    fn eq(&self, other: &[U; N]) -> bool {
+        self.deref() == other
+    }
+}
+
+impl<T, U, H> PartialEq<OwnedSlice<U, H>> for [T]
+where
+    H: Header,
+    T: PartialEq<U>,
+{
+    #[inline]
+    fn eq(&self, other: &OwnedSlice<U, H>) -> bool {
+        self == other.deref()
+    }
+}
+
+impl<T, U, H> PartialEq<OwnedSlice<U, H>> for &[T]
+where
+    H: Header,
+    T: PartialEq<U>,
+{
+    #[inline]
+    fn eq(&self, other: &OwnedSlice<U, H>) -> bool {
+        *self == other.deref()
+    }
+}
+
+impl<T, U, H, const N: usize> PartialEq<OwnedSlice<U, H>> for [T; N]
+where
+    H: Header,
+    T: PartialEq<U>,
+{
+    #[inline]
+    fn eq(&self, other: &OwnedSlice<U, H>) -> bool {
+        self[..] == *other.deref()
+    }
+}
+
+/// Compares the elements lexicographically.
+///
+/// Like with the equality, the slices can have different headers.
+impl<T, H1, H2> PartialOrd<OwnedSlice<T, H2>> for OwnedSlice<T, H1>
+where
+    H1: Header,
+    H2: Header,
+    T: PartialOrd,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &OwnedSlice<T, H2>) -> Option<Ordering> {
+        self.deref().partial_cmp(other.deref())
+    }
+}
+
+impl<T, H> Ord for OwnedSlice<T, H>
+where
+    H: Header,
+    T: Ord,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+impl<T, H> Eq for OwnedSlice<T, H>
+where
+    H: Header,
+    T: Eq,
+{
+}
+
+impl<T, H> Debug for OwnedSlice<T, H>
+where
+    H: Header,
+    T: Debug,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        self.deref().fmt(fmt)
+    }
+}
+
+/// Formats the elements as a bracketed, comma-separated list.
+///
+/// This is the same format as [`Debug`] uses, but with the elements formatted by their
+/// [`Display`] implementation (so strings are not quoted). The format flags (like the precision)
+/// are applied to each element. For bytes, the [`LowerHex`] and [`UpperHex`] implementations
+/// provide a hex dump instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::OwnedSlice;
+/// let s: OwnedSlice<&str> = OwnedSlice::new(&["a", "b"]).unwrap();
+/// assert_eq!("[a, b]", s.to_string());
+/// ```
+impl<T, H> Display for OwnedSlice<T, H>
+where
+    H: Header,
+    T: Display,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.write_char('[')?;
+        for (idx, elem) in self.iter().enumerate() {
+            if idx > 0 {
+                fmt.write_str(", ")?;
+            }
+            Display::fmt(elem, fmt)?;
+        }
+        fmt.write_char(']')
+    }
+}
+
+/// Formats the bytes as a contiguous hex string.
+///
+/// The width, fill, alignment and the `#` (`0x` prefix) flags are respected, padding with zeroes
+/// (`{:08x}`) is put after the prefix.
+fn fmt_hex(bytes: &[u8], fmt: &mut Formatter, upper: bool) -> FmtResult {
+    let prefix = if fmt.alternate() { "0x" } else { "" };
+    let len = prefix.len() + bytes.len() * 2;
+    let padding = fmt.width().unwrap_or(0).saturating_sub(len);
+    let (fill, pre, post) = if fmt.sign_aware_zero_pad() {
+        ('0', padding, 0)
+    } else {
+        let (pre, post) = match fmt.align() {
+            Some(Alignment::Left) => (0, padding),
+            Some(Alignment::Center) => (padding / 2, padding - padding / 2),
+            Some(Alignment::Right) | None => (padding, 0),
+        };
+        (fmt.fill(), pre, post)
+    };
+
+    if fill == '0' {
+        fmt.write_str(prefix)?;
+    }
+    for _ in 0..pre {
+        fmt.write_char(fill)?;
+    }
+    if fill != '0' {
+        fmt.write_str(prefix)?;
+    }
+    for b in bytes {
+        if upper {
+            write!(fmt, "{:02X}", b)?;
+        } else {
+            write!(fmt, "{:02x}", b)?;
+        }
+    }
+    for _ in 0..post {
+        fmt.write_char(fill)?;
+    }
+    Ok(())
+}
+
+/// Formats the bytes as a contiguous lower case hex string.
+///
+/// If you want a human readable escaped form instead, use the
+/// [`escape_ascii`][slice::escape_ascii] method of the slice.
+///
+/// ```rust
+/// use squash::OwnedSlice;
+/// let s: OwnedSlice<u8> = OwnedSlice::new(b"Hi\x01").unwrap();
+/// assert_eq!("486901", format!("{:x}", s));
+/// assert_eq!("0x486901", format!("{:#x}", s));
+/// assert_eq!("  486901", format!("{:>8x}", s));
+/// assert_eq!("Hi\\x01", s.escape_ascii().to_string());
+/// ```
+impl<H> LowerHex for OwnedSlice<u8, H>
+where
+    H: Header,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt_hex(self, fmt, false)
+    }
+}
+
+/// Formats the bytes as a contiguous upper case hex string.
+///
+/// See the [`LowerHex`] implementation for details.
+impl<H> UpperHex for OwnedSlice<u8, H>
+where
+    H: Header,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt_hex(self, fmt, true)
+    }
+}
+
+/// Creates an empty slice.
+///
+/// The empty slice doesn't allocate.
+impl<T, H> Default for OwnedSlice<T, H>
+where
+    H: Header,
+{
+    fn default() -> Self {
+        stats::record_sentinel();
+        Self {
+            header: NonNull::new((&ZERO_SENTINEL as *const u8 as *mut u8).cast()).unwrap(),
+            _data: PhantomData,
+        }
+    }
+}
+
+/// A weak reference to a shared [`OwnedSlice`].
+///
+/// This is the equivalent of [`Weak`][alloc::sync::Weak]. It doesn't keep the data alive (only
+/// the memory itself), but it can be turned back into the [`OwnedSlice`] as long as some strong
+/// references still exist. It is created by [`OwnedSlice::downgrade`] and, like the
+/// [`OwnedSlice`], is a single pointer in size.
+///
+/// A weak reference to an empty slice can always be upgraded (empty slices don't allocate and
+/// are never destroyed).
+pub struct WeakSlice<T, H>
+where
+    H: Header,
+{
+    header: NonNull<H>,
+    _data: PhantomData<T>,
+}
+
+impl<T, H> WeakSlice<T, H>
+where
+    H: Header,
+{
+    #[inline]
+    fn is_sentinel(&self) -> bool {
+        ptr::eq(self.header.as_ptr().cast::<u8>(), &ZERO_SENTINEL)
+    }
+
+    /// Attempts to turn the weak reference back into a slice.
+    ///
+    /// Returns `None` if the data was already destroyed.
+    pub fn upgrade(&self) -> Option<OwnedSlice<T, H>> {
+        if self.is_sentinel() || unsafe { self.header.as_ref().upgrade() } {
+            Some(OwnedSlice {
+                header: self.header,
+                _data: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, H> Clone for WeakSlice<T, H>
+where
+    H: Header,
+{
+    fn clone(&self) -> Self {
+        if !self.is_sentinel() {
+            let incremented = unsafe { self.header.as_ref().inc_weak() };
+            assert!(incremented, "Weak count overflowed");
+        }
+
+        Self {
+            header: self.header,
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<T, H> Drop for WeakSlice<T, H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        if self.is_sentinel() {
+            return;
+        }
+
+        unsafe {
+            if self.header.as_ref().dec_weak() {
+                // The data were already dropped by the last strong reference, but the header and
+                // length are still valid.
+                let len = OwnedSlice::<T, H>::decode_len(self.header);
+                let layout = OwnedSlice::<T, H>::layout(len);
+                mem_dealloc(self.header.as_ptr().cast::<u8>(), layout);
+            }
+        }
+    }
+}
+
+impl<T, H> Debug for WeakSlice<T, H>
+where
+    H: Header,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "(Weak)")
+    }
+}
+
+// With some headers, we do Arc-like sharing of stuff. Therefore we need to be conservative about
+// these and require both Send + Sync as the bounds, just like Arc. The uniquely owned ones get the
+// same bounds as Box. The Ownership marker of the header decides which one applies.
+unsafe impl<T, H> Send for OwnedSlice<T, H>
+where
+    H: Header + Send + Sync,
+    <H::Ownership as Ownership>::Marker<T>: Send,
+{
+}
+
+unsafe impl<T, H> Sync for OwnedSlice<T, H>
+where
+    H: Header + Send + Sync,
+    <H::Ownership as Ownership>::Marker<T>: Sync,
+{
+}
+
+unsafe impl<T, H> Send for WeakSlice<T, H>
+where
+    H: Header + Send + Sync,
+    T: Send + Sync,
+{
+}
+
+unsafe impl<T, H> Sync for WeakSlice<T, H>
+where
+    H: Header + Send + Sync,
+    T: Send + Sync,
+{
+}
+
+#[cfg(all(feature = "std", test))]
 mod tests {
+    use std::io::{Cursor, Error as IoError, ErrorKind, Read};
     use std::panic;
+    use std::rc::Rc;
+    use std::thread;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::header::mock::{
+        RcMockHeader, SaturatedMockHeader, ShortMockHeader, TinyRcMockHeader,
+    };
+    use crate::{ArcHeader, RcHeader, Str};
+
+    /// Check we have the null-pointer optimisation.
+    #[test]
+    fn null_ptr_opt() {
+        assert_eq!(
+            mem::size_of::<OwnedSlice<String>>(),
+            mem::size_of::<Option<OwnedSlice<String>>>(),
+        );
+    }
+
+    #[test]
+    fn as_non_null() {
+        let s = OwnedSlice::<u8, RcHeader>::new(b"abc").unwrap();
+        let s2 = s.clone();
+        let copy = OwnedSlice::<u8, RcHeader>::new(&s).unwrap();
+        assert_eq!(OwnedSlice::as_non_null(&s), OwnedSlice::as_non_null(&s2));
+        assert_ne!(OwnedSlice::as_non_null(&s), OwnedSlice::as_non_null(&copy));
+        assert_eq!(
+            OwnedSlice::as_opaque_ptr(&s),
+            OwnedSlice::as_non_null(&s).as_ptr() as *const c_void
+        );
+    }
+
+    /// Pretends to be a C function, borrowing the handle.
+    #[no_mangle]
+    unsafe extern "C" fn squash_test_opaque_len(handle: *const c_void) -> usize {
+        let s = ManuallyDrop::new(OwnedSlice::<String>::from_opaque_ptr(handle as *mut c_void));
+        s.len()
+    }
+
+    /// Pretends to be a C function, taking the ownership of the handle.
+    #[no_mangle]
+    unsafe extern "C" fn squash_test_opaque_free(handle: *mut c_void) {
+        drop(OwnedSlice::<String>::from_opaque_ptr(handle));
+    }
+
+    #[test]
+    fn opaque_ptr() {
+        assert_eq!(
+            mem::size_of::<*const c_void>(),
+            mem::size_of::<OwnedSlice<String>>()
+        );
+        for s in [strings(), OwnedSlice::default()] {
+            let len = s.len();
+            let ptr = OwnedSlice::as_opaque_ptr(&s);
+            assert!(!ptr.is_null());
+            assert_eq!(len, unsafe { squash_test_opaque_len(ptr) });
+
+            let handle = OwnedSlice::into_opaque_ptr(s);
+            assert_eq!(ptr, handle as *const c_void);
+            assert_eq!(len, unsafe { squash_test_opaque_len(handle) });
+            unsafe { squash_test_opaque_free(handle) };
+        }
+
+        let handle = OwnedSlice::into_opaque_ptr(strings());
+        let s = unsafe { OwnedSlice::<String>::from_opaque_ptr(handle) };
+        assert_eq!(strings(), s);
+    }
+
+    /// Exercise the special handling of the sentinel.
+    #[test]
+    fn empty() {
+        let mut s = OwnedSlice::<String>::new(&[]).unwrap();
+        assert_eq!(s.deref(), &[] as &[String]);
+        assert_eq!(s.deref_mut(), &mut [] as &mut [String]);
+        let s2 = s.clone();
+        assert_eq!(&s as &[_], &s2 as &[_]);
+        assert_eq!("[]", format!("{:?}", s));
+        assert_eq!(0, s.len());
+        assert!(s.is_empty());
+
+        let s3 = OwnedSlice::<String>::default();
+        assert_eq!(&s as &[_], &s3 as &[_]);
+    }
+
+    /// Test with few strings.
+    ///
+    /// Use strings so miri can check we run destructors alright.
+    #[test]
+    fn full() {
+        let mut s = OwnedSlice::<String>::new(&["Hello".to_owned(), "World".to_owned()]).unwrap();
+        assert_eq!(2, s.len());
+        assert_eq!((*s).len(), s.len());
+        assert!(!s.is_empty());
+        assert_eq!(s[1], "World");
+        s[0] = "Round".to_owned();
+        assert_eq!(s[0], "Round");
+        let s2 = s.clone();
+        assert_eq!(s.deref(), s2.deref());
+        assert_eq!(2, s2.len());
+        assert_eq!("[\"Round\", \"World\"]", format!("{:?}", s2));
+    }
+
+    #[test]
+    fn long() {
+        let long = vec![0u8; 300];
+        let s = OwnedSlice::<_>::new(&long).unwrap();
+        assert_eq!(long.deref(), s.deref());
+    }
+
+    /// The header can encode the length, but the elements don't fit into the address space.
+    #[test]
+    fn huge_elements() {
+        // Never actually created, only the layout is computed.
+        type Huge = [u8; isize::MAX as usize >> 20];
+        let len = 1 << 29;
+        assert!(len <= BoxHeader::MAX_LEN);
+        let err = OwnedSlice::<Huge>::layout_for_len(len).unwrap_err();
+        assert_eq!(len, err.len);
+        assert!(err.max < len);
+        assert!(OwnedSlice::<Huge>::layout_for_len(1).is_ok());
+    }
+
+    /// Check we can handle panics during partial initialization.
+    ///
+    /// Miri will catch anything we might forget to deallocate. Therefore we put strings in there
+    /// just to make sure there's some allocation in there.
+    #[test]
+    fn panic_in_init() {
+        struct MaybePanic(String);
+
+        impl Clone for MaybePanic {
+            fn clone(&self) -> Self {
+                if self.0 == "!!!" {
+                    panic!("Panicking for the good measure of it");
+                } else {
+                    Self(self.0.clone())
+                }
+            }
+        }
+
+        let input = vec![
+            MaybePanic("One".to_owned()),
+            MaybePanic("Two".to_owned()),
+            MaybePanic("!!!".to_owned()),
+            MaybePanic("Three".to_owned()),
+        ];
+
+        panic::catch_unwind(|| {
+            let _ = OwnedSlice::<MaybePanic>::new(&input);
+        })
+        .unwrap_err();
+    }
+
+    /// Panicking on the very first element must not drop the not yet written one (checked by
+    /// miri).
+    #[test]
+    fn panic_in_init_first() {
+        for panic_at in 0..3 {
+            let drops = Rc::new(Cell::new(0));
+            let src = (0..3)
+                .map(|i| CloneCounter {
+                    clones: Rc::new(Cell::new(0)),
+                    drops: Rc::clone(&drops),
+                    panic_at: if i == panic_at { 0 } else { usize::MAX },
+                })
+                .collect::<Vec<_>>();
+            panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                let _ = OwnedSlice::<CloneCounter>::new(&src);
+            }))
+            .unwrap_err();
+            assert_eq!(panic_at, drops.get());
+        }
+    }
+
+    fn strings() -> OwnedSlice<String> {
+        OwnedSlice::new(&["Hello".to_owned(), "".to_owned(), "World!".to_owned()]).unwrap()
+    }
+
+    /// Moving the strings out and transforming them.
+    #[test]
+    fn map() {
+        let lens = strings().map(|s| s.len());
+        assert_eq!(&[5, 0, 6], lens.deref());
+
+        let empty = OwnedSlice::<String>::default().map(|s| s.len());
+        assert!(empty.is_empty());
+    }
+
+    /// The error is propagated and both the already mapped and the leftover elements are dropped.
+    #[test]
+    fn try_map_err() {
+        let ok = strings().try_map(|s| Ok::<_, ()>(s.into_bytes()));
+        assert_eq!(b"World!", ok.unwrap()[2].as_slice());
+
+        let err = strings().try_map(|s| if s.is_empty() { Err(42) } else { Ok(s + "!") });
+        assert_eq!(42, err.unwrap_err());
+    }
+
+    /// Panicking in the middle of map doesn't leak anything (checked by miri).
+    #[test]
+    fn map_panic() {
+        panic::catch_unwind(|| {
+            strings().map(|s| {
+                assert!(!s.is_empty(), "Panicking for the good measure of it");
+                s + "!"
+            })
+        })
+        .unwrap_err();
+    }
+
+    /// Mutable access is granted only when the data is not shared.
+    #[test]
+    fn get_mut_shared() {
+        let mut s =
+            OwnedSlice::<String, RcMockHeader>::new(&["Hello".to_owned(), "World".to_owned()])
+                .unwrap();
+        OwnedSlice::get_mut(&mut s).unwrap()[0] = "Round".to_owned();
+
+        let mut s2 = s.clone();
+        assert!(OwnedSlice::get_mut(&mut s).is_none());
+        assert!(OwnedSlice::get_mut(&mut s2).is_none());
+        drop(s2);
+
+        OwnedSlice::get_mut(&mut s).unwrap()[1] = "Trip".to_owned();
+        assert_eq!(&["Round", "Trip"], s.deref());
+
+        let mut empty = OwnedSlice::<String, RcMockHeader>::default();
+        let _empty2 = empty.clone();
+        assert_eq!(0, OwnedSlice::get_mut(&mut empty).unwrap().len());
+    }
+
+    #[test]
+    fn concat() {
+        let a = ["a".to_owned(), "b".to_owned()];
+        let b = ["c".to_owned()];
+        let c = ["d".to_owned(), "e".to_owned(), "f".to_owned()];
+        let parts: &[&[String]] = &[&a, &b, &c];
+        let s = OwnedSlice::<String>::concat(parts).unwrap();
+        assert_eq!(parts.concat(), s.deref());
+
+        let single = OwnedSlice::<String>::concat(&[&a]).unwrap();
+        assert_eq!(&a, single.deref());
+
+        let empty = OwnedSlice::<String>::concat(&[]).unwrap();
+        assert!(empty.is_empty());
+        let empty = OwnedSlice::<String>::concat(&[&[], &[]]).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn new_truncated() {
+        let (s, kept) = OwnedSlice::<String>::new_truncated(&strings());
+        assert_eq!(3, kept);
+        assert_eq!(strings(), s);
+
+        let (s, kept) = OwnedSlice::<u8, ShortMockHeader>::new_truncated(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(4, kept);
+        assert_eq!([1, 2, 3, 4], s[..]);
+
+        let (s, kept) = OwnedSlice::<u8, ShortMockHeader>::new_truncated(&[]);
+        assert_eq!(0, kept);
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn extend_from_slice() {
+        let s = strings();
+        let longer = s.extend_from_slice(&["!".to_owned()]).unwrap();
+        assert_eq!(["Hello", "", "World!", "!"], longer[..]);
+        assert_eq!(3, s.len());
+        assert_eq!(s, s.extend_from_slice(&[]).unwrap());
+        let from_empty = OwnedSlice::<String>::default()
+            .extend_from_slice(&s)
+            .unwrap();
+        assert_eq!(s, from_empty);
+    }
+
+    /// Counts how many times it was dropped.
+    #[derive(Clone, Debug)]
+    struct DropCounter(Rc<Cell<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn clear() {
+        let drops = Rc::new(Cell::new(0));
+        let mut s =
+            OwnedSlice::<DropCounter>::from_fn(3, |_| DropCounter(Rc::clone(&drops))).unwrap();
+        s.clear();
+        assert_eq!(3, drops.get());
+        assert!(s.is_sentinel());
+        s.clear();
+        assert_eq!(3, drops.get());
+
+        let mut s = OwnedSlice::<String, RcHeader>::new(&strings()).unwrap();
+        let s2 = s.clone();
+        s.clear();
+        assert_eq!(OwnedSlice::<String, RcHeader>::default(), s);
+        assert_eq!(strings(), s2);
+    }
+
+    #[test]
+    fn filtered() {
+        let s = strings();
+        assert_eq!(&["Hello", "World!"], s.filtered(|s| !s.is_empty()).deref());
+        assert!(s.filtered(|_| false).is_sentinel());
+        let all = s.filtered(|_| true);
+        assert_eq!(s, all);
+        assert_ne!(s.as_ptr(), all.as_ptr());
+        let shared = OwnedSlice::<u32, ArcHeader>::from_fn(100, |i| i as u32).unwrap();
+        let odd = shared.filtered(|i| i % 2 == 1);
+        assert_eq!(50, odd.len());
+        assert!(odd.iter().all(|i| i % 2 == 1));
+        assert_ne!(shared.as_ptr(), shared.filtered(|_| true).as_ptr());
+
+        let s: OwnedSlice<u8> = OwnedSlice::new(&[1, 2, 3, 4, 5]).unwrap();
+        let mut calls = 0;
+        let even = s.filtered(|v| {
+            calls += 1;
+            v % 2 == 0
+        });
+        assert_eq!([2, 4], even[..]);
+        assert_eq!(10, calls);
+    }
+
+    #[test]
+    fn filter_into() {
+        let drops = Rc::new(Cell::new(0));
+        let s = counted(10, &drops).filter_into(|(i, _)| i % 3 == 0);
+        assert_eq!(6, drops.get());
+        assert_eq!(
+            vec![0, 3, 6, 9],
+            s.iter().map(|(i, _)| *i).collect::<Vec<_>>()
+        );
+        drop(s);
+        assert_eq!(10, drops.get());
+
+        let s = counted(10, &drops).filter_into(|_| false);
+        assert!(s.is_sentinel());
+        assert_eq!(20, drops.get());
+
+        let s = counted(10, &drops);
+        let ptr = s.as_ptr();
+        let s = s.filter_into(|_| true);
+        assert_eq!(ptr, s.as_ptr());
+        assert_eq!(20, drops.get());
+
+        // Needs an extra byte for the length before, but not after filtering.
+        let s = OwnedSlice::<u8>::from_fn(200, |i| i as u8).unwrap();
+        let s = s.filter_into(|i| i % 10 == 0);
+        assert_eq!(&[0, 10, 20, 30, 40, 50, 60, 70, 80, 90], &s[..10]);
+        assert_eq!(20, s.len());
+        assert_eq!(21, s.heap_size());
+
+        assert!(OwnedSlice::<String>::default()
+            .filter_into(|_| true)
+            .is_sentinel());
+    }
+
+    /// Panicking predicate drops everything exactly once (checked by miri).
+    #[test]
+    fn filter_into_panic() {
+        for panic_at in 0..6 {
+            let drops = Rc::new(Cell::new(0));
+            let s = counted(6, &drops);
+            panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                s.filter_into(|(i, _)| {
+                    assert_ne!(*i, panic_at, "Panicking for the good measure of it");
+                    i % 2 == 0
+                })
+            }))
+            .unwrap_err();
+            assert_eq!(6, drops.get());
+        }
+    }
+
+    #[test]
+    fn chunks_collect() {
+        let s = OwnedSlice::<u32>::from_fn(10, |i| i as u32).unwrap();
+        let chunks = s.chunks_collect(3);
+        assert_eq!(4, chunks.len());
+        for (chunk, orig) in chunks.iter().zip(s.chunks(3)) {
+            assert_eq!(orig, chunk.deref());
+        }
+        assert_eq!([9], chunks[3][..]);
+        let chunks = s.chunks_collect(5);
+        assert_eq!(2, chunks.len());
+        assert_eq!([0, 1, 2, 3, 4], chunks[0][..]);
+        assert_eq!([5, 6, 7, 8, 9], chunks[1][..]);
+        assert!(OwnedSlice::<u32>::default().chunks_collect(3).is_empty());
+
+        let shared = OwnedSlice::<String, RcHeader>::new(&strings()).unwrap();
+        let chunks = shared.chunks_collect(10);
+        assert_eq!(1, chunks.len());
+        assert_eq!(shared.as_ptr(), chunks[0].as_ptr());
+    }
+
+    #[test]
+    #[should_panic(expected = "Chunk size must be non-zero")]
+    fn chunks_collect_zero() {
+        OwnedSlice::<u32>::default().chunks_collect(0);
+    }
+
+    #[test]
+    fn partition() {
+        let s: OwnedSlice<u8> = OwnedSlice::new(&[1, 2, 3, 4, 5]).unwrap();
+        let mut calls = 0;
+        let (even, odd) = s.partition(|v| {
+            calls += 1;
+            v % 2 == 0
+        });
+        assert_eq!([2, 4], even[..]);
+        assert_eq!([1, 3, 5], odd[..]);
+        assert_eq!(10, calls);
+
+        let s = strings();
+        let (all, none) = s.partition(|_| true);
+        assert_eq!(s, all);
+        assert!(none.is_sentinel());
+        let (none, all) = s.partition(|_| false);
+        assert!(none.is_sentinel());
+        assert_eq!(s, all);
+
+        let (empty, empty2) = OwnedSlice::<String>::new(&[]).unwrap().partition(|_| true);
+        assert!(empty.is_sentinel());
+        assert!(empty2.is_sentinel());
+
+        let long = OwnedSlice::<u32, ArcHeader>::from_fn(200, |i| i as u32).unwrap();
+        let (small, big) = long.partition(|&i| i < 130);
+        assert_eq!((0..130).collect::<Vec<_>>(), small.deref());
+        assert_eq!((130..200).collect::<Vec<_>>(), big.deref());
+    }
+
+    fn counted(n: usize, drops: &Rc<Cell<usize>>) -> OwnedSlice<(usize, DropCounter)> {
+        OwnedSlice::from_fn(n, |i| (i, DropCounter(Rc::clone(drops)))).unwrap()
+    }
+
+    #[test]
+    fn to_vec() {
+        let s = strings();
+        let v = s.to_vec();
+        assert_eq!(s.len(), v.len());
+        assert_eq!(s, v[..]);
+        assert!(OwnedSlice::<String>::default().to_vec().is_empty());
+    }
+
+    #[test]
+    fn arrays() {
+        let s = OwnedSlice::<String>::from(["a".to_owned(), "b".to_owned()]);
+        assert_eq!(
+            Some(&["a".to_owned(), "b".to_owned()]),
+            s.try_as_array::<2>()
+        );
+        assert!(s.try_as_array::<3>().is_none());
+        let array: [String; 2] = s.try_into().unwrap();
+        assert_eq!(["a", "b"], array);
+
+        // Mismatched length gives the slice back, without dropping anything
+        let drops = Rc::new(Cell::new(0));
+        let s = counted(3, &drops);
+        let s = <[_; 2]>::try_from(s).unwrap_err();
+        let s = <[_; 4]>::try_from(s).unwrap_err();
+        assert_eq!(0, drops.get());
+        let array: [_; 3] = s.try_into().unwrap();
+        assert_eq!(0, drops.get());
+        assert_eq!([0, 1, 2], array.each_ref().map(|(i, _)| *i));
+        drop(array);
+        assert_eq!(3, drops.get());
+
+        // Shared can't be moved out of
+        let shared = OwnedSlice::<u8, ArcHeader>::from([1, 2]);
+        let shared2 = shared.clone();
+        let shared = <[u8; 2]>::try_from(shared).unwrap_err();
+        drop(shared2);
+        assert_eq!([1, 2], <[u8; 2]>::try_from(shared).unwrap());
+
+        let empty = OwnedSlice::<String>::from([]);
+        assert!(empty.is_sentinel());
+        assert_eq!(Some(&[]), empty.try_as_array::<0>());
+        let array: [String; 0] = empty.try_into().unwrap();
+        assert!(array.is_empty());
+        <[u8; 0]>::try_from(OwnedSlice::<u8>::from([1])).unwrap_err();
+    }
+
+    #[test]
+    #[should_panic(expected = "Array too long for the header")]
+    fn array_too_long() {
+        let _ = OwnedSlice::<u8, ShortMockHeader>::from([0; 5]);
+    }
+
+    #[test]
+    fn into_boxed_slice() {
+        let v: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        let s = OwnedSlice::<String>::new(&v).unwrap();
+        let boxed = s.into_boxed_slice();
+        assert_eq!(v, boxed.into_vec());
+
+        let drops = Rc::new(Cell::new(0));
+        let boxed = counted(3, &drops).into_boxed_slice();
+        assert_eq!(0, drops.get());
+        assert_eq!(
+            [0, 1, 2],
+            *boxed.iter().map(|(i, _)| *i).collect::<Vec<_>>()
+        );
+        drop(boxed);
+        assert_eq!(3, drops.get());
+
+        assert!(OwnedSlice::<String>::default()
+            .into_boxed_slice()
+            .is_empty());
+    }
+
+    /// An iterator reporting a different length than it has.
+    struct Liar {
+        real: usize,
+        claimed: usize,
+        drops: Rc<Cell<usize>>,
+    }
+
+    impl Iterator for Liar {
+        type Item = DropCounter;
+
+        fn next(&mut self) -> Option<DropCounter> {
+            if self.real == 0 {
+                return None;
+            }
+            self.real -= 1;
+            self.claimed = self.claimed.saturating_sub(1);
+            Some(DropCounter(Rc::clone(&self.drops)))
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.claimed, Some(self.claimed))
+        }
+    }
+
+    impl ExactSizeIterator for Liar {}
+
+    #[test]
+    fn from_iter_exact() {
+        let s =
+            OwnedSlice::<String, ArcHeader>::from_iter_exact(strings().iter().cloned()).unwrap();
+        assert_eq!(strings(), s);
+        let s = OwnedSlice::<u32>::from_iter_exact(0..300).unwrap();
+        assert_eq!(s, (0..300).collect::<Vec<_>>()[..]);
+        assert!(OwnedSlice::<u32>::from_iter_exact(0..0)
+            .unwrap()
+            .is_sentinel());
+        OwnedSlice::<u8, ShortMockHeader>::from_iter_exact(0..5).unwrap_err();
+
+        // Fewer than claimed shrinks the slice, even to fewer extra bytes.
+        let drops = Rc::new(Cell::new(0));
+        for (real, claimed) in [(0, 5), (3, 5), (10, 100), (10, 1000)] {
+            let liar = Liar {
+                real,
+                claimed,
+                drops: Rc::clone(&drops),
+            };
+            let s = OwnedSlice::<DropCounter>::from_iter_exact(liar).unwrap();
+            assert_eq!(real, s.len());
+            assert_eq!(real == 0, s.is_sentinel());
+            drop(s);
+        }
+        assert_eq!(23, drops.get());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn from_iter_exact_longer() {
+        let drops = Rc::new(Cell::new(0));
+        let liar = Liar {
+            real: 5,
+            claimed: 3,
+            drops: Rc::clone(&drops),
+        };
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            OwnedSlice::<DropCounter>::from_iter_exact(liar).unwrap();
+        }))
+        .unwrap_err();
+        // The 3 taken and the one that made the check fire, the last one is never created.
+        assert_eq!(4, drops.get());
+    }
+
+    /// Same layout of the elements reuses the allocation.
+    #[test]
+    fn map_in_place() {
+        let s = OwnedSlice::<u32>::from_fn(100, |i| i as u32).unwrap();
+        let ptr = s.as_ptr() as usize;
+        let s = s.map(|i| -(i as i32));
+        assert_eq!(ptr, s.as_ptr() as usize);
+        assert_eq!((0..100).map(|i| -i).collect::<Vec<_>>(), s.deref());
+
+        let s = strings();
+        let ptr = s.as_ptr() as usize;
+        let s = s.map(|s| s.into_bytes());
+        assert_eq!(ptr, s.as_ptr() as usize);
+        assert_eq!(b"Hello", s[0].as_slice());
+
+        // Different size needs a new allocation
+        let s = OwnedSlice::<u8>::new(&[1, 2, 3]).unwrap().map(u64::from);
+        assert_eq!([1, 2, 3], s[..]);
+    }
+
+    /// Uniquely owned shared slices are mapped in place, the ones with other owners are copied.
+    #[test]
+    fn map_shared() {
+        let s = OwnedSlice::<String, RcHeader>::new(&strings()).unwrap();
+        let ptr = s.as_ptr() as usize;
+        let s = s.map(|s| s.into_bytes());
+        assert_eq!(ptr, s.as_ptr() as usize);
+        assert_eq!(b"Hello", s[0].as_slice());
+
+        let s = OwnedSlice::<String, RcHeader>::new(&strings()).unwrap();
+        let s2 = s.clone();
+        let lens = s.map(|s| s.len());
+        assert_eq!([5, 0, 6], lens[..]);
+        assert_eq!(strings(), s2);
+
+        let s = OwnedSlice::<u8, RcHeader>::new(&[1, 2, 3]).unwrap();
+        let _s2 = s.clone();
+        let err = s.try_map(|v| if v == 2 { Err(v) } else { Ok(u64::from(v)) });
+        assert_eq!(2, err.unwrap_err());
+    }
+
+    /// Each element is dropped exactly once when the in-place map panics or fails.
+    #[test]
+    fn map_in_place_abort() {
+        let drops = Rc::new(Cell::new(0));
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            counted(10, &drops).map(|(i, d)| {
+                assert_ne!(4, i, "Panic in the middle");
+                (i * 2, d)
+            })
+        }))
+        .unwrap_err();
+        assert_eq!(10, drops.get());
+
+        let drops = Rc::new(Cell::new(0));
+        let err = counted(10, &drops).try_map(|(i, d)| if i == 7 { Err(i) } else { Ok((i, d)) });
+        assert_eq!(7, err.unwrap_err());
+        assert_eq!(10, drops.get());
+    }
+
+    #[test]
+    fn map_ref() {
+        let s = OwnedSlice::<String, RcHeader>::new(&strings()).unwrap();
+        let lens: OwnedSlice<usize, RcHeader> = s.map_ref(String::len);
+        assert_eq!([5, 0, 6], lens[..]);
+        assert_eq!(strings(), s);
+        assert!(OwnedSlice::<String>::default()
+            .map_ref(String::len)
+            .is_sentinel());
+
+        let drops = Rc::new(Cell::new(0));
+        let src = counted(5, &drops);
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            src.map_ref(|(i, d)| {
+                assert_ne!(3, *i, "Panic in the middle");
+                DropCounter(Rc::clone(&d.0))
+            })
+        }))
+        .unwrap_err();
+        assert_eq!(3, drops.get());
+        drop(src);
+        assert_eq!(8, drops.get());
+    }
+
+    /// Inconsistent answers panic in the second pass, without leaking anything.
+    #[test]
+    fn partition_inconsistent() {
+        let drops = Rc::new(Cell::new(0));
+        let s = OwnedSlice::<DropCounter>::from_fn(3, |_| DropCounter(Rc::clone(&drops))).unwrap();
+        // All counted as matching, then the first one already goes to the other side
+        let mut calls = 0;
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            s.partition(|_| {
+                calls += 1;
+                calls <= 3
+            })
+        }))
+        .unwrap_err();
+        assert_eq!(1, drops.get());
+        // One counted as matching, then all of them match in the second pass
+        let mut calls = 0;
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            s.partition(|_| {
+                calls += 1;
+                calls == 1 || calls > 3
+            })
+        }))
+        .unwrap_err();
+        assert_eq!(3, drops.get());
+        drop(s);
+        assert_eq!(6, drops.get());
+    }
+
+    /// A predicate changing its mind between the passes is caught and nothing leaks.
+    #[test]
+    fn filtered_inconsistent() {
+        let drops = Rc::new(Cell::new(0));
+        let s = OwnedSlice::<DropCounter>::from_fn(3, |_| DropCounter(Rc::clone(&drops))).unwrap();
+        // Fewer matches in the second pass (3 counted, 1 cloned)
+        let mut calls = 0;
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            s.filtered(|_| {
+                calls += 1;
+                calls <= 4
+            })
+        }))
+        .unwrap_err();
+        assert_eq!(1, drops.get());
+        // More matches in the second pass (1 counted, 2 cloned)
+        let mut calls = 0;
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            s.filtered(|_| {
+                calls += 1;
+                calls > 2
+            })
+        }))
+        .unwrap_err();
+        assert_eq!(3, drops.get());
+        drop(s);
+        assert_eq!(6, drops.get());
+    }
+
+    #[test]
+    fn from_fn() {
+        let s = OwnedSlice::<String>::from_fn(3, |i| i.to_string()).unwrap();
+        assert_eq!(&["0", "1", "2"], s.deref());
+
+        let empty = OwnedSlice::<String>::from_fn(0, |_| unreachable!()).unwrap();
+        assert!(empty.is_sentinel());
+    }
+
+    /// Panicking in the middle drops exactly the already created elements.
+    #[test]
+    fn from_fn_panic() {
+        let drops = Rc::new(Cell::new(0));
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            OwnedSlice::<DropCounter>::from_fn(5, |i| {
+                assert!(i < 3, "Panicking for the good measure of it");
+                DropCounter(Rc::clone(&drops))
+            })
+        }))
+        .unwrap_err();
+        assert_eq!(3, drops.get());
+    }
+
+    #[test]
+    fn try_from_fn() {
+        #[derive(Debug, PartialEq)]
+        enum Error {
+            TooLong,
+            Fail(usize),
+        }
+
+        impl From<TooLong> for Error {
+            fn from(_: TooLong) -> Self {
+                Error::TooLong
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let result = OwnedSlice::<DropCounter>::try_from_fn(5, |i| {
+            if i == 2 {
+                Err(Error::Fail(i))
+            } else {
+                Ok(DropCounter(Rc::clone(&drops)))
+            }
+        });
+        assert_eq!(Error::Fail(2), result.unwrap_err());
+        assert_eq!(2, drops.get());
+
+        let s = OwnedSlice::<usize>::try_from_fn(3, Ok::<_, Error>).unwrap();
+        assert_eq!(&[0, 1, 2], s.deref());
+
+        let err = OwnedSlice::<u8>::try_from_fn(usize::MAX, |_| unreachable!()).unwrap_err();
+        assert_eq!(Error::TooLong, err);
+    }
+
+    #[test]
+    fn filled() {
+        let s = OwnedSlice::<u8>::filled(1024, 0xFF).unwrap();
+        assert_eq!(1024, s.len());
+        assert!(s.iter().all(|&b| b == 0xFF));
+
+        let s = OwnedSlice::<String>::filled(3, "Hello".to_owned()).unwrap();
+        assert_eq!(&["Hello", "Hello", "Hello"], s.deref());
+
+        let s = OwnedSlice::<String>::filled(0, "Hello".to_owned()).unwrap();
+        assert!(s.is_sentinel());
+
+        let s = OwnedSlice::<u16>::splat(300, 42).unwrap();
+        assert_eq!(&[42; 300][..], s.deref());
+
+        let s = OwnedSlice::<u16>::splat(0, 42).unwrap();
+        assert!(s.is_sentinel());
+    }
+
+    /// Cleans up after a panicking clone (checked by miri).
+    #[test]
+    fn filled_panic() {
+        struct PanicSecond(Rc<Cell<usize>>, String);
+
+        impl Clone for PanicSecond {
+            fn clone(&self) -> Self {
+                let cnt = self.0.get() + 1;
+                self.0.set(cnt);
+                assert!(cnt < 2, "Panicking for the good measure of it");
+                Self(Rc::clone(&self.0), self.1.clone())
+            }
+        }
+
+        let value = PanicSecond(Rc::new(Cell::new(0)), "Hello".to_owned());
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _ = OwnedSlice::<PanicSecond>::filled(5, value);
+        }))
+        .unwrap_err();
+    }
+
+    /// Conversions between headers keep the content, whichever way they happen.
+    #[test]
+    fn convert() {
+        let input = ["Hello".to_owned(), "World".to_owned()];
+
+        // Same layout, in place
+        let s = OwnedSlice::<String>::new(&input).unwrap();
+        let ptr = s.as_ptr();
+        let s: OwnedSlice<String, BoxHeader> = s.convert().unwrap();
+        assert_eq!(ptr, s.as_ptr());
+        assert_eq!(&input, s.deref());
+
+        // Different layout, moved
+        let s: OwnedSlice<String, RcMockHeader> = s.convert().unwrap();
+        assert_eq!(&input, s.deref());
+
+        // Shared, cloned
+        let s2 = s.clone();
+        let s3: OwnedSlice<String, BoxHeader> = s.convert().unwrap();
+        assert_eq!(&input, s2.deref());
+        assert_eq!(&input, s3.deref());
+
+        let empty = OwnedSlice::<String>::default();
+        let empty: OwnedSlice<String, RcMockHeader> = empty.convert().unwrap();
+        assert!(empty.is_sentinel());
+
+        // Unique slices are always moved, the elements don't need to be Clone.
+        let s = OwnedSlice::<NoClone>::from_fn(2, |i| NoClone(i as u32)).unwrap();
+        let s: OwnedSlice<NoClone, SaturatedMockHeader> = s.convert().unwrap();
+        let s: OwnedSlice<NoClone, RcMockHeader> = s.convert().unwrap();
+        assert_eq!(&[NoClone(0), NoClone(1)], s.deref());
+    }
+
+    /// The debug output, including the pretty printing, is the same as for the slice.
+    #[test]
+    fn debug() {
+        let s = OwnedSlice::<u8>::new(&[1, 2]).unwrap();
+        assert_eq!("[1, 2]", format!("{:?}", s));
+        assert_eq!("[\n    1,\n    2,\n]", format!("{:#?}", s));
+        assert_eq!(format!("{:#?}", &[1u8, 2]), format!("{:#?}", s));
+        assert_eq!("[01, 02]", format!("{:02?}", s));
+    }
+
+    #[test]
+    fn display() {
+        let s = OwnedSlice::<u32>::new(&[1, 20, 300]).unwrap();
+        assert_eq!("[1, 20, 300]", format!("{}", s));
+        assert_eq!("[001, 020, 300]", format!("{:03}", s));
+        assert_eq!("[]", format!("{}", OwnedSlice::<u32>::default()));
+        assert_eq!("[Hello, , World!]", strings().to_string());
+        let s = OwnedSlice::<f32>::new(&[1.0, 0.5]).unwrap();
+        assert_eq!("[1.00, 0.50]", format!("{:.2}", s));
+    }
+
+    #[test]
+    fn hex() {
+        let s = OwnedSlice::<u8>::new(&[0xab, 0x01, 0xff]).unwrap();
+        assert_eq!("ab01ff", format!("{:x}", s));
+        assert_eq!("AB01FF", format!("{:X}", s));
+        assert_eq!("0xab01ff", format!("{:#x}", s));
+        assert_eq!("ab01ff", format!("{:4x}", s));
+        assert_eq!("    ab01ff", format!("{:10x}", s));
+        assert_eq!("ab01ff____", format!("{:_<10x}", s));
+        assert_eq!("__ab01ff__", format!("{:_^10x}", s));
+        assert_eq!("0x00ab01ff", format!("{:#010x}", s));
+
+        let empty = OwnedSlice::<u8>::default();
+        assert_eq!("", format!("{:x}", empty));
+        assert_eq!("0x", format!("{:#X}", empty));
+        assert_eq!("***", format!("{:*>3x}", empty));
+
+        let long = OwnedSlice::<u8>::from_fn(300, |i| i as u8).unwrap();
+        let expected = long
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        assert_eq!(expected, format!("{:x}", long));
+    }
+
+    /// When the header refuses to increment the reference count, a full copy is made.
+    #[test]
+    fn clone_saturated() {
+        let s = OwnedSlice::<String, SaturatedMockHeader>::new(&["Hello".to_owned()]).unwrap();
+        let s2 = s.try_clone().unwrap();
+        assert_ne!(s.as_ptr(), s2.as_ptr());
+        assert_eq!(s.deref(), s2.deref());
+        let s3 = s2.clone();
+        assert_ne!(s2.as_ptr(), s3.as_ptr());
+        assert_eq!(s.deref(), s3.deref());
+
+        let s = OwnedSlice::<String, RcMockHeader>::new(&["Hello".to_owned()]).unwrap();
+        let s2 = s.try_clone().unwrap();
+        assert_eq!(s.as_ptr(), s2.as_ptr());
+    }
+
+    /// Clones until the 3-bit counter of the mock header is full.
+    fn saturate<T, const P: u8, O>(
+        s: &OwnedSlice<T, TinyRcMockHeader<P, O>>,
+    ) -> Vec<OwnedSlice<T, TinyRcMockHeader<P, O>>>
+    where
+        O: CloneStrategy<T>,
+    {
+        let clones = (0..6).map(|_| s.clone()).collect::<Vec<_>>();
+        assert_eq!(Some(7), OwnedSlice::strong_count(s));
+        clones
+    }
+
+    #[test]
+    fn overflow_deep_clone() {
+        let s = OwnedSlice::<String, TinyRcMockHeader<0>>::new(&["Hello".to_owned()]).unwrap();
+        assert_eq!(Some(1), OwnedSlice::strong_count(&s));
+        let _clones = saturate(&s);
+
+        let copy = s.try_clone().unwrap();
+        assert_ne!(s.as_ptr(), copy.as_ptr());
+        assert_eq!(s, copy);
+        assert_eq!(Some(7), OwnedSlice::strong_count(&s));
+        assert_eq!(Some(1), OwnedSlice::strong_count(&copy));
+
+        // Clone of shared slices can't copy, the elements don't have to be Clone.
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| s.clone()));
+        assert!(result.is_err());
+        assert_eq!(Some(7), OwnedSlice::strong_count(&s));
+    }
+
+    #[test]
+    fn overflow_deep_clone_through_clone() {
+        type H = TinyRcMockHeader<0, CopyOnOverflow>;
+        let s = OwnedSlice::<String, H>::new(&["Hello".to_owned()]).unwrap();
+        let _clones = saturate(&s);
+
+        let copy = s.clone();
+        assert_ne!(s.as_ptr(), copy.as_ptr());
+        assert_eq!(s, copy);
+        assert_eq!(Some(7), OwnedSlice::strong_count(&s));
+        assert_eq!(Some(1), OwnedSlice::strong_count(&copy));
+
+        // The copy shares again, until it overflows too.
+        let copy2 = copy.clone();
+        assert_eq!(copy.as_ptr(), copy2.as_ptr());
+        assert_eq!(Some(2), OwnedSlice::strong_count(&copy));
+    }
+
+    #[test]
+    fn overflow_panic() {
+        let drops = Rc::new(Cell::new(0));
+        let s = OwnedSlice::<_, TinyRcMockHeader<1>>::from_fn(3, |i| {
+            (i, DropCounter(Rc::clone(&drops)))
+        })
+        .unwrap();
+        let clones = saturate(&s);
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| s.clone()));
+        assert!(result.is_err());
+        let s2 = OwnedSlice::<u8, TinyRcMockHeader<1>>::new(b"Hello").unwrap();
+        let _clones2 = saturate(&s2);
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| s2.try_clone()));
+        assert!(result.is_err());
+        assert_eq!(Some(7), OwnedSlice::strong_count(&s2));
+
+        // Nothing got leaked by the failed clone.
+        assert_eq!(Some(7), OwnedSlice::strong_count(&s));
+        drop(clones);
+        assert_eq!(Some(1), OwnedSlice::strong_count(&s));
+        drop(s);
+        assert_eq!(3, drops.get());
+    }
+
+    /// Runs the aborting part in a child process (this same test binary), as it kills the process.
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn overflow_abort() {
+        const VAR: &str = "SQUASH_OVERFLOW_ABORT_CHILD";
+        if std::env::var_os(VAR).is_some() {
+            let s = OwnedSlice::<u8, TinyRcMockHeader<2>>::new(b"Hello").unwrap();
+            let _clones = saturate(&s);
+            let _ = s.clone();
+            unreachable!("Should have aborted");
+        }
+
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "slice::tests::overflow_abort", "--nocapture"])
+            .env(VAR, "1")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            // SIGABRT
+            assert_eq!(Some(6), output.status.signal());
+        }
+    }
+
+    /// Checks the data is aligned for all the lengths and headers, including extended alignments,
+    /// and that the content survives.
+    fn check_aligned<T: Clone + Debug + PartialEq>(make: fn(usize) -> T) {
+        fn check<T: Clone + Debug + PartialEq, H: Header>(make: fn(usize) -> T) {
+            for len in [0, 1, 2, 3, 63, 64, 65, 1000] {
+                let expected = (0..len).map(make).collect::<Vec<_>>();
+                let s = OwnedSlice::<T, H>::from_fn(len, make).unwrap();
+                assert!(s.as_ptr().is_aligned());
+                assert_eq!(0, s.as_ptr() as usize % mem::align_of::<T>());
+                assert_eq!(len, s.len());
+                assert_eq!(expected, s.deref());
+                assert_eq!(expected, s.try_clone().unwrap().deref());
+            }
+        }
+        check::<T, BoxHeader>(make);
+        check::<T, RcHeader>(make);
+        check::<T, ArcHeader>(make);
+        check::<T, SaturatedMockHeader>(make);
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    #[repr(align(16))]
+    struct Align16(u64);
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    #[repr(align(32))]
+    struct Align32([f32; 8]);
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    #[repr(align(64))]
+    struct Align64(u8);
+
+    #[test]
+    fn aligned() {
+        check_aligned(|i| i as u8);
+        check_aligned(|i| i as u128);
+        check_aligned(|i| [i as f32; 4]);
+        check_aligned(|i| [i as f32; 8]);
+        check_aligned(|i| Align16(i as u64));
+        check_aligned(|i| Align32([i as f32; 8]));
+        check_aligned(|i| Align64(i as u8));
+        assert_eq!(16, mem::align_of::<Align16>());
+        assert_eq!(32, mem::align_of::<Align32>());
+        assert_eq!(64, mem::align_of::<Align64>());
+    }
+
+    /// Deliberately not `Clone`.
+    #[derive(Debug, PartialEq)]
+    struct NoClone(u32);
+
+    /// The derive compiles, because shared slices don't need their elements to be `Clone`.
+    #[derive(Clone)]
+    struct Holder {
+        arc: OwnedSlice<NoClone, ArcHeader>,
+        rc: OwnedSlice<NoClone, RcHeader>,
+    }
+
+    #[test]
+    fn clone_shared_without_clone() {
+        let holder = Holder {
+            arc: OwnedSlice::from_fn(3, |i| NoClone(i as u32)).unwrap(),
+            rc: OwnedSlice::default(),
+        };
+        let holder2 = holder.clone();
+        assert_eq!(holder.arc.as_ptr(), holder2.arc.as_ptr());
+        drop(holder);
+        assert_eq!(&[NoClone(0), NoClone(1), NoClone(2)], &holder2.arc[..]);
+        assert!(holder2.rc.is_empty());
+    }
+
+    #[test]
+    fn copy_from_slice() {
+        let s = OwnedSlice::<u64>::copy_from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(&[1, 2, 3], s.deref());
+        let empty = OwnedSlice::<u64>::copy_from_slice(&[]).unwrap();
+        assert!(empty.is_sentinel());
+        OwnedSlice::<u64, ShortMockHeader>::copy_from_slice(&[0; 5]).unwrap_err();
+    }
+
+    #[test]
+    fn copy_clone() {
+        let s = OwnedSlice::<u64>::new(&[1, 2, 3]).unwrap();
+        let s2 = s.try_copy_clone().unwrap();
+        assert_ne!(s.as_ptr(), s2.as_ptr());
+        assert_eq!(s, s2);
+
+        let s = OwnedSlice::<u64, SaturatedMockHeader>::new(&[1, 2, 3]).unwrap();
+        let s2 = s.try_copy_clone().unwrap();
+        assert_ne!(s.as_ptr(), s2.as_ptr());
+        assert_eq!(s, s2);
+
+        let s = OwnedSlice::<u64, RcMockHeader>::new(&[1, 2, 3]).unwrap();
+        let s2 = s.try_copy_clone().unwrap();
+        assert!(OwnedSlice::ptr_eq(&s, &s2));
+
+        let empty = OwnedSlice::<u64>::default();
+        assert!(empty.try_copy_clone().unwrap().is_sentinel());
+    }
+
+    /// The single copy produces the same as cloning, across the lengths changing the header size.
+    #[test]
+    fn copy_from_slice_lengths() {
+        fn check<H: Header>() {
+            let data = (0..20_000u32).collect::<Vec<_>>();
+            let bytes = data.iter().map(|&i| i as u8).collect::<Vec<_>>();
+            let lens = (0..=300).chain([16_383, 16_384, 16_385, 20_000]);
+            for len in lens {
+                let s = OwnedSlice::<u32, H>::copy_from_slice(&data[..len]).unwrap();
+                assert_eq!(OwnedSlice::<u32, H>::new(&data[..len]).unwrap(), s);
+                let s = OwnedSlice::<u8, H>::copy_from_slice(&bytes[..len]).unwrap();
+                assert_eq!(&bytes[..len], s.deref());
+            }
+        }
+        if cfg!(miri) {
+            return;
+        }
+        check::<BoxHeader>();
+        check::<RcHeader>();
+        check::<ArcHeader>();
+    }
 
-    use super::*;
+    /// Upgrading works only while there are strong references.
+    #[test]
+    fn weak() {
+        let s = OwnedSlice::<String, ArcHeader>::new(&["Hello".to_owned()]).unwrap();
+        let weak = OwnedSlice::downgrade(&s);
+        let weak2 = weak.clone();
+        let s2 = weak.upgrade().unwrap();
+        assert_eq!(s.as_ptr(), s2.as_ptr());
+        drop(s);
+        assert_eq!(&["Hello"], weak2.upgrade().unwrap().deref());
+        drop(s2);
+        assert!(weak.upgrade().is_none());
+        assert!(weak2.upgrade().is_none());
+        assert_eq!("(Weak)", format!("{:?}", weak));
+    }
 
-    /// Check we have the null-pointer optimisation.
+    /// The data are dropped exactly once, when the last strong reference goes away. The memory
+    /// is freed when the last weak one goes away (checked by miri).
     #[test]
-    fn null_ptr_opt() {
-        assert_eq!(
-            mem::size_of::<OwnedSlice<String>>(),
-            mem::size_of::<Option<OwnedSlice<String>>>(),
-        );
+    fn weak_drops() {
+        let drops = Rc::new(Cell::new(0));
+        let s = OwnedSlice::<DropCounter, RcHeader>::from_fn(3, |_| DropCounter(Rc::clone(&drops)))
+            .unwrap();
+        let weak = OwnedSlice::downgrade(&s);
+        let s2 = weak.upgrade().unwrap();
+        drop(s);
+        assert_eq!(0, drops.get());
+        drop(s2);
+        assert_eq!(3, drops.get());
+        let weak2 = weak.clone();
+        drop(weak);
+        assert!(weak2.upgrade().is_none());
+        drop(weak2);
+        assert_eq!(3, drops.get());
+
+        // The other order ‒ the weak one goes away first.
+        let s = OwnedSlice::<DropCounter, RcHeader>::from_fn(2, |_| DropCounter(Rc::clone(&drops)))
+            .unwrap();
+        drop(OwnedSlice::downgrade(&s));
+        drop(s);
+        assert_eq!(5, drops.get());
     }
 
-    /// Exercise the special handling of the sentinel.
+    /// Outstanding weak references prevent mutable access.
     #[test]
-    fn empty() {
-        let mut s = OwnedSlice::<String>::new(&[]).unwrap();
-        assert_eq!(s.deref(), &[] as &[String]);
-        assert_eq!(s.deref_mut(), &mut [] as &mut [String]);
-        let s2 = s.clone();
-        assert_eq!(&s as &[_], &s2 as &[_]);
-        assert_eq!("[]", format!("{:?}", s));
+    fn weak_unique() {
+        let mut s = OwnedSlice::<u8, ArcHeader>::new(&[1, 2, 3]).unwrap();
+        let weak = OwnedSlice::downgrade(&s);
+        assert!(OwnedSlice::get_mut(&mut s).is_none());
+        drop(weak);
+        assert!(OwnedSlice::get_mut(&mut s).is_some());
+    }
 
-        let s3 = OwnedSlice::<String>::default();
-        assert_eq!(&s as &[_], &s3 as &[_]);
+    #[test]
+    fn weak_empty() {
+        let s = OwnedSlice::<String, ArcHeader>::default();
+        let weak = OwnedSlice::downgrade(&s);
+        drop(s);
+        assert!(weak.upgrade().unwrap().is_empty());
     }
 
-    /// Test with few strings.
-    ///
-    /// Use strings so miri can check we run destructors alright.
     #[test]
-    fn full() {
-        let mut s = OwnedSlice::<String>::new(&["Hello".to_owned(), "World".to_owned()]).unwrap();
-        assert_eq!(2, s.len());
-        assert_eq!(s[1], "World");
-        s[0] = "Round".to_owned();
-        assert_eq!(s[0], "Round");
+    #[should_panic(expected = "doesn't support weak references")]
+    fn weak_unsupported() {
+        let s = OwnedSlice::<u8>::new(&[1]).unwrap();
+        OwnedSlice::downgrade(&s);
+    }
+
+    /// Share the slice between threads, with some weak references thrown into the mix.
+    #[test]
+    fn arc_threads() {
+        let s = OwnedSlice::<String, ArcHeader>::from_fn(10, |i| i.to_string()).unwrap();
+        let threads = (0..4)
+            .map(|_| {
+                let s = s.clone();
+                let weak = OwnedSlice::downgrade(&s);
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        let s2 = weak.upgrade().unwrap();
+                        assert_eq!(s.deref(), s2.deref());
+                        assert_eq!("9", s2[9]);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for t in threads {
+            t.join().unwrap();
+        }
+        let mut s = s;
+        assert!(OwnedSlice::get_mut(&mut s).is_some());
+    }
+
+    #[test]
+    fn repeat() {
+        let s = OwnedSlice::<String>::repeat(&["a".to_owned(), "b".to_owned()], 3).unwrap();
+        assert_eq!(&["a", "b", "a", "b", "a", "b"], s.deref());
+
+        // Around the boundary of the inline length in the header
+        for n in 31..=33 {
+            let s = OwnedSlice::<u8>::repeat(&[1, 2], n).unwrap();
+            assert_eq!([1, 2].repeat(n), s.deref());
+        }
+
+        assert!(OwnedSlice::<u8>::repeat(&[1, 2], 0).unwrap().is_sentinel());
+        assert!(OwnedSlice::<u8>::repeat(&[], 10).unwrap().is_sentinel());
+        assert!(OwnedSlice::<u8>::repeat(&[1, 2], usize::MAX).is_err());
+        assert!(OwnedSlice::<u8>::repeat(&[1], usize::MAX).is_err());
+    }
+
+    /// Panicking in the middle of repeating cleans up (checked by miri).
+    #[test]
+    fn repeat_panic() {
+        let drops = Rc::new(Cell::new(0));
+        let src = [CloneCounter {
+            clones: Rc::new(Cell::new(0)),
+            drops: Rc::clone(&drops),
+            panic_at: 3,
+        }];
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _ = OwnedSlice::<CloneCounter>::repeat(&src, 5);
+        }))
+        .unwrap_err();
+        // The 3 successful clones got dropped
+        assert_eq!(3, drops.get());
+    }
+
+    /// Panics on the given clone. Counts the drops of the clones (not the original).
+    struct CloneCounter {
+        clones: Rc<Cell<usize>>,
+        drops: Rc<Cell<usize>>,
+        panic_at: usize,
+    }
+
+    impl Clone for CloneCounter {
+        fn clone(&self) -> Self {
+            let cnt = self.clones.get();
+            assert!(cnt != self.panic_at, "Panicking for the good measure of it");
+            self.clones.set(cnt + 1);
+            Self {
+                clones: Rc::clone(&self.clones),
+                drops: Rc::clone(&self.drops),
+                panic_at: self.panic_at,
+            }
+        }
+    }
+
+    impl Drop for CloneCounter {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    #[test]
+    fn as_ptr() {
+        let s = OwnedSlice::<u64>::new(&[]).unwrap();
+        assert!(!s.as_ptr().is_null());
+        assert_eq!(0, s.as_ptr() as usize % mem::align_of::<u64>());
+
+        let mut s = OwnedSlice::<u64>::new(&[1, 2, 3]).unwrap();
+        assert_eq!(1, unsafe { *s.as_ptr() });
+        assert_eq!(s.deref().as_ptr(), s.as_ptr());
+        unsafe { *s.as_mut_ptr().add(2) = 42 };
+        assert_eq!(&[1, 2, 42], s.deref());
+
+        let s = OwnedSlice::<u8, RcHeader>::new(&[1, 2, 3]).unwrap();
         let s2 = s.clone();
-        assert_eq!(s.deref(), s2.deref());
-        assert_eq!(2, s2.len());
-        assert_eq!("[\"Round\", \"World\"]", format!("{:?}", s2));
+        assert_eq!(s.as_ptr(), s2.as_ptr());
     }
 
     #[test]
-    fn long() {
-        let long = vec![0u8; 300];
-        let s = OwnedSlice::<_>::new(&long).unwrap();
-        assert_eq!(long.deref(), s.deref());
+    fn in_place() {
+        let layout = OwnedSlice::<String, RcHeader>::layout_for_len(2).unwrap();
+        assert!(layout.align() <= mem::align_of::<u64>());
+        let mut buffer = vec![0u64; layout.size().div_ceil(8)];
+        let ptr = NonNull::new(buffer.as_mut_ptr()).unwrap().cast();
+        let strings = strings();
+        let mut s =
+            unsafe { OwnedSlice::<String, RcHeader>::init_in_place(ptr, &strings[..2]) }.unwrap();
+        assert_eq!(&strings[..2], s.deref().deref());
+        assert_eq!(buffer.as_ptr().cast::<u8>(), s.header.as_ptr().cast::<u8>());
+
+        // Sharing works as usual and doesn't free the memory.
+        let s2 = OwnedSlice::clone(&s);
+        assert_eq!(s.as_ptr(), s2.as_ptr());
+        drop(s2);
+        assert!(OwnedSlice::get_mut(&mut s).is_some());
+
+        // Release the strings inside.
+        unsafe { ptr::drop_in_place(OwnedSlice::get_mut(&mut s).unwrap()) };
+        drop(buffer);
     }
 
-    /// Check we can handle panics during partial initialization.
-    ///
-    /// Miri will catch anything we might forget to deallocate. Therefore we put strings in there
-    /// just to make sure there's some allocation in there.
     #[test]
-    fn panic_in_init() {
-        struct MaybePanic(String);
+    fn in_place_empty() {
+        let s = unsafe { OwnedSlice::<u8>::init_in_place(NonNull::dangling(), &[]) }.unwrap();
+        assert!(s.is_sentinel());
+        assert!(OwnedSlice::<u8>::layout_for_len(usize::MAX).is_err());
+    }
 
-        impl Clone for MaybePanic {
-            fn clone(&self) -> Self {
-                if self.0 == "!!!" {
-                    panic!("Panicking for the good measure of it");
-                } else {
-                    Self(self.0.clone())
-                }
+    #[test]
+    fn too_long_details() {
+        let long = vec![String::new(); 5];
+        let err = OwnedSlice::<String, ShortMockHeader>::new(&long).unwrap_err();
+        assert_eq!(TooLong { len: 5, max: 4 }, err);
+        assert_eq!("slice of length 5 exceeds maximum 4", err.to_string());
+
+        let err = OwnedSlice::<u8, ShortMockHeader>::concat(&[&[1, 2], &[3, 4, 5]]).unwrap_err();
+        assert_eq!(TooLong { len: 5, max: 4 }, err);
+        // The length doesn't fit into usize
+        let err = OwnedSlice::<u8, ShortMockHeader>::repeat(&[1, 2], usize::MAX).unwrap_err();
+        assert_eq!(TooLong::overflow::<ShortMockHeader>(), err);
+        assert_eq!(usize::MAX, err.len);
+        let err = Str::<ShortMockHeader>::new("hello").unwrap_err();
+        assert_eq!(TooLong { len: 5, max: 4 }, err);
+
+        let err = BoxHeader::extra_needed(BoxHeader::MAX_LEN + 1).unwrap_err();
+        assert_eq!(BoxHeader::MAX_LEN + 1, err.len);
+        assert_eq!(BoxHeader::MAX_LEN, err.max);
+        let err = RcHeader::extra_needed(usize::MAX).unwrap_err();
+        assert_eq!(RcHeader::MAX_LEN, err.max);
+    }
+
+    #[test]
+    fn max_len() {
+        assert_eq!(BoxHeader::MAX_LEN, OwnedSlice::<String>::MAX_LEN);
+        assert_eq!(BoxHeader::MAX_LEN, OwnedSlice::<u8, ArcHeader>::MAX_LEN);
+        assert!(OwnedSlice::<u8>::layout_for_len(OwnedSlice::<u8>::MAX_LEN).is_ok());
+        assert!(OwnedSlice::<u8>::layout_for_len(OwnedSlice::<u8>::MAX_LEN + 1).is_err());
+
+        assert_eq!(4, OwnedSlice::<String, ShortMockHeader>::MAX_LEN);
+        let long = vec![String::new(); 5];
+        OwnedSlice::<String, ShortMockHeader>::new(&long[..4]).unwrap();
+        OwnedSlice::<String, ShortMockHeader>::new(&long).unwrap_err();
+    }
+
+    #[test]
+    fn uninit() {
+        let mut s = OwnedSlice::<u32>::try_new_uninit(1024).unwrap();
+        for (i, v) in s.iter_mut().enumerate() {
+            *v = MaybeUninit::new(i as u32);
+        }
+        let s = unsafe { s.assume_init() };
+        assert_eq!(1024, s.len());
+        assert!(s.iter().enumerate().all(|(i, v)| i as u32 == *v));
+
+        let s = OwnedSlice::<String, RcHeader>::try_new_uninit(0).unwrap();
+        assert!(s.is_sentinel());
+        assert!(unsafe { s.assume_init() }.is_empty());
+    }
+
+    /// The elements get dropped once initialized.
+    #[test]
+    fn uninit_drop() {
+        let strings = strings();
+        let mut s = OwnedSlice::<String, RcHeader>::try_new_uninit(strings.len()).unwrap();
+        for (dst, src) in OwnedSlice::get_mut(&mut s)
+            .unwrap()
+            .iter_mut()
+            .zip(strings.iter())
+        {
+            *dst = MaybeUninit::new(src.clone());
+        }
+        let s = unsafe { s.assume_init() };
+        assert_eq!(strings.deref(), s.deref());
+    }
+
+    #[test]
+    fn raw_heap_bytes() {
+        for len in [0, 1, 63, 64, 100_000] {
+            let s = OwnedSlice::<u8>::from_fn(len, |i| i as u8).unwrap();
+            let raw = s.raw_heap_bytes();
+            let extra = if len >= 64 {
+                1 + usize::from(len >= 16384)
+            } else {
+                0
+            };
+            let overhead = if len == 0 { 0 } else { 1 + extra };
+            assert_eq!(len + overhead, raw.len());
+            let s2 = OwnedSlice::from_raw_heap_bytes(raw).unwrap();
+            assert_eq!(s.deref(), s2.deref());
+            assert_eq!(len == 0, s2.is_sentinel());
+        }
+    }
+
+    #[test]
+    fn raw_heap_bytes_invalid() {
+        let s = OwnedSlice::<u8>::from_fn(100, |i| i as u8).unwrap();
+        let raw = s.raw_heap_bytes();
+        for truncated in [&raw[..1], &raw[..2], &raw[..raw.len() - 1]] {
+            let e = OwnedSlice::from_raw_heap_bytes(truncated).unwrap_err();
+            assert!(matches!(e, DecodeError::Truncated), "{:?}", e);
+        }
+
+        let mut longer = raw.to_vec();
+        longer.push(0);
+        let e = OwnedSlice::from_raw_heap_bytes(&longer).unwrap_err();
+        assert!(matches!(e, DecodeError::Corrupted));
+
+        // Length 5 encoded with a needless extra byte
+        let e = OwnedSlice::from_raw_heap_bytes(b"\x40\x05hello").unwrap_err();
+        assert!(matches!(e, DecodeError::Corrupted));
+
+        // Header claiming different length
+        let e = OwnedSlice::from_raw_heap_bytes(b"\x04hello").unwrap_err();
+        assert!(matches!(e, DecodeError::Corrupted));
+    }
+
+    #[test]
+    fn reverse_rotate() {
+        let mut s = OwnedSlice::<u32>::new(&[1, 2, 3, 4, 5]).unwrap();
+        s.reverse();
+        assert_eq!(&[5, 4, 3, 2, 1], s.deref());
+        s.rotate_left(2);
+        assert_eq!(&[3, 2, 1, 5, 4], s.deref());
+        s.rotate_right(1).reverse();
+        assert_eq!(&[5, 1, 2, 3, 4], s.deref());
+
+        let mut s = OwnedSlice::<String>::default();
+        s.reverse().rotate_left(0).rotate_right(0);
+        assert!(s.is_sentinel());
+
+        let mut s = OwnedSlice::<String>::new(&["hello".to_owned()]).unwrap();
+        s.reverse().rotate_left(1).rotate_right(1);
+        assert_eq!(&["hello"], s.deref());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rotate_empty() {
+        OwnedSlice::<u8>::default().rotate_left(1);
+    }
+
+    #[test]
+    fn swap() {
+        let mut s = strings();
+        s.swap(0, 2).swap(1, 1);
+        assert_eq!(["World!", "", "Hello"], s);
+        unsafe { s.swap_unchecked(0, 1).swap_unchecked(2, 2) };
+        assert_eq!(["", "World!", "Hello"], s);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swap_out_of_bounds() {
+        strings().swap(0, 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swap_empty() {
+        OwnedSlice::<u8>::default().swap(0, 0);
+    }
+
+    #[test]
+    fn eq() {
+        for len in [0, 1, 63, 64, 65, 1000] {
+            let a = OwnedSlice::<u8>::from_fn(len, |i| i as u8).unwrap();
+            let b = OwnedSlice::<u8>::from_fn(len, |i| i as u8).unwrap();
+            assert_eq!(a, b);
+            let shorter = OwnedSlice::<u8>::from_fn(len.saturating_sub(1), |i| i as u8).unwrap();
+            assert_eq!(len == 0, a == shorter);
+            if len > 0 {
+                let different = OwnedSlice::<u8>::from_fn(len, |i| (i + 1) as u8).unwrap();
+                assert_ne!(a, different);
             }
         }
 
-        let input = vec![
-            MaybePanic("One".to_owned()),
-            MaybePanic("Two".to_owned()),
-            MaybePanic("!!!".to_owned()),
-            MaybePanic("Three".to_owned()),
-        ];
+        assert_eq!(strings(), strings());
+        let nan = OwnedSlice::<f32>::new(&[f32::NAN]).unwrap();
+        assert_ne!(nan, nan);
+    }
 
-        panic::catch_unwind(|| {
-            let _ = OwnedSlice::<MaybePanic>::new(&input);
+    #[test]
+    fn eq_cross_header() {
+        let strings = strings();
+        let rc = OwnedSlice::<String, RcHeader>::new(&strings).unwrap();
+        let arc = OwnedSlice::<String, ArcHeader>::new(&strings).unwrap();
+        assert_eq!(strings, rc);
+        assert_eq!(rc, arc);
+        assert_eq!(arc, strings);
+        assert!(rc == *strings.deref());
+
+        let other = OwnedSlice::<String, RcHeader>::new(&strings[..2]).unwrap();
+        assert_ne!(strings, other);
+        assert!(other != *strings.deref());
+    }
+
+    #[test]
+    fn eq_cross_type() {
+        let strings = strings();
+        let strs = OwnedSlice::<&str, ArcHeader>::new(&["Hello", "", "World!"]).unwrap();
+        assert!(strings == strs);
+        assert!(strs == strings);
+        let slice: &[&str] = &["Hello", "", "World!"];
+        assert!(strings == ["Hello", "", "World!"]);
+        assert!(strings == slice);
+        assert!(strings == *slice);
+        assert!(["Hello", "", "World!"] == strings);
+        assert!(slice == strings);
+        assert!(*slice == strings);
+        assert!(strings != ["Hello", ""]);
+        assert!(["Hello", "", "World"] != strings);
+    }
+
+    /// Comparisons of the same types don't need any annotations.
+    #[test]
+    fn eq_inference() {
+        let a: OwnedSlice<u8> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+        let b: OwnedSlice<u8> = OwnedSlice::new(&[1, 2, 3]).unwrap();
+        assert!(a == b);
+        assert_eq!(a, b);
+        assert!(a == [1, 2, 3]);
+        assert!(a == [1, 2, 3][..]);
+        assert!([1, 2, 3] == a);
+    }
+
+    #[test]
+    fn ord() {
+        let a = OwnedSlice::<u8>::new(&[1, 2, 3]).unwrap();
+        let b = OwnedSlice::<u8, RcHeader>::new(&[1, 3]).unwrap();
+        let c = OwnedSlice::<u8>::new(&[1, 2]).unwrap();
+        assert!(a < b);
+        assert!(c < a);
+        assert_eq!(Some(Ordering::Greater), b.partial_cmp(&c));
+        assert_eq!(Ordering::Less, c.cmp(&a));
+        let mut sorted = vec![a.clone(), c.clone(), OwnedSlice::default()];
+        sorted.sort();
+        assert_eq!(vec![OwnedSlice::default(), c, a], sorted);
+
+        let nan = OwnedSlice::<f32>::new(&[f32::NAN]).unwrap();
+        assert_eq!(None, nan.partial_cmp(&nan));
+    }
+
+    #[test]
+    fn leak() {
+        let s = strings();
+        // Keep the pointer to reclaim the memory at the end, so miri doesn't report the leak.
+        let header = s.header;
+        let leaked: &'static mut [String] = OwnedSlice::leak(s);
+        leaked[1].push('!');
+        assert_eq!(&["Hello", "!", "World!"], leaked);
+        let reclaimed = unsafe { OwnedSlice::<String>::from_raw(header) };
+        assert_eq!(&["Hello", "!", "World!"], reclaimed.deref());
+
+        let leaked: &'static [String] = OwnedSlice::leak(OwnedSlice::default());
+        assert!(leaked.is_empty());
+    }
+
+    #[test]
+    fn try_leak() {
+        let s = OwnedSlice::<String, ArcHeader>::new(&["Hello".to_owned()]).unwrap();
+        let header = s.header;
+        let weak = OwnedSlice::downgrade(&s);
+        let s = OwnedSlice::try_leak(s).unwrap_err();
+        drop(weak);
+        let leaked: &'static [String] = OwnedSlice::try_leak(s).unwrap();
+        assert_eq!(&["Hello"], leaked);
+        drop(unsafe { OwnedSlice::<String, ArcHeader>::from_raw(header) });
+
+        let leaked = OwnedSlice::try_leak(OwnedSlice::<String, RcHeader>::default()).unwrap();
+        assert!(leaked.is_empty());
+    }
+
+    #[test]
+    fn first_last() {
+        let mut s = strings();
+        assert_eq!("Hello", s.first().unwrap());
+        assert_eq!("World!", s.last().unwrap());
+        s.first_mut().unwrap().push('!');
+        *s.last_mut().unwrap() = "Everyone".to_owned();
+        assert_eq!(&["Hello!", "", "Everyone"], s.deref());
+
+        let mut empty = OwnedSlice::<String>::default();
+        assert!(empty.first().is_none());
+        assert!(empty.last().is_none());
+        assert!(empty.first_mut().is_none());
+        assert!(empty.last_mut().is_none());
+        let shared = OwnedSlice::<u8, RcHeader>::new(&[1, 2]).unwrap();
+        assert_eq!((Some(&1), Some(&2)), (shared.first(), shared.last()));
+    }
+
+    #[test]
+    fn get() {
+        let mut s = strings();
+        assert_eq!("World!", s.get(2).unwrap());
+        assert!(s.get(3).is_none());
+        assert_eq!(&["", "World!"], s.get(1..).unwrap());
+        assert_eq!(&[] as &[String], s.get(3..).unwrap());
+        assert!(s.get(2..4).is_none());
+        s[..].get_mut(1).unwrap().push('!');
+        assert!(s[..].get_mut(3).is_none());
+        assert_eq!("!", s[1]);
+
+        let empty = OwnedSlice::<String, RcHeader>::default();
+        assert!(empty.get(0).is_none());
+        assert_eq!(&[] as &[String], empty.get(..).unwrap());
+    }
+
+    /// The direct methods resolve without going through the Deref.
+    fn accessors<H: Header>(s: &OwnedSlice<String, H>) -> Option<(&String, &String, &[String])> {
+        assert_eq!(s.len(), s.iter().count());
+        let first = s.first()?;
+        assert_eq!(first, &s[0]);
+        assert_eq!(s.get(1..), Some(&s[1..]));
+        Some((first, s.last()?, &s[1..s.len()]))
+    }
+
+    #[test]
+    fn index() {
+        let mut s = strings();
+        let (first, last, rest) = accessors(&s).unwrap();
+        assert_eq!(("Hello", "World!"), (first.as_str(), last.as_str()));
+        assert_eq!(&["", "World!"], rest);
+        s[1] = "Big".to_owned();
+        s[0..1][0].push('!');
+        assert_eq!(&["Hello!", "Big", "World!"], &s[..]);
+        let shared = OwnedSlice::<String, RcHeader>::new(&s).unwrap();
+        assert_eq!("Big", &shared[1]);
+
+        assert!(accessors(&OwnedSlice::<String, RcHeader>::default()).is_none());
+        let empty = OwnedSlice::<String>::default();
+        assert!(empty.iter().next().is_none());
+        assert_eq!(&[] as &[String], &empty[..]);
+    }
+
+    #[test]
+    fn iter() {
+        let mut s = strings();
+        assert!(s.iter().eq((*s).iter()));
+        for v in s.iter_mut() {
+            v.push('!');
+        }
+        assert_eq!(&["Hello!", "!", "World!!"], s.deref());
+
+        let mut empty = OwnedSlice::<String>::default();
+        assert!(empty.iter_mut().next().is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_bounds() {
+        let _ = &OwnedSlice::<String>::default()[0];
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    /// Uniquely owned slices have the same auto trait bounds as Box.
+    #[test]
+    fn unique_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<OwnedSlice<Cell<u8>>>();
+        assert_send::<Str>();
+        assert_send_sync::<OwnedSlice<String>>();
+        assert_send_sync::<OwnedSlice<String, ArcHeader>>();
+        assert_send_sync::<Str<ArcHeader>>();
+    }
+
+    #[test]
+    fn ptr_eq() {
+        let s = OwnedSlice::<String, ArcHeader>::new(&["Hello".to_owned()]).unwrap();
+        let shallow = s.clone();
+        assert!(OwnedSlice::ptr_eq(&s, &shallow));
+        let deep = OwnedSlice::<String, ArcHeader>::new(&s).unwrap();
+        assert!(!OwnedSlice::ptr_eq(&s, &deep));
+        assert_eq!(s, deep);
+
+        let s = strings();
+        assert!(OwnedSlice::ptr_eq(&s, &s));
+        assert!(!OwnedSlice::ptr_eq(&s, &s.clone()));
+
+        let empty = OwnedSlice::<String>::default();
+        assert!(OwnedSlice::ptr_eq(&empty, &OwnedSlice::new(&[]).unwrap()));
+    }
+
+    /// A reader returning at most `chunk` bytes at a time and failing after `fail_after` bytes.
+    struct ChunkedReader {
+        data: Cursor<Vec<u8>>,
+        chunk: usize,
+        fail_after: Option<u64>,
+    }
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+            if Some(self.data.position()) == self.fail_after {
+                return Err(IoError::other("Failing for the good measure of it"));
+            }
+            let len = cmp::min(buf.len(), self.chunk);
+            self.data.read(&mut buf[..len])
+        }
+    }
+
+    fn chunked(data: &[u8], chunk: usize, fail_after: Option<u64>) -> ChunkedReader {
+        ChunkedReader {
+            data: Cursor::new(data.to_vec()),
+            chunk,
+            fail_after,
+        }
+    }
+
+    #[test]
+    fn copy_from_reader() {
+        let mut exact = chunked(b"hello", 5, None);
+        let s = OwnedSlice::<u8>::copy_from_reader(5, &mut exact).unwrap();
+        assert_eq!(b"hello", s.deref());
+
+        let mut short_reads = chunked(b"hello world", 2, None);
+        let s = OwnedSlice::<u8, ArcHeader>::copy_from_reader(7, &mut short_reads).unwrap();
+        assert_eq!(b"hello w", s.deref());
+        let s = OwnedSlice::<u8, RcHeader>::copy_from_reader(4, &mut short_reads).unwrap();
+        assert_eq!(b"orld", s.deref());
+
+        let s = OwnedSlice::<u8>::copy_from_reader(0, &mut short_reads).unwrap();
+        assert!(s.is_sentinel());
+    }
+
+    /// Failures free the allocation (checked by miri).
+    #[test]
+    fn copy_from_reader_err() {
+        let mut eof = chunked(b"hello", 2, None);
+        match OwnedSlice::<u8>::copy_from_reader(10, &mut eof) {
+            Err(ReadError::Io(e)) => assert_eq!(ErrorKind::UnexpectedEof, e.kind()),
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let mut failing = chunked(b"hello world", 3, Some(6));
+        match OwnedSlice::<u8, RcMockHeader>::copy_from_reader(10, &mut failing) {
+            Err(ReadError::Io(e)) => assert_eq!(ErrorKind::Other, e.kind()),
+            other => panic!("Unexpected result {:?}", other.map(|s| s.to_vec())),
+        }
+    }
+
+    #[test]
+    fn copy_from_fill() {
+        let mut calls = 0;
+        let s = OwnedSlice::<u8>::copy_from_fill(0, |buf| {
+            calls += 1;
+            assert!(buf.is_empty());
+            Ok::<_, TooLong>(())
         })
+        .unwrap();
+        assert!(s.is_sentinel());
+        assert_eq!(1, calls);
+
+        let err = OwnedSlice::<u8>::copy_from_fill(3, |buf| {
+            buf[0] = 1;
+            Err(ReadError::Io(IoError::other("Nope")))
+        });
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn split_at() {
+        let strings = strings();
+        for mid in 0..=strings.len() {
+            let (a, b) = OwnedSlice::split_at(&strings, mid);
+            assert_eq!(strings.len(), a.len() + b.len());
+            assert_eq!(&strings[..mid], a.deref());
+            assert_eq!(&strings[mid..], b.deref());
+        }
+
+        let (a, b) = OwnedSlice::split_at(&OwnedSlice::<u8>::default(), 0);
+        assert!(a.is_sentinel());
+        assert!(b.is_sentinel());
+
+        // The whole thing is shared, not copied
+        let s = OwnedSlice::<u8, RcHeader>::new(b"hello").unwrap();
+        let (a, b) = OwnedSlice::split_at(&s, 5);
+        assert_eq!(s.as_ptr(), a.as_ptr());
+        assert!(b.is_sentinel());
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_at_out_of_bounds() {
+        OwnedSlice::split_at(&strings(), 4);
+    }
+
+    #[test]
+    fn sorted() {
+        let s = OwnedSlice::<u32>::sorted(&[5, 3, 1, 4, 2]).unwrap();
+        assert_eq!(&[1, 2, 3, 4, 5], s.deref());
+        assert!(s.is_sorted());
+        let s = OwnedSlice::<&str>::sorted_by_key(&["ccc", "a", "bb"], |s| s.len()).unwrap();
+        assert_eq!(&["a", "bb", "ccc"], s.deref());
+        let s = OwnedSlice::<u32, RcHeader>::sorted_by(&[1, 3, 2], |a, b| b.cmp(a)).unwrap();
+        assert_eq!(&[3, 2, 1], s.deref());
+        assert!(!s.is_sorted());
+        assert!(OwnedSlice::<u32>::sorted(&[]).unwrap().is_sentinel());
+
+        let mut s = OwnedSlice::<u32>::new(&[3, 1, 2]).unwrap();
+        s.sort().reverse();
+        assert_eq!(&[3, 2, 1], s.deref());
+    }
+
+    /// Panicking comparator doesn't leak or double-drop (checked by miri).
+    #[test]
+    fn sorted_panic() {
+        let drops = Rc::new(Cell::new(0));
+        let src = (0..10)
+            .map(|_| DropCounter(Rc::clone(&drops)))
+            .collect::<Vec<_>>();
+        let mut comparisons = 0;
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _ = OwnedSlice::<DropCounter>::sorted_by(&src, |_, _| {
+                comparisons += 1;
+                assert!(comparisons < 5, "Panicking for the good measure of it");
+                Ordering::Less
+            });
+        }))
+        .unwrap_err();
+        assert_eq!(10, drops.get());
+    }
+
+    /// Panicking clone during the construction cleans up too.
+    #[test]
+    fn sorted_clone_panic() {
+        let drops = Rc::new(Cell::new(0));
+        let src = [
+            CloneCounter {
+                clones: Rc::new(Cell::new(0)),
+                drops: Rc::clone(&drops),
+                panic_at: 1,
+            },
+            CloneCounter {
+                clones: Rc::new(Cell::new(0)),
+                drops: Rc::clone(&drops),
+                panic_at: 0,
+            },
+        ];
+        panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _ = OwnedSlice::<CloneCounter>::sorted_by(&src, |_, _| Ordering::Equal);
+        }))
         .unwrap_err();
+        assert_eq!(1, drops.get());
+    }
+
+    #[test]
+    fn iterate_refs() {
+        let mut s = OwnedSlice::<u32>::new(&[1, 2, 3]).unwrap();
+        let mut sum = 0;
+        for x in &s {
+            sum += x;
+        }
+        assert_eq!(6, sum);
+        for x in &mut s {
+            *x *= 2;
+        }
+        assert_eq!([2, 4, 6], s[..]);
+
+        let shared = OwnedSlice::<u32, ArcHeader>::new(&[1, 2]).unwrap();
+        assert_eq!(vec![&1, &2], (&shared).into_iter().collect::<Vec<_>>());
+
+        let mut empty = OwnedSlice::<u32>::default();
+        let mut count = 0;
+        for _ in &empty {
+            count += 1;
+        }
+        for _ in &mut empty {
+            count += 1;
+        }
+        assert_eq!(0, count);
+    }
+
+    proptest! {
+        #[test]
+        fn sorted_like_vec(mut v in proptest::collection::vec(any::<u8>(), 0..200)) {
+            let s = OwnedSlice::<u8>::sorted(&v).unwrap();
+            v.sort();
+            prop_assert_eq!(&v[..], s.deref());
+        }
     }
 }
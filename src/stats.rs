@@ -0,0 +1,151 @@
+//! Counters of the heap allocations made by this library.
+//!
+//! With the `stats` feature, every allocation and deallocation of the slice data (and creation of
+//! the non-allocating empty slices) is recorded in global atomic counters. This allows measuring
+//! how much memory storing some data takes, for example in tests or benchmarks.
+//!
+//! Only the memory from the global allocator is counted (not the memory from custom allocators
+//! like the `bumpalo` arena or memory provided by the caller). The allocator overhead is not
+//! included either.
+//!
+//! Without the feature, the counters are not compiled in at all. They need atomic pointer-sized
+//! integers, so they are not available on targets without these either.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(all(feature = "stats", target_has_atomic = "ptr"))] {
+//! use squash::{stats, Str};
+//!
+//! let before = stats::snapshot();
+//! let strings: Vec<Str> = ["Hello", "World"]
+//!     .iter()
+//!     .map(|s| Str::new(s).unwrap())
+//!     .collect();
+//! let after = stats::snapshot();
+//! // These would be off if other threads created slices at the same time.
+//! assert_eq!(2, after.allocations - before.allocations);
+//! // A byte of header and 5 bytes of data for each.
+//! assert_eq!(12, after.live_bytes - before.live_bytes);
+//! # drop(strings);
+//! # }
+//! ```
+
+use alloc::alloc::{
+    alloc as raw_alloc, alloc_zeroed as raw_alloc_zeroed, dealloc as raw_dealloc,
+    realloc as raw_realloc, Layout,
+};
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+static SENTINEL_CONSTRUCTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// A snapshot of the counters.
+///
+/// See [`snapshot`].
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Number of heap allocations made.
+    pub allocations: usize,
+    /// Number of heap allocations freed.
+    pub deallocations: usize,
+    /// Total number of bytes requested by all the allocations.
+    pub allocated_bytes: usize,
+    /// Number of bytes currently allocated.
+    ///
+    /// This wraps around if memory allocated before the last [`reset`] gets freed.
+    pub live_bytes: usize,
+    /// Number of empty slices created without allocating.
+    pub sentinel_constructions: usize,
+}
+
+/// Reads the current values of the counters.
+///
+/// The counters are read one by one, so the snapshot may be slightly inconsistent if other
+/// threads allocate at the same time.
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+pub fn snapshot() -> Stats {
+    Stats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        live_bytes: LIVE_BYTES.load(Ordering::Relaxed),
+        sentinel_constructions: SENTINEL_CONSTRUCTIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Sets all the counters to 0.
+#[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+pub fn reset() {
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    DEALLOCATIONS.store(0, Ordering::Relaxed);
+    ALLOCATED_BYTES.store(0, Ordering::Relaxed);
+    LIVE_BYTES.store(0, Ordering::Relaxed);
+    SENTINEL_CONSTRUCTIONS.store(0, Ordering::Relaxed);
+}
+
+#[inline]
+fn record_alloc(ptr: *mut u8, _layout: Layout) -> *mut u8 {
+    #[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+    if !ptr.is_null() {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(_layout.size(), Ordering::Relaxed);
+        LIVE_BYTES.fetch_add(_layout.size(), Ordering::Relaxed);
+    }
+    ptr
+}
+
+/// Records creation of an empty slice pointing to a sentinel.
+#[inline]
+pub(crate) fn record_sentinel() {
+    #[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+    SENTINEL_CONSTRUCTIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// [`alloc::alloc::alloc`], recorded in the stats.
+#[inline]
+pub(crate) unsafe fn mem_alloc(layout: Layout) -> *mut u8 {
+    record_alloc(raw_alloc(layout), layout)
+}
+
+/// [`alloc::alloc::alloc_zeroed`], recorded in the stats.
+#[inline]
+pub(crate) unsafe fn mem_alloc_zeroed(layout: Layout) -> *mut u8 {
+    record_alloc(raw_alloc_zeroed(layout), layout)
+}
+
+/// [`alloc::alloc::dealloc`], recorded in the stats.
+#[inline]
+pub(crate) unsafe fn mem_dealloc(ptr: *mut u8, layout: Layout) {
+    #[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+    {
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+    raw_dealloc(ptr, layout)
+}
+
+/// [`alloc::alloc::realloc`], recorded in the stats.
+///
+/// This is not counted as a new allocation, only the change of size is.
+#[inline]
+pub(crate) unsafe fn mem_realloc(ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    let result = raw_realloc(ptr, layout, new_size);
+    #[cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+    if !result.is_null() {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        LIVE_BYTES.fetch_add(new_size, Ordering::Relaxed);
+    }
+    result
+}
@@ -0,0 +1,400 @@
+//! Checks of custom [`Header`] implementations.
+//!
+//! The [`Header`] trait is unsafe to implement and the compiler can't check its contract. The
+//! [`check_header`] function exercises an implementation the way the slices use it and panics
+//! with a description of the first violation found. It is meant to be called from the tests of
+//! the crate implementing the header.
+//!
+//! Passing the check doesn't prove the header is correct, but it catches the common mistakes.
+//!
+//! This is available with the `testkit` feature.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use squash::testkit::check_header;
+//! use squash::BoxHeader;
+//!
+//! check_header::<BoxHeader>(100_000);
+//! ```
+
+use alloc::alloc::{alloc, dealloc, Layout};
+use alloc::vec::Vec;
+use core::any::type_name;
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr;
+
+use crate::{Header, Ownership};
+
+/// Bytes placed after the extra bytes, to detect writes past them.
+const GUARD: usize = 16;
+const GUARD_BYTE: u8 = 0xA5;
+/// How many times the strong count is incremented while looking for saturation.
+const MAX_INCS: usize = 10_000;
+
+/// The memory of a header, laid out the same way as in the slices.
+///
+/// The extra bytes follow right after the header. The header is not dropped, the same as in the
+/// slices.
+struct Encoded<H> {
+    ptr: *mut u8,
+    layout: Layout,
+    len: usize,
+    extra: usize,
+    _header: PhantomData<H>,
+}
+
+impl<H: Header> Encoded<H> {
+    fn new(len: usize) -> Self {
+        let extra = H::extra_needed(len).unwrap_or_else(|_| {
+            panic!(
+                "{}: extra_needed fails for length {} <= MAX_LEN",
+                type_name::<H>(),
+                len
+            )
+        });
+        let size = mem::size_of::<H>() + extra + GUARD;
+        let layout = Layout::from_size_align(size, mem::align_of::<H>()).unwrap();
+        unsafe {
+            let ptr = alloc(layout);
+            assert!(!ptr.is_null(), "Failed to allocate memory for the header");
+            ptr::write_bytes(ptr, GUARD_BYTE, size);
+            let header = H::encode_len(len, ptr.add(mem::size_of::<H>()));
+            ptr::write(ptr.cast::<H>(), header);
+            Self {
+                ptr,
+                layout,
+                len,
+                extra,
+                _header: PhantomData,
+            }
+        }
+    }
+
+    fn header(&self) -> &H {
+        unsafe { &*self.ptr.cast::<H>() }
+    }
+
+    fn check_len(&self) {
+        let name = type_name::<H>();
+        let extra_ptr = unsafe { self.ptr.add(mem::size_of::<H>()) };
+        let guard = unsafe { core::slice::from_raw_parts(extra_ptr.add(self.extra), GUARD) };
+        assert!(
+            guard.iter().all(|&b| b == GUARD_BYTE),
+            "{}: encode_len of length {} wrote past the {} extra bytes",
+            name,
+            self.len,
+            self.extra,
+        );
+        let decoded = unsafe { self.header().decode_len(extra_ptr) };
+        assert_eq!(
+            self.len, decoded,
+            "{}: length {} decoded as {}",
+            name, self.len, decoded
+        );
+        if let Some(extra_len) = self.header().extra_len() {
+            assert_eq!(
+                self.extra, extra_len,
+                "{}: extra_len of length {} differs from extra_needed",
+                name, self.len
+            );
+        }
+    }
+}
+
+impl<H> Drop for Encoded<H> {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.ptr, self.layout) };
+    }
+}
+
+/// The lengths to check, up to `max`.
+fn lengths(max: usize, header_max: usize) -> Vec<usize> {
+    let max = max.min(header_max);
+    let mut result: Vec<usize> = (0..=max.min(300)).collect();
+    for shift in 1..usize::BITS {
+        let power = 1usize << shift;
+        result.extend([power - 1, power, power.saturating_add(1)]);
+    }
+    result.push(header_max);
+    result.retain(|&len| len <= max);
+    result.sort_unstable();
+    result.dedup();
+    result
+}
+
+/// Checks the reference counting of a fresh header of a uniquely owned slice.
+fn check_unique<H: Header>(h: &H) {
+    let name = type_name::<H>();
+    assert!(h.is_unique(), "{}: a new header is not unique", name);
+    assert!(
+        !h.inc(),
+        "{}: inc succeeds, but the Ownership is Unique",
+        name
+    );
+    assert!(
+        !h.inc_weak(),
+        "{}: inc_weak succeeds, but the Ownership is Unique",
+        name
+    );
+    assert!(h.dec(), "{}: dec of the only owner doesn't destroy", name);
+}
+
+/// Checks the reference counting of a fresh header of a shared slice.
+///
+/// Headers that don't track their owners (eg. because the memory is owned by an arena) are never
+/// unique. They are allowed to never destroy the data, because they can't know when the last
+/// owner is gone.
+fn check_shared<H: Header>(h: &H) {
+    let name = type_name::<H>();
+    let tracked = h.is_unique();
+
+    let mut count = 1;
+    while count < MAX_INCS && h.inc() {
+        count += 1;
+        assert!(!h.is_unique(), "{}: unique with {} owners", name, count);
+    }
+    if count < MAX_INCS {
+        // Saturated, it must stay so.
+        for _ in 0..10 {
+            assert!(!h.inc(), "{}: inc succeeds after failing", name);
+        }
+    }
+    for remaining in (1..count).rev() {
+        assert!(
+            !h.dec(),
+            "{}: dec destroys while {} owners remain",
+            name,
+            remaining
+        );
+    }
+    if !tracked {
+        assert!(
+            !h.is_unique(),
+            "{}: became unique, but a new header is not",
+            name
+        );
+        if h.dec() {
+            assert!(h.dec_weak(), "{}: dec_weak without weak doesn't free", name);
+        }
+        return;
+    }
+    assert!(
+        h.is_unique(),
+        "{}: not unique after returning to 1 owner",
+        name
+    );
+
+    if h.inc_weak() {
+        assert!(
+            !h.is_unique(),
+            "{}: unique while a weak reference exists",
+            name
+        );
+        assert!(h.upgrade(), "{}: upgrade fails while alive", name);
+        assert!(
+            !h.dec(),
+            "{}: dec destroys with an upgraded reference",
+            name
+        );
+        assert!(h.dec(), "{}: dec of the last strong doesn't destroy", name);
+        assert!(!h.upgrade(), "{}: upgrade succeeds after destruction", name);
+        assert!(
+            !h.dec_weak(),
+            "{}: memory freed while a weak reference exists",
+            name
+        );
+        assert!(h.dec_weak(), "{}: dec_weak of the last doesn't free", name);
+    } else {
+        assert!(h.dec(), "{}: dec of the only owner doesn't destroy", name);
+        assert!(h.dec_weak(), "{}: dec_weak without weak doesn't free", name);
+    }
+}
+
+/// Checks the [`Header`] implementation upholds its contract.
+///
+/// This checks:
+///
+/// * [`extra_needed`][Header::extra_needed] succeeds exactly up to
+///   [`MAX_LEN`][Header::MAX_LEN].
+/// * Lengths round trip through [`encode_len`][Header::encode_len] and
+///   [`decode_len`][Header::decode_len] (all the short lengths and the ones around powers of two
+///   up to `max_len_to_test`), the encoding doesn't write past the extra bytes and
+///   [`extra_len`][Header::extra_len] agrees with them.
+/// * The reference counts pair. The strong count is incremented until the header refuses (but at
+///   most 10 000 times), then it must keep refusing. Weak references, if supported, can be
+///   upgraded only until the last strong one is gone. Shared headers that don't track their
+///   owners (a new header is not [unique][Header::is_unique]) may never destroy the data.
+/// * Headers with the [`Unique`][crate::Unique] ownership never share.
+///
+/// No data is allocated, so lengths up to [`MAX_LEN`][Header::MAX_LEN] are cheap to check.
+///
+/// # Panics
+///
+/// With a description of the violation, if the header violates the contract.
+pub fn check_header<H: Header>(max_len_to_test: usize) {
+    let name = type_name::<H>();
+    if let Some(over) = H::MAX_LEN.checked_add(1) {
+        assert!(
+            H::extra_needed(over).is_err(),
+            "{}: extra_needed succeeds for length {} > MAX_LEN",
+            name,
+            over
+        );
+    }
+
+    for len in lengths(max_len_to_test, H::MAX_LEN) {
+        Encoded::<H>::new(len).check_len();
+    }
+
+    let encoded = Encoded::<H>::new(1);
+    if H::Ownership::is_shared() {
+        check_shared(encoded.header());
+    } else {
+        check_unique(encoded.header());
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use core::cell::Cell;
+
+    use super::*;
+    use crate::header::mock::{RcMockHeader, SaturatedMockHeader, ShortMockHeader};
+    use crate::{ArcHeader, BoxHeader, ByteHeader, RcHeader, Shared, TooLong, Unique};
+
+    #[test]
+    fn provided_headers() {
+        check_header::<BoxHeader>(usize::MAX);
+        check_header::<RcHeader>(usize::MAX);
+        check_header::<ArcHeader>(usize::MAX);
+        check_header::<RcMockHeader>(1000);
+        check_header::<SaturatedMockHeader>(1000);
+        check_header::<ShortMockHeader>(1000);
+        check_header::<ByteHeader>(1000);
+    }
+
+    /// The arena doesn't track the owners, so it is an exception to the reference counting.
+    #[test]
+    #[cfg(feature = "bumpalo")]
+    fn bump_header() {
+        check_header::<crate::BumpHeader>(usize::MAX);
+    }
+
+    /// A header with a tiny reference count (it saturates).
+    struct TinyHeader {
+        count: Cell<u8>,
+        len: BoxHeader,
+    }
+
+    unsafe impl Header for TinyHeader {
+        type Ownership = Shared;
+        const MAX_LEN: usize = BoxHeader::MAX_LEN;
+
+        fn extra_needed(len: usize) -> Result<usize, TooLong> {
+            BoxHeader::extra_needed(len)
+        }
+        unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+            Self {
+                count: Cell::new(1),
+                len: BoxHeader::encode_len(len, extra),
+            }
+        }
+        unsafe fn decode_len(&self, extra: *const u8) -> usize {
+            self.len.decode_len(extra)
+        }
+        fn inc(&self) -> bool {
+            match self.count.get().checked_add(1) {
+                Some(c) => {
+                    self.count.set(c);
+                    true
+                }
+                None => false,
+            }
+        }
+        fn dec(&self) -> bool {
+            self.count.set(self.count.get() - 1);
+            self.count.get() == 0
+        }
+        fn is_unique(&self) -> bool {
+            self.count.get() == 1
+        }
+    }
+
+    #[test]
+    fn saturating() {
+        check_header::<TinyHeader>(1000);
+    }
+
+    /// Decodes one less for long lengths.
+    struct BrokenLen(BoxHeader);
+
+    unsafe impl Header for BrokenLen {
+        type Ownership = Unique;
+        const MAX_LEN: usize = BoxHeader::MAX_LEN;
+
+        fn extra_needed(len: usize) -> Result<usize, TooLong> {
+            BoxHeader::extra_needed(len)
+        }
+        unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+            Self(BoxHeader::encode_len(len, extra))
+        }
+        unsafe fn decode_len(&self, extra: *const u8) -> usize {
+            let len = self.0.decode_len(extra);
+            if len > 1000 {
+                len - 1
+            } else {
+                len
+            }
+        }
+        fn inc(&self) -> bool {
+            false
+        }
+        fn dec(&self) -> bool {
+            true
+        }
+        fn is_unique(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "length 1023 decoded as 1022")]
+    fn broken_len() {
+        check_header::<BrokenLen>(2000);
+    }
+
+    /// Claims to be unique, but counts references.
+    struct BrokenUnique(RcMockHeader);
+
+    unsafe impl Header for BrokenUnique {
+        type Ownership = Unique;
+        const MAX_LEN: usize = BoxHeader::MAX_LEN;
+
+        fn extra_needed(len: usize) -> Result<usize, TooLong> {
+            BoxHeader::extra_needed(len)
+        }
+        unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+            Self(RcMockHeader::encode_len(len, extra))
+        }
+        unsafe fn decode_len(&self, extra: *const u8) -> usize {
+            self.0.decode_len(extra)
+        }
+        fn inc(&self) -> bool {
+            self.0.inc()
+        }
+        fn dec(&self) -> bool {
+            self.0.dec()
+        }
+        fn is_unique(&self) -> bool {
+            self.0.is_unique()
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "inc succeeds, but the Ownership is Unique")]
+    fn broken_unique() {
+        check_header::<BrokenUnique>(10);
+    }
+}
@@ -0,0 +1,265 @@
+use alloc::ffi::CString;
+use alloc::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use core::convert::TryFrom;
+use core::error::Error;
+use core::ffi::{c_char, CStr, FromBytesWithNulError};
+use core::iter;
+use core::ops::Deref;
+
+use crate::{BoxHeader, Header, OwnedSlice, TooLong};
+
+/// An error when creating the [`OwnedCStr`] from bytes.
+#[derive(Clone, Debug)]
+pub enum CStrError {
+    /// The string is too long for the header.
+    TooLong(TooLong),
+    /// The bytes don't form a valid C string.
+    ///
+    /// They either miss the terminating nul byte or contain a nul byte somewhere in the middle.
+    Nul(FromBytesWithNulError),
+    /// The bytes contain a nul byte at the given position.
+    ///
+    /// Returned when the terminating nul byte is to be appended, but there's one already inside.
+    InteriorNul(usize),
+}
+
+impl Display for CStrError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            CStrError::TooLong(e) => Display::fmt(e, fmt),
+            CStrError::Nul(e) => Display::fmt(e, fmt),
+            CStrError::InteriorNul(pos) => write!(fmt, "Nul byte found at position {}", pos),
+        }
+    }
+}
+
+impl Error for CStrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CStrError::TooLong(e) => Some(e),
+            CStrError::Nul(e) => Some(e),
+            CStrError::InteriorNul(_) => None,
+        }
+    }
+}
+
+impl From<TooLong> for CStrError {
+    fn from(e: TooLong) -> Self {
+        CStrError::TooLong(e)
+    }
+}
+
+impl From<FromBytesWithNulError> for CStrError {
+    fn from(e: FromBytesWithNulError) -> Self {
+        CStrError::Nul(e)
+    }
+}
+
+/// An owned C string.
+///
+/// This is the same optimisation as [`OwnedSlice`] does, but applied to [`CStr`] ‒ the
+/// nul-terminated strings used by C. The terminating nul byte is stored in the allocation, so the
+/// pointer can be passed to C functions directly.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::ffi::CStr;
+/// use squash::OwnedCStr;
+///
+/// let c: OwnedCStr = OwnedCStr::from_bytes_with_nul(b"Hello\0").unwrap();
+/// assert_eq!(c.to_str().unwrap(), "Hello");
+/// let ptr = c.as_ptr();
+/// assert_eq!(unsafe { CStr::from_ptr(ptr) }, &c as &CStr);
+/// ```
+pub struct OwnedCStr<H: Header = BoxHeader>(OwnedSlice<u8, H>);
+
+impl<H> OwnedCStr<H>
+where
+    H: Header,
+{
+    /// Creates a new owned C string.
+    #[inline]
+    pub fn new(s: &CStr) -> Result<Self, TooLong> {
+        OwnedSlice::new(s.to_bytes_with_nul()).map(Self)
+    }
+
+    /// Creates a new owned C string from bytes.
+    ///
+    /// The bytes must contain exactly one nul byte, at the very end.
+    pub fn from_bytes_with_nul(bytes: &[u8]) -> Result<Self, CStrError> {
+        let s = CStr::from_bytes_with_nul(bytes)?;
+        Ok(Self::new(s)?)
+    }
+
+    /// Creates a new owned C string from bytes without the terminating nul byte.
+    ///
+    /// The nul byte is appended (in the same allocation). The bytes must not contain any nul
+    /// byte.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{BoxHeader, CStrError, OwnedCStr};
+    ///
+    /// let c: OwnedCStr = OwnedCStr::from_bytes(b"Hello").unwrap();
+    /// assert_eq!(b"Hello\0", c.to_bytes_with_nul());
+    /// let err = OwnedCStr::<BoxHeader>::from_bytes(b"He\0llo");
+    /// assert!(matches!(err, Err(CStrError::InteriorNul(2))));
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CStrError> {
+        if let Some(pos) = bytes.iter().position(|&b| b == 0) {
+            return Err(CStrError::InteriorNul(pos));
+        }
+        let with_nul = bytes.iter().copied().chain(iter::once(0));
+        Ok(Self(OwnedSlice::from_iter_len(bytes.len() + 1, with_nul)?))
+    }
+
+    /// Returns the pointer to the beginning of the string.
+    ///
+    /// The string is nul-terminated and the pointer is valid for as long as this owned string
+    /// lives.
+    #[inline]
+    pub fn as_ptr(&self) -> *const c_char {
+        self.0.as_ptr().cast()
+    }
+}
+
+// Not derived, the derive would require H: Clone
+impl<H> Clone for OwnedCStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(
+            self.0
+                .try_copy_clone()
+                .expect("Already have layout for this size"),
+        )
+    }
+}
+
+impl<H> Debug for OwnedCStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "{:?}", self.deref())
+    }
+}
+
+impl<H> Deref for OwnedCStr<H>
+where
+    H: Header,
+{
+    type Target = CStr;
+
+    #[inline]
+    fn deref(&self) -> &CStr {
+        // It was created from CStr originally
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.0) }
+    }
+}
+
+impl<H> TryFrom<&CStr> for OwnedCStr<H>
+where
+    H: Header,
+{
+    type Error = TooLong;
+
+    #[inline]
+    fn try_from(s: &CStr) -> Result<Self, TooLong> {
+        Self::new(s)
+    }
+}
+
+/// Appends the terminating nul byte.
+///
+/// Fails if the string contains a nul byte.
+impl<H> TryFrom<&str> for OwnedCStr<H>
+where
+    H: Header,
+{
+    type Error = CStrError;
+
+    #[inline]
+    fn try_from(s: &str) -> Result<Self, CStrError> {
+        Self::from_bytes(s.as_bytes())
+    }
+}
+
+impl<H> From<&OwnedCStr<H>> for CString
+where
+    H: Header,
+{
+    #[inline]
+    fn from(s: &OwnedCStr<H>) -> CString {
+        CString::from(s.deref())
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use super::*;
+    use crate::RcHeader;
+
+    #[test]
+    fn cstr() {
+        let src = CStr::from_bytes_with_nul(b"Hello\0").unwrap();
+        let s: OwnedCStr = OwnedCStr::new(src).unwrap();
+        assert_eq!(src, s.deref());
+        assert_eq!(b"Hello\0", &s.0[..]);
+        assert_eq!(src, unsafe { CStr::from_ptr(s.as_ptr()) });
+        assert_eq!("\"Hello\"", format!("{:?}", s));
+
+        let s2 = s.clone();
+        assert_eq!(src, s2.deref());
+    }
+
+    #[test]
+    fn empty() {
+        let s: OwnedCStr = OwnedCStr::from_bytes_with_nul(b"\0").unwrap();
+        assert!(s.is_empty());
+        assert_eq!(b"\0", &s.0[..]);
+        assert_eq!(0, unsafe { *s.as_ptr() });
+    }
+
+    #[test]
+    fn invalid() {
+        let missing = OwnedCStr::<BoxHeader>::from_bytes_with_nul(b"Hello");
+        assert!(matches!(missing, Err(CStrError::Nul(_))));
+        let interior = OwnedCStr::<BoxHeader>::from_bytes_with_nul(b"Hel\0lo\0");
+        assert!(matches!(interior, Err(CStrError::Nul(_))));
+        let interior = OwnedCStr::<BoxHeader>::try_from("Hel\0lo");
+        assert!(matches!(interior, Err(CStrError::InteriorNul(3))));
+        let trailing = OwnedCStr::<BoxHeader>::from_bytes(b"Hello\0");
+        assert!(matches!(trailing, Err(CStrError::InteriorNul(5))));
+    }
+
+    #[test]
+    fn from_str() {
+        let s: OwnedCStr = OwnedCStr::try_from("Hello").unwrap();
+        assert_eq!(b"Hello\0", &s.0[..]);
+        assert_eq!(
+            b"Hello\0",
+            unsafe { CStr::from_ptr(s.as_ptr()) }.to_bytes_with_nul()
+        );
+
+        let empty: OwnedCStr = OwnedCStr::try_from("").unwrap();
+        assert_eq!(b"\0", &empty.0[..]);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn cstring_round_trip() {
+        for src in ["", "Hello", "Příliš žluťoučký kůň"] {
+            let orig = CString::new(src).unwrap();
+            let s = OwnedCStr::<RcHeader>::try_from(orig.as_c_str()).unwrap();
+            let back = CString::from(&s);
+            assert_eq!(orig, back);
+            assert_eq!(src, back.to_str().unwrap());
+        }
+    }
+}
@@ -0,0 +1,471 @@
+use alloc::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop};
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+use core::{slice, str};
+
+use crate::{BoxHeader, Header, OverflowPolicy, OwnedSlice, Str, TooLong};
+
+const WORD: usize = mem::size_of::<usize>();
+/// How many bytes fit inline (one byte of the word is taken by the tag and length).
+const INLINE_CAP: usize = WORD - 1;
+/// The byte holding the lowest bit of the pointer when seen as an integer.
+const TAG_IDX: usize = if cfg!(target_endian = "little") {
+    0
+} else {
+    WORD - 1
+};
+/// Where the inline data start.
+const DATA_IDX: usize = if cfg!(target_endian = "little") { 1 } else { 0 };
+
+/// Makes sure the heap allocations are aligned to at least 2.
+///
+/// That keeps the lowest bit of the pointer free for the tag.
+#[repr(C, align(2))]
+struct Aligned<H>(H);
+
+unsafe impl<H> Header for Aligned<H>
+where
+    H: Header,
+{
+    type Ownership = H::Ownership;
+    const MAX_LEN: usize = H::MAX_LEN;
+
+    #[inline]
+    fn extra_needed(len: usize) -> Result<usize, TooLong> {
+        H::extra_needed(len)
+    }
+    #[inline]
+    unsafe fn encode_len(len: usize, extra: *mut u8) -> Self {
+        Self(H::encode_len(len, extra))
+    }
+    #[inline]
+    unsafe fn decode_len(&self, extra: *const u8) -> usize {
+        self.0.decode_len(extra)
+    }
+    #[inline]
+    fn extra_len(&self) -> Option<usize> {
+        self.0.extra_len()
+    }
+    #[inline]
+    fn inc(&self) -> bool {
+        self.0.inc()
+    }
+    #[inline]
+    fn dec(&self) -> bool {
+        self.0.dec()
+    }
+    #[inline]
+    fn on_overflow() -> OverflowPolicy {
+        H::on_overflow()
+    }
+    #[inline]
+    fn strong_count(&self) -> Option<usize> {
+        self.0.strong_count()
+    }
+    #[inline]
+    fn inc_weak(&self) -> bool {
+        self.0.inc_weak()
+    }
+    #[inline]
+    fn dec_weak(&self) -> bool {
+        self.0.dec_weak()
+    }
+    #[inline]
+    fn upgrade(&self) -> bool {
+        self.0.upgrade()
+    }
+    #[inline]
+    fn is_unique(&self) -> bool {
+        self.0.is_unique()
+    }
+}
+
+/// An owned string slice, storing short strings inline.
+///
+/// This is a hybrid between the [`Str`] and the small string optimisation. It is still a single
+/// word on the stack (and `Option<InlineStr>` is too), but strings of up to 7 bytes (3 on 32bit
+/// platforms) are stored directly in that word, without any allocation. Longer strings are
+/// stored on the heap the same way as in [`Str`].
+///
+/// The lowest bit of the word tells the representations apart. To keep it free, the heap
+/// allocations are aligned to at least 2 bytes, which may cost a byte of padding after the
+/// [`BoxHeader`].
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::InlineStr;
+/// let short: InlineStr = InlineStr::new("hello").unwrap();
+/// assert!(short.is_inline());
+/// let long: InlineStr = InlineStr::new("hello world").unwrap();
+/// assert!(!long.is_inline());
+/// assert_eq!("hello world", &long as &str);
+/// ```
+pub struct InlineStr<H: Header = BoxHeader> {
+    /// Either the heap pointer of `Str<Aligned<H>>` or the inline data with the tag bit set.
+    ptr: NonNull<u8>,
+    _str: PhantomData<Str<H>>,
+}
+
+impl<H> InlineStr<H>
+where
+    H: Header,
+{
+    /// Creates a new owned string slice.
+    ///
+    /// # Errors
+    ///
+    /// If the string is bigger than the header can encode, [`TooLong`] is returned.
+    pub fn new(s: &str) -> Result<Self, TooLong> {
+        if s.len() <= INLINE_CAP {
+            Ok(Self::inline(s))
+        } else {
+            Str::new(s).map(Self::heap)
+        }
+    }
+
+    fn inline(s: &str) -> Self {
+        debug_assert!(s.len() <= INLINE_CAP);
+        let mut bytes = [0; WORD];
+        bytes[TAG_IDX] = (s.len() as u8) << 1 | 1;
+        bytes[DATA_IDX..DATA_IDX + s.len()].copy_from_slice(s.as_bytes());
+        let addr = usize::from_ne_bytes(bytes);
+        // Not a real pointer, but never null thanks to the tag.
+        let ptr = unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(addr)) };
+        Self {
+            ptr,
+            _str: PhantomData,
+        }
+    }
+
+    fn heap(s: Str<Aligned<H>>) -> Self {
+        let ptr = OwnedSlice::into_raw(s.0).cast::<u8>();
+        debug_assert_eq!(0, ptr.as_ptr().addr() & 1);
+        Self {
+            ptr,
+            _str: PhantomData,
+        }
+    }
+
+    /// Checks if the string is stored inline (doesn't use the heap).
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.ptr.as_ptr().addr() & 1 == 1
+    }
+
+    #[inline]
+    fn inline_len(&self) -> usize {
+        usize::from(self.ptr.as_ptr().addr().to_ne_bytes()[TAG_IDX] >> 1)
+    }
+
+    #[inline]
+    fn inline_data(&self) -> *const u8 {
+        unsafe { (&self.ptr as *const NonNull<u8>).cast::<u8>().add(DATA_IDX) }
+    }
+
+    /// Temporarily reconstructs the heap string.
+    ///
+    /// Must not be called on the inline representation.
+    #[inline]
+    unsafe fn heap_str(&self) -> ManuallyDrop<Str<Aligned<H>>> {
+        debug_assert!(!self.is_inline());
+        ManuallyDrop::new(Str(OwnedSlice::from_raw(self.ptr.cast())))
+    }
+
+    /// How many bytes the heap allocation takes (not counting any allocator overhead).
+    #[cfg_attr(not(feature = "deepsize"), allow(dead_code))]
+    pub(crate) fn heap_size(&self) -> usize {
+        if self.is_inline() {
+            0
+        } else {
+            unsafe { self.heap_str() }.0.heap_size()
+        }
+    }
+
+    /// Clones the string, reporting errors instead of panicking.
+    ///
+    /// See [`OwnedSlice::try_clone`] for details.
+    pub fn try_clone(&self) -> Result<Self, TooLong> {
+        if self.is_inline() {
+            Ok(Self {
+                ptr: self.ptr,
+                _str: PhantomData,
+            })
+        } else {
+            unsafe { self.heap_str() }.try_clone().map(Self::heap)
+        }
+    }
+
+    /// Provides mutable access to the string, if it is not shared.
+    ///
+    /// See [`Str::get_mut`].
+    pub fn get_mut(this: &mut Self) -> Option<&mut str> {
+        if this.is_inline() {
+            let len = this.inline_len();
+            unsafe {
+                let data = (&mut this.ptr as *mut NonNull<u8>)
+                    .cast::<u8>()
+                    .add(DATA_IDX);
+                let bytes = slice::from_raw_parts_mut(data, len);
+                Some(str::from_utf8_unchecked_mut(bytes))
+            }
+        } else {
+            let mut s = unsafe { this.heap_str() };
+            // The data lives in the heap allocation we own, not in the temporary.
+            Str::get_mut(&mut s).map(|s| unsafe { &mut *(s as *mut str) })
+        }
+    }
+}
+
+impl<H> Drop for InlineStr<H>
+where
+    H: Header,
+{
+    fn drop(&mut self) {
+        if !self.is_inline() {
+            unsafe { ManuallyDrop::drop(&mut self.heap_str()) };
+        }
+    }
+}
+
+// Not derived, the derive would require H: Clone
+impl<H> Clone for InlineStr<H>
+where
+    H: Header,
+{
+    fn clone(&self) -> Self {
+        self.try_clone().expect("Already have layout for this size")
+    }
+}
+
+impl<H> Default for InlineStr<H>
+where
+    H: Header,
+{
+    fn default() -> Self {
+        Self::inline("")
+    }
+}
+
+impl<H> Debug for InlineStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        Debug::fmt(self.deref(), fmt)
+    }
+}
+
+impl<H> Display for InlineStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        Display::fmt(self.deref(), fmt)
+    }
+}
+
+/// Compares the content, regardless of where it is stored or what the headers are.
+impl<H1, H2> PartialEq<InlineStr<H2>> for InlineStr<H1>
+where
+    H1: Header,
+    H2: Header,
+{
+    #[inline]
+    fn eq(&self, other: &InlineStr<H2>) -> bool {
+        self.deref() == other.deref()
+    }
+}
+
+impl<H> PartialEq<str> for InlineStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.deref() == other
+    }
+}
+
+impl<H> PartialEq<&str> for InlineStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.deref() == *other
+    }
+}
+
+impl<H> Eq for InlineStr<H> where H: Header {}
+
+/// Compares the strings lexicographically, by their bytes.
+impl<H1, H2> PartialOrd<InlineStr<H2>> for InlineStr<H1>
+where
+    H1: Header,
+    H2: Header,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &InlineStr<H2>) -> Option<Ordering> {
+        Some(self.deref().cmp(other.deref()))
+    }
+}
+
+impl<H> Ord for InlineStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+/// Hashes the same way as the [`str`][prim@str] does.
+impl<H> Hash for InlineStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.deref().hash(state)
+    }
+}
+
+impl<H> Deref for InlineStr<H>
+where
+    H: Header,
+{
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        if self.is_inline() {
+            unsafe {
+                let bytes = slice::from_raw_parts(self.inline_data(), self.inline_len());
+                str::from_utf8_unchecked(bytes)
+            }
+        } else {
+            let s = unsafe { self.heap_str() };
+            // The data lives in the heap allocation we own, not in the temporary.
+            unsafe { &*(s.deref().deref() as *const str) }
+        }
+    }
+}
+
+impl DerefMut for InlineStr<BoxHeader> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut str {
+        InlineStr::get_mut(self).expect("Box header is always unique")
+    }
+}
+
+unsafe impl<H> Send for InlineStr<H> where H: Header + Send + Sync {}
+
+unsafe impl<H> Sync for InlineStr<H> where H: Header + Send + Sync {}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+    use crate::RcHeader;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<InlineStr>());
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<Option<InlineStr>>());
+        assert_eq!(
+            mem::size_of::<usize>(),
+            mem::size_of::<Option<InlineStr<RcHeader>>>()
+        );
+    }
+
+    #[test]
+    fn boundary() {
+        let all = "0123456789abcdef";
+        for len in 0..=all.len() {
+            let s: InlineStr = InlineStr::new(&all[..len]).unwrap();
+            assert_eq!(len <= INLINE_CAP, s.is_inline());
+            assert_eq!(&all[..len], s.deref());
+            let s2 = s.clone();
+            assert_eq!(&all[..len], s2.deref());
+            assert_eq!(format!("{:?}", &all[..len]), format!("{:?}", s2));
+        }
+    }
+
+    #[test]
+    fn mutate() {
+        for src in ["hello", "hello world"] {
+            let mut s: InlineStr = InlineStr::new(src).unwrap();
+            s.make_ascii_uppercase();
+            assert_eq!(src.to_uppercase(), s.deref());
+        }
+    }
+
+    #[test]
+    fn shared() {
+        let mut s: InlineStr<RcHeader> = InlineStr::new("hello world").unwrap();
+        let s2 = s.clone();
+        assert_eq!(s.as_ptr(), s2.as_ptr());
+        assert!(InlineStr::get_mut(&mut s).is_none());
+        drop(s2);
+        InlineStr::get_mut(&mut s).unwrap().make_ascii_uppercase();
+        assert_eq!("HELLO WORLD", s.deref());
+
+        let mut s: InlineStr<RcHeader> = InlineStr::new("hi").unwrap();
+        let _s2 = s.clone();
+        assert!(InlineStr::get_mut(&mut s).is_some());
+    }
+
+    #[test]
+    fn unicode() {
+        let s: InlineStr = InlineStr::new("ěšč").unwrap();
+        assert!(s.is_inline());
+        assert_eq!("ěšč", s.deref());
+        let s: InlineStr = InlineStr::new("ěščř").unwrap();
+        assert!(!s.is_inline());
+        assert_eq!("ěščř", s.deref());
+        assert_eq!("", InlineStr::<BoxHeader>::default().deref());
+    }
+
+    fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn cmp_hash() {
+        let short: InlineStr = InlineStr::new("abc").unwrap();
+        let long: InlineStr<RcHeader> = InlineStr::new("abcdefghijk").unwrap();
+        let long2: InlineStr = InlineStr::new("abcdefghijk").unwrap();
+        assert!(short.is_inline());
+        assert!(!long.is_inline());
+        assert_eq!(long, long2);
+        assert_ne!(short, long2);
+        assert!(short == "abc");
+        assert!(long == *"abcdefghijk");
+        assert!(short < long);
+        assert!(long2 > short);
+
+        let mut sorted: Vec<InlineStr> = ["b", "", "abcdefghijk", "a"]
+            .iter()
+            .map(|s| InlineStr::new(s).unwrap())
+            .collect();
+        sorted.sort();
+        assert!(sorted
+            .iter()
+            .map(Deref::deref)
+            .eq(["", "a", "abcdefghijk", "b"]));
+
+        assert_eq!(hash_of("abc"), hash_of(&short));
+        assert_eq!(hash_of(&long), hash_of(&long2));
+    }
+}
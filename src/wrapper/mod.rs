@@ -1,3 +1,9 @@
+pub(crate) mod cstr;
+pub(crate) mod inline;
+#[cfg(feature = "std")]
+pub(crate) mod os_str;
+#[cfg(feature = "std")]
+pub(crate) mod path;
 pub(crate) mod str;
 
-// TODO: CStr and other wrappers
+// TODO: Other wrappers
@@ -0,0 +1,258 @@
+use alloc::fmt::{Debug, Formatter, Result as FmtResult};
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::{BoxHeader, Header, OwnedSlice, TooLong};
+
+/// An owned platform string.
+///
+/// This is the same optimisation as [`OwnedSlice`] does, but applied to [`OsStr`]. It stores the
+/// platform specific encoding of the string (see [`OsStr::as_encoded_bytes`]), so it can hold
+/// anything the original [`OsStr`] can, including non-UTF-8 strings.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::convert::TryFrom;
+/// use std::path::Path;
+/// use squash::OwnedOsStr;
+///
+/// let s: OwnedOsStr = OwnedOsStr::try_from("/etc/passwd").unwrap();
+/// assert_eq!(Path::new("/etc/passwd"), AsRef::<Path>::as_ref(&s));
+/// ```
+pub struct OwnedOsStr<H: Header = BoxHeader>(OwnedSlice<u8, H>);
+
+impl<H> OwnedOsStr<H>
+where
+    H: Header,
+{
+    /// Creates a new owned platform string.
+    #[inline]
+    pub fn new(s: &OsStr) -> Result<Self, TooLong> {
+        OwnedSlice::new(s.as_encoded_bytes()).map(Self)
+    }
+}
+
+// Not derived, the derive would require H: Clone
+impl<H> Clone for OwnedOsStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(
+            self.0
+                .try_copy_clone()
+                .expect("Already have layout for this size"),
+        )
+    }
+}
+
+impl<H> Debug for OwnedOsStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "{:?}", self.deref())
+    }
+}
+
+impl<H> Deref for OwnedOsStr<H>
+where
+    H: Header,
+{
+    type Target = OsStr;
+
+    #[inline]
+    fn deref(&self) -> &OsStr {
+        // It was created from OsStr originally, on the same platform
+        unsafe { OsStr::from_encoded_bytes_unchecked(&self.0) }
+    }
+}
+
+impl<H> AsRef<OsStr> for OwnedOsStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn as_ref(&self) -> &OsStr {
+        self.deref()
+    }
+}
+
+impl<H> AsRef<Path> for OwnedOsStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        Path::new(self.deref())
+    }
+}
+
+impl<H> TryFrom<&OsStr> for OwnedOsStr<H>
+where
+    H: Header,
+{
+    type Error = TooLong;
+
+    #[inline]
+    fn try_from(s: &OsStr) -> Result<Self, TooLong> {
+        Self::new(s)
+    }
+}
+
+impl<H> TryFrom<&str> for OwnedOsStr<H>
+where
+    H: Header,
+{
+    type Error = TooLong;
+
+    #[inline]
+    fn try_from(s: &str) -> Result<Self, TooLong> {
+        Self::new(s.as_ref())
+    }
+}
+
+impl<H> Borrow<OsStr> for OwnedOsStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn borrow(&self) -> &OsStr {
+        self.deref()
+    }
+}
+
+impl<H1, H2> PartialEq<OwnedOsStr<H2>> for OwnedOsStr<H1>
+where
+    H1: Header,
+    H2: Header,
+{
+    #[inline]
+    fn eq(&self, other: &OwnedOsStr<H2>) -> bool {
+        self.deref() == other.deref()
+    }
+}
+
+impl<H> PartialEq<OsStr> for OwnedOsStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &OsStr) -> bool {
+        self.deref() == other
+    }
+}
+
+impl<H> Eq for OwnedOsStr<H> where H: Header {}
+
+impl<H1, H2> PartialOrd<OwnedOsStr<H2>> for OwnedOsStr<H1>
+where
+    H1: Header,
+    H2: Header,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &OwnedOsStr<H2>) -> Option<Ordering> {
+        Some(self.deref().cmp(other.deref()))
+    }
+}
+
+impl<H> Ord for OwnedOsStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+impl<H> Hash for OwnedOsStr<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.deref().hash(state)
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::*;
+
+    #[test]
+    fn path() {
+        let s: OwnedOsStr = OwnedOsStr::try_from("/usr/bin").unwrap();
+        assert_eq!(OsStr::new("/usr/bin"), s.deref());
+        assert_eq!(Path::new("/usr/bin"), AsRef::<Path>::as_ref(&s));
+        assert_eq!("\"/usr/bin\"", format!("{:?}", s));
+        let s2 = s.clone();
+        assert_eq!(s.deref(), s2.deref());
+    }
+
+    #[test]
+    fn empty() {
+        let s: OwnedOsStr = OwnedOsStr::new(OsStr::new("")).unwrap();
+        assert!(s.is_empty());
+        assert_eq!(OsStr::new(""), s.deref());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let src = OsStr::from_bytes(b"Hello\xff");
+        let s: OwnedOsStr = OwnedOsStr::new(src).unwrap();
+        assert_eq!(src, s.deref());
+        assert!(s.to_str().is_none());
+
+        let empty: OwnedOsStr = OwnedOsStr::new(OsStr::from_bytes(b"")).unwrap();
+        assert_eq!(OsStr::new(""), empty.deref());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn non_utf8() {
+        use std::os::windows::ffi::OsStringExt;
+
+        // An unpaired surrogate
+        let src = OsString::from_wide(&[0x48, 0xD800, 0x49]);
+        let s: OwnedOsStr = OwnedOsStr::new(&src).unwrap();
+        assert_eq!(src, s.deref());
+        assert!(s.to_str().is_none());
+
+        let empty: OwnedOsStr = OwnedOsStr::new(&OsString::from_wide(&[])).unwrap();
+        assert_eq!(OsStr::new(""), empty.deref());
+    }
+
+    #[test]
+    fn compare() {
+        use std::collections::HashSet;
+
+        let a: OwnedOsStr = OwnedOsStr::try_from("a").unwrap();
+        let b: OwnedOsStr<crate::RcHeader> = OwnedOsStr::try_from("b").unwrap();
+        assert!(a < b);
+        assert_ne!(a, b);
+        assert_eq!(a, *OsStr::new("a"));
+        let set = std::iter::once(a.clone()).collect::<HashSet<_>>();
+        assert!(set.contains(OsStr::new("a")));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let src = OsString::from("some/relative/path");
+        let s: OwnedOsStr = OwnedOsStr::new(&src).unwrap();
+        assert_eq!(src, s.to_os_string());
+    }
+}
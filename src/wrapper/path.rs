@@ -0,0 +1,296 @@
+use alloc::fmt::{Debug, Formatter, Result as FmtResult};
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::{BoxHeader, Header, OwnedOsStr, TooLong};
+
+/// An owned filesystem path.
+///
+/// This is a thin wrapper around [`OwnedOsStr`] that dereferences to [`Path`] (and therefore
+/// behaves like [`PathBuf`][std::path::PathBuf], except it can't be modified). It takes 8 bytes
+/// instead of 24 of the [`PathBuf`][std::path::PathBuf], which adds up when keeping millions of
+/// paths around.
+///
+/// The equality, ordering and hashing are the same as of [`Path`] (eg. `a/b` and `a//b` are
+/// equal), so it is possible to look the paths up in maps by [`Path`].
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::convert::TryFrom;
+/// use std::path::Path;
+/// use squash::OwnedPath;
+///
+/// let mut sizes: HashMap<OwnedPath, usize> = HashMap::new();
+/// sizes.insert(OwnedPath::try_from(Path::new("/etc/passwd")).unwrap(), 42);
+/// assert_eq!(Some(&42), sizes.get(Path::new("/etc/passwd")));
+/// ```
+pub struct OwnedPath<H: Header = BoxHeader>(OwnedOsStr<H>);
+
+impl<H> OwnedPath<H>
+where
+    H: Header,
+{
+    /// Creates a new owned path.
+    #[inline]
+    pub fn new(path: &Path) -> Result<Self, TooLong> {
+        OwnedOsStr::new(path.as_os_str()).map(Self)
+    }
+
+    /// Returns the underlying platform string.
+    #[inline]
+    pub fn into_os_str(self) -> OwnedOsStr<H> {
+        self.0
+    }
+}
+
+// Not derived, the derive would require H: Clone
+impl<H> Clone for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<H> Debug for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "{:?}", self.deref())
+    }
+}
+
+impl<H> Deref for OwnedPath<H>
+where
+    H: Header,
+{
+    type Target = Path;
+
+    #[inline]
+    fn deref(&self) -> &Path {
+        Path::new(self.0.deref())
+    }
+}
+
+impl<H> AsRef<Path> for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        self.deref()
+    }
+}
+
+impl<H> AsRef<OsStr> for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn as_ref(&self) -> &OsStr {
+        self.0.deref()
+    }
+}
+
+impl<H> Borrow<Path> for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn borrow(&self) -> &Path {
+        self.deref()
+    }
+}
+
+impl<H> TryFrom<&Path> for OwnedPath<H>
+where
+    H: Header,
+{
+    type Error = TooLong;
+
+    #[inline]
+    fn try_from(path: &Path) -> Result<Self, TooLong> {
+        Self::new(path)
+    }
+}
+
+impl<H> TryFrom<&str> for OwnedPath<H>
+where
+    H: Header,
+{
+    type Error = TooLong;
+
+    #[inline]
+    fn try_from(path: &str) -> Result<Self, TooLong> {
+        Self::new(path.as_ref())
+    }
+}
+
+impl<H> From<OwnedOsStr<H>> for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn from(s: OwnedOsStr<H>) -> Self {
+        Self(s)
+    }
+}
+
+impl<H1, H2> PartialEq<OwnedPath<H2>> for OwnedPath<H1>
+where
+    H1: Header,
+    H2: Header,
+{
+    #[inline]
+    fn eq(&self, other: &OwnedPath<H2>) -> bool {
+        self.deref() == other.deref()
+    }
+}
+
+impl<H> PartialEq<Path> for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &Path) -> bool {
+        self.deref() == other
+    }
+}
+
+impl<H> PartialEq<&Path> for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &&Path) -> bool {
+        self.deref() == *other
+    }
+}
+
+impl<H> Eq for OwnedPath<H> where H: Header {}
+
+impl<H1, H2> PartialOrd<OwnedPath<H2>> for OwnedPath<H1>
+where
+    H1: Header,
+    H2: Header,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &OwnedPath<H2>) -> Option<Ordering> {
+        Some(self.deref().cmp(other.deref()))
+    }
+}
+
+impl<H> Ord for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+impl<H> Hash for OwnedPath<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.deref().hash(state)
+    }
+}
+
+#[cfg(all(feature = "std", test))]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::fs::{self, Metadata};
+
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::ArcHeader;
+
+    fn hash<T: Hash + ?Sized>(v: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        v.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn path() {
+        let p: OwnedPath = OwnedPath::try_from("/usr/bin").unwrap();
+        assert_eq!(Path::new("/usr/bin"), p.deref());
+        assert_eq!(p, Path::new("/usr/bin"));
+        assert_eq!(Some(OsStr::new("bin")), p.file_name());
+        assert_eq!("\"/usr/bin\"", format!("{:?}", p));
+        assert_eq!(PathBuf::from("/usr/bin"), PathBuf::from(&p));
+        assert_eq!(p, p.clone());
+    }
+
+    #[test]
+    fn empty() {
+        let p: OwnedPath = OwnedPath::new(Path::new("")).unwrap();
+        assert_eq!(Path::new(""), p.deref());
+        assert!(p.as_os_str().is_empty());
+        assert_eq!(None, p.parent());
+    }
+
+    /// The comparisons are by components, like with Path.
+    #[test]
+    fn consistent_with_path() {
+        let a: OwnedPath = OwnedPath::try_from("a/b").unwrap();
+        let b: OwnedPath<ArcHeader> = OwnedPath::try_from("a//b/").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(Some(Ordering::Equal), a.partial_cmp(&b));
+        assert_eq!(hash(Path::new("a/b")), hash(&a));
+        assert_eq!(hash(Path::new("a//b/")), hash(&a));
+
+        let mut paths = ["b", "a/c", "a/b/c", "a", "a.b"]
+            .iter()
+            .map(|p| OwnedPath::<BoxHeader>::try_from(*p).unwrap())
+            .collect::<Vec<_>>();
+        paths.sort();
+        let mut expected = ["b", "a/c", "a/b/c", "a", "a.b"].map(Path::new);
+        expected.sort();
+        assert!(paths.iter().eq(expected.iter()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let src = Path::new(OsStr::from_bytes(b"/tmp/Hello\xff"));
+        let p: OwnedPath = OwnedPath::new(src).unwrap();
+        assert_eq!(src, p.deref());
+        assert!(p.to_str().is_none());
+        assert_eq!(b"Hello\xff", p.file_name().unwrap().as_bytes());
+    }
+
+    #[test]
+    fn map_of_metadata() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let mut meta: HashMap<OwnedPath, Metadata> = HashMap::new();
+        for entry in fs::read_dir(Path::new(dir).join("src")).unwrap() {
+            let entry = entry.unwrap();
+            let path = OwnedPath::new(&entry.path()).unwrap();
+            // Can be passed to fs functions directly
+            meta.insert(path.clone(), fs::metadata(&path).unwrap());
+        }
+        let lib = Path::new(dir).join("src").join("lib.rs");
+        assert!(meta[lib.as_path()].is_file());
+        assert!(meta[Path::new(dir).join("src/wrapper").as_path()].is_dir());
+    }
+}
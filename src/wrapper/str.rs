@@ -1,24 +1,799 @@
+use alloc::borrow::ToOwned;
 use alloc::fmt::{Debug, Display, Formatter, Result as FmtResult};
-use core::ops::{Deref, DerefMut};
-use core::str;
+use alloc::string::String;
+use core::cmp;
+use core::cmp::Ordering;
+use core::error::Error;
+use core::iter::FromIterator;
+use core::mem;
+use core::ops::{Add, Deref, DerefMut};
+use core::str::{self, FromStr, Utf8Error};
 
-use crate::{BoxHeader, Header, OwnedSlice, TooLong};
+use crate::slice::Builder;
+use crate::{AllocOrTooLong, BoxHeader, DecodeError, Header, OwnedSlice, TooLong};
+
+/// An error when replacing a character by one of different encoded length.
+///
+/// See [`Str::replace_char_in_place`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct SameLenError;
+
+impl Display for SameLenError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "The characters have different lengths in UTF-8")
+    }
+}
+
+impl Error for SameLenError {}
+
+/// Fills the bytes with zeroes when dropped, unless forgotten.
+///
+/// This keeps a [`Str`] valid UTF-8 if its mutation through the bytes is cut short by a panic.
+struct ClearOnUnwind<'a>(&'a mut [u8]);
+
+impl Drop for ClearOnUnwind<'_> {
+    fn drop(&mut self) {
+        self.0.fill(0);
+    }
+}
 
 /// An owned string slice.
 ///
 /// This is the same optimisation as [`OwnedSlice`] does, but applied to `&str`.
-#[derive(Clone, Default)]
-pub struct Str<H: Header = BoxHeader>(OwnedSlice<u8, H>);
+///
+/// It has the same layout guarantees as the [`OwnedSlice`] ‒ it is the size of a single pointer,
+/// including the null pointer niche for `Option<Str>`.
+#[repr(transparent)]
+pub struct Str<H: Header = BoxHeader>(pub(crate) OwnedSlice<u8, H>);
+
+// The layout guarantees, checked at build time.
+const _: () = assert!(core::mem::size_of::<Str>() == core::mem::size_of::<usize>());
+const _: () = assert!(core::mem::size_of::<Option<Str>>() == core::mem::size_of::<usize>());
 
 impl<H> Str<H>
 where
     H: Header,
 {
+    /// The longest string the header is able to encode, in bytes.
+    ///
+    /// See [`Header::MAX_LEN`] for details.
+    pub const MAX_LEN: usize = H::MAX_LEN;
+
     /// Creates a new owned string slice.
     #[inline]
     pub fn new(s: &str) -> Result<Self, TooLong> {
         OwnedSlice::new(s.as_bytes()).map(Self)
     }
+
+    /// Creates a new owned string slice, or an empty one for `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new_or_empty(None).unwrap();
+    /// assert!(s.is_empty());
+    /// ```
+    #[inline]
+    pub fn new_or_empty(s: Option<&str>) -> Result<Self, TooLong> {
+        Self::new(s.unwrap_or(""))
+    }
+
+    /// Creates a new owned string slice, truncating it if it is too long for the header.
+    ///
+    /// The string is cut at the longest length the header can encode, backed off to a char
+    /// boundary. Therefore this never fails, but the truncation happens only for absurdly long
+    /// inputs.
+    pub fn new_lossy(s: &str) -> Self {
+        let mut len = cmp::min(s.len(), H::MAX_LEN);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        Self::new(&s[..len]).expect("MAX_LEN is always encodable")
+    }
+
+    /// Turns the bytes into a string, if they are valid UTF-8.
+    ///
+    /// The bytes are validated and wrapped, without copying or reallocating them. If they are not
+    /// valid UTF-8, they are returned back together with the error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::{OwnedSlice, Str};
+    /// let bytes: OwnedSlice<u8> = OwnedSlice::new(b"Hello").unwrap();
+    /// let s = Str::from_utf8(bytes).unwrap();
+    /// assert_eq!("Hello", &s as &str);
+    ///
+    /// let invalid: OwnedSlice<u8> = OwnedSlice::new(b"\xff").unwrap();
+    /// let (bytes, _) = Str::from_utf8(invalid).unwrap_err();
+    /// assert_eq!(b"\xff", &bytes[..]);
+    /// ```
+    pub fn from_utf8(bytes: OwnedSlice<u8, H>) -> Result<Self, (OwnedSlice<u8, H>, Utf8Error)> {
+        match str::from_utf8(&bytes) {
+            Ok(_) => Ok(Self(bytes)),
+            Err(e) => Err((bytes, e)),
+        }
+    }
+
+    /// Creates a string from bytes, replacing invalid UTF-8 sequences.
+    ///
+    /// This is the equivalent of
+    /// [`String::from_utf8_lossy`][alloc::string::String::from_utf8_lossy] ‒ invalid sequences
+    /// are replaced by [`U+FFFD REPLACEMENT CHARACTER`][char::REPLACEMENT_CHARACTER]. The result
+    /// is written directly into the final allocation.
+    ///
+    /// # Errors
+    ///
+    /// If the resulting string is too long for the header, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::from_utf8_lossy(b"h\x80llo").unwrap();
+    /// assert_eq!("h\u{FFFD}llo", &s as &str);
+    /// ```
+    pub fn from_utf8_lossy(bytes: &[u8]) -> Result<Self, TooLong> {
+        const REPLACEMENT: &str = "\u{FFFD}";
+        let len = bytes
+            .utf8_chunks()
+            .map(|chunk| {
+                let replacement = if chunk.invalid().is_empty() {
+                    0
+                } else {
+                    REPLACEMENT.len()
+                };
+                chunk.valid().len() + replacement
+            })
+            .try_fold(0usize, |total, len| total.checked_add(len))
+            .ok_or_else(TooLong::overflow::<H>)?;
+        let mut builder = Builder::new(len)?;
+        for chunk in bytes.utf8_chunks() {
+            builder.extend_copied(chunk.valid().as_bytes());
+            if !chunk.invalid().is_empty() {
+                builder.extend_copied(REPLACEMENT.as_bytes());
+            }
+        }
+        Ok(Self(builder.finish()))
+    }
+
+    /// Drops the current content and makes the string empty.
+    ///
+    /// See [`OwnedSlice::clear`].
+    #[inline]
+    pub fn clear_to_empty(&mut self) {
+        self.0.clear();
+    }
+
+    /// Copies the string into a [`String`].
+    ///
+    /// This is the same as the [`to_string`][alloc::string::ToString::to_string] provided through
+    /// [`Display`], but without going through the formatting machinery (and easier to find).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("Hello").unwrap();
+    /// let mut owned = s.to_string_owned();
+    /// owned.push_str(" World");
+    /// assert_eq!("Hello World", owned);
+    /// ```
+    #[inline]
+    pub fn to_string_owned(&self) -> String {
+        self.deref().to_owned()
+    }
+
+    /// Returns the length of the string, in bytes.
+    ///
+    /// Like with [`str::len`], this is not the number of characters, see
+    /// [`char_count`][Str::char_count] for that.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the string is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Counts the characters (unicode scalar values) of the string.
+    ///
+    /// This iterates through the whole string.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("café").unwrap();
+    /// assert_eq!(5, s.len());
+    /// assert_eq!(4, s.char_count());
+    /// ```
+    pub fn char_count(&self) -> usize {
+        self.chars().count()
+    }
+
+    /// Returns an iterator over the characters of the string.
+    ///
+    /// See [`str::chars`].
+    #[inline]
+    pub fn chars(&self) -> str::Chars<'_> {
+        self.deref().chars()
+    }
+
+    /// Returns an iterator over the bytes of the string.
+    ///
+    /// See [`str::bytes`].
+    #[inline]
+    pub fn bytes(&self) -> str::Bytes<'_> {
+        self.deref().bytes()
+    }
+
+    /// Returns an iterator over the characters of the string and their byte positions.
+    ///
+    /// See [`str::char_indices`].
+    #[inline]
+    pub fn char_indices(&self) -> str::CharIndices<'_> {
+        self.deref().char_indices()
+    }
+
+    /// Parses the string into another type.
+    ///
+    /// See [`str::parse`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("42").unwrap();
+    /// assert_eq!(Ok(42), s.parse::<u8>());
+    /// ```
+    #[inline]
+    pub fn parse<F>(&self) -> Result<F, F::Err>
+    where
+        F: FromStr,
+    {
+        self.deref().parse()
+    }
+
+    /// Creates a new owned string slice, panicking if it is too long.
+    ///
+    /// This is for the cases where the strings are known to be short and handling the
+    /// [`TooLong`] error would be just noise. For literals, see the [`str_lit`][crate::str_lit]
+    /// macro.
+    ///
+    /// # Panics
+    ///
+    /// If the string is longer than the header can encode.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new_short("Hello");
+    /// assert_eq!("Hello", &s as &str);
+    /// ```
+    pub fn new_short(s: &str) -> Self {
+        Self::new(s)
+            .unwrap_or_else(|_| panic!("String of {} bytes is too long for the header", s.len()))
+    }
+
+    /// Leaks the string if this is the only owner, returning a reference to it that lives as
+    /// long as needed.
+    ///
+    /// See [`OwnedSlice::try_leak`].
+    pub fn try_leak<'a>(this: Self) -> Result<&'a str, Self> {
+        OwnedSlice::try_leak(this.0)
+            // It was created from str originally
+            .map(|s| unsafe { str::from_utf8_unchecked(s) })
+            .map_err(Self)
+    }
+
+    /// Creates a new owned string slice, without aborting on allocation failure.
+    ///
+    /// See [`OwnedSlice::new_no_abort`].
+    #[inline]
+    pub fn new_no_abort(s: &str) -> Result<Self, AllocOrTooLong> {
+        OwnedSlice::new_no_abort(s.as_bytes()).map(Self)
+    }
+
+    /// Creates a new owned string slice by concatenating several strings together.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::concat(&["Hello", " ", "World"]).unwrap();
+    /// assert_eq!("Hello World", &s as &str);
+    /// ```
+    pub fn concat(parts: &[&str]) -> Result<Self, TooLong> {
+        // Every part is valid utf8, so their concatenation is too.
+        let len = parts
+            .iter()
+            .try_fold(0usize, |acc, part| acc.checked_add(part.len()))
+            .ok_or_else(TooLong::overflow::<H>)?;
+        OwnedSlice::from_iter_len(len, parts.iter().flat_map(|part| part.bytes())).map(Self)
+    }
+
+    /// Creates a new, longer string with `s` appended.
+    ///
+    /// Unlike [`String::push_str`], this leaves `self` unchanged and makes a new allocation (the
+    /// length is fixed).
+    ///
+    /// # Errors
+    ///
+    /// If the resulting length is bigger than the header can encode, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("Hello").unwrap();
+    /// assert_eq!("Hello World", &s.push_str(" World").unwrap() as &str);
+    /// ```
+    pub fn push_str(&self, s: &str) -> Result<Self, TooLong> {
+        Self::concat(&[self, s])
+    }
+
+    /// Clones the string, reporting errors instead of panicking.
+    ///
+    /// See [`OwnedSlice::try_clone`] for details.
+    #[inline]
+    pub fn try_clone(&self) -> Result<Self, TooLong> {
+        self.0.try_copy_clone().map(Self)
+    }
+
+    /// Creates a new owned string slice by repeating the passed one `n` times.
+    ///
+    /// This is similar to [`str::repeat`], but without the intermediate
+    /// [`String`][alloc::string::String].
+    ///
+    /// # Errors
+    ///
+    /// If the resulting length is bigger than the header can encode (or doesn't even fit into
+    /// `usize`), [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::repeat_str("ab", 3).unwrap();
+    /// assert_eq!("ababab", &s as &str);
+    /// ```
+    pub fn repeat_str(s: &str, n: usize) -> Result<Self, TooLong> {
+        let len = s.len().checked_mul(n).ok_or_else(TooLong::overflow::<H>)?;
+        let mut builder = Builder::new(len)?;
+        if len > 0 {
+            builder.extend_copied(s.as_bytes());
+            builder.fill_repeated();
+        }
+        Ok(Self(builder.finish()))
+    }
+
+    /// Splits the string by the separator, yielding the pieces as new owned strings.
+    ///
+    /// The lazy variant of [`split_collect`][Str::split_collect], with the same allocation
+    /// behaviour. It is not called `split`, to not shadow [`str::split`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("a,b,c").unwrap();
+    /// let mut pieces = s.split_squashed(",");
+    /// assert_eq!("a", &pieces.next().unwrap() as &str);
+    /// assert_eq!(2, pieces.count());
+    /// ```
+    pub fn split_squashed<'a>(&'a self, sep: &'a str) -> impl Iterator<Item = Self> + 'a {
+        self.deref()
+            .split(sep)
+            .map(move |piece| self.substring(piece).expect("Shorter than the original"))
+    }
+
+    /// Iterates over the lines of the string, as new owned strings.
+    ///
+    /// The lines are split the same way as by [`str::lines`] (the line endings are not included).
+    /// It is not called `lines`, to not shadow [`str::lines`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("first\r\nsecond\n").unwrap();
+    /// let lines: Vec<Str> = s.lines_squashed().collect();
+    /// assert_eq!(2, lines.len());
+    /// assert_eq!("second", &lines[1] as &str);
+    /// ```
+    pub fn lines_squashed(&self) -> impl Iterator<Item = Self> + '_ {
+        self.deref()
+            .lines()
+            .map(|line| Self::new(line).expect("Shorter than the original"))
+    }
+
+    /// Maps each character of the string, writing the result directly into a new allocation.
+    ///
+    /// If every character maps to itself, the string is cloned instead (which shares it with
+    /// sharing headers).
+    fn map_chars<F, I>(&self, f: F) -> Result<Self, TooLong>
+    where
+        F: Fn(char) -> I,
+        I: Iterator<Item = char>,
+    {
+        let mut len = 0usize;
+        let mut unchanged = true;
+        for c in self.chars() {
+            let mut mapped = f(c);
+            unchanged &= mapped.next() == Some(c) && mapped.next().is_none();
+            len = f(c)
+                .map(char::len_utf8)
+                .try_fold(len, usize::checked_add)
+                .ok_or_else(TooLong::overflow::<H>)?;
+        }
+        if unchanged {
+            return self.try_clone();
+        }
+
+        let mut builder = Builder::new(len)?;
+        let mut buf = [0; 4];
+        for c in self.chars().flat_map(f) {
+            builder.extend_copied(c.encode_utf8(&mut buf).as_bytes());
+        }
+        Ok(Self(builder.finish()))
+    }
+
+    /// Returns the lowercase equivalent of the string, as a new owned string.
+    ///
+    /// The same as [`str::to_lowercase`], but without the intermediate
+    /// [`String`][alloc::string::String]. If the string is already lowercase, it is only cloned.
+    ///
+    /// The rare strings containing `Σ` need the context of the whole word, these are delegated to
+    /// [`str::to_lowercase`].
+    ///
+    /// # Errors
+    ///
+    /// If the result is too long for the header, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("Hello WORLD").unwrap();
+    /// assert_eq!("hello world", &s.to_lowercase_squashed().unwrap() as &str);
+    /// ```
+    pub fn to_lowercase_squashed(&self) -> Result<Self, TooLong> {
+        if self.contains('Σ') {
+            return Self::new(&self.deref().to_lowercase());
+        }
+        self.map_chars(char::to_lowercase)
+    }
+
+    /// Returns the uppercase equivalent of the string, as a new owned string.
+    ///
+    /// The same as [`str::to_uppercase`], but without the intermediate
+    /// [`String`][alloc::string::String]. If the string is already uppercase, it is only cloned.
+    ///
+    /// # Errors
+    ///
+    /// If the result is too long for the header, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("Straße").unwrap();
+    /// assert_eq!("STRASSE", &s.to_uppercase_squashed().unwrap() as &str);
+    /// ```
+    pub fn to_uppercase_squashed(&self) -> Result<Self, TooLong> {
+        self.map_chars(char::to_uppercase)
+    }
+
+    /// Changes the bytes of a copy of the string, unless they don't need any change.
+    fn map_ascii<F, C>(&self, needs_change: C, change: F) -> Result<Self, TooLong>
+    where
+        C: Fn(&u8) -> bool,
+        F: FnOnce(&mut [u8]),
+    {
+        if !self.as_bytes().iter().any(needs_change) {
+            return self.try_clone();
+        }
+        let mut copy = OwnedSlice::<u8, H>::copy_from_slice(self.as_bytes())?;
+        change(OwnedSlice::get_mut(&mut copy).expect("A new copy is unique"));
+        Ok(Self(copy))
+    }
+
+    /// Returns a copy of the string with ASCII letters lowercased.
+    ///
+    /// The same as [`str::to_ascii_lowercase`], but without the intermediate
+    /// [`String`][alloc::string::String]. If there's nothing to change, the string is only
+    /// cloned.
+    ///
+    /// # Errors
+    ///
+    /// This doesn't change the length, so it fails only if cloning does (see
+    /// [`try_clone`][Str::try_clone]).
+    pub fn to_ascii_lowercase_squashed(&self) -> Result<Self, TooLong> {
+        self.map_ascii(u8::is_ascii_uppercase, <[u8]>::make_ascii_lowercase)
+    }
+
+    /// Returns a copy of the string with ASCII letters uppercased.
+    ///
+    /// The same as [`str::to_ascii_uppercase`], but without the intermediate
+    /// [`String`][alloc::string::String]. If there's nothing to change, the string is only
+    /// cloned.
+    ///
+    /// # Errors
+    ///
+    /// This doesn't change the length, so it fails only if cloning does (see
+    /// [`try_clone`][Str::try_clone]).
+    pub fn to_ascii_uppercase_squashed(&self) -> Result<Self, TooLong> {
+        self.map_ascii(u8::is_ascii_lowercase, <[u8]>::make_ascii_uppercase)
+    }
+
+    /// Creates a new string from a substring, or clones if it is the whole string.
+    fn substring(&self, sub: &str) -> Result<Self, TooLong> {
+        if sub.len() == self.len() {
+            self.try_clone()
+        } else {
+            OwnedSlice::copy_from_slice(sub.as_bytes()).map(Self)
+        }
+    }
+
+    /// Replaces all the matches of a pattern by another string, producing a new owned string.
+    ///
+    /// This is like [`str::replace`], but the result is written directly into a single
+    /// allocation of the exact size, without the intermediate
+    /// [`String`][alloc::string::String]. If there's no match, the string is only cloned.
+    ///
+    /// It is not called `replace`, to not shadow [`str::replace`].
+    ///
+    /// # Errors
+    ///
+    /// If the result is too long for the header, [`TooLong`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("hello world").unwrap();
+    /// assert_eq!("hell0 w0rld", &s.replaced("o", "0").unwrap() as &str);
+    /// ```
+    pub fn replaced(&self, from: &str, to: &str) -> Result<Self, TooLong> {
+        let matches = self.matches(from).count();
+        if matches == 0 {
+            return self.try_clone();
+        }
+        let len = matches
+            .checked_mul(to.len())
+            .and_then(|added| (self.len() - matches * from.len()).checked_add(added))
+            .ok_or_else(TooLong::overflow::<H>)?;
+
+        let mut builder = Builder::new(len)?;
+        let mut last = 0;
+        for (pos, _) in self.match_indices(from) {
+            builder.extend_copied(&self.as_bytes()[last..pos]);
+            builder.extend_copied(to.as_bytes());
+            last = pos + from.len();
+        }
+        builder.extend_copied(&self.as_bytes()[last..]);
+        Ok(Self(builder.finish()))
+    }
+
+    /// Splits the string by the separator, collecting the pieces as new owned strings.
+    ///
+    /// This is like [`str::split`], but each piece is allocated with its exact size and copied
+    /// in one go. Empty pieces (for example around repeated or trailing separators) don't
+    /// allocate. If the separator is not found, the only piece is a clone of the whole string.
+    ///
+    /// The pieces are never longer than the original, so this can't fail on the length.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let line: Str = Str::new("a,bc,,d").unwrap();
+    /// let fields: Vec<Str> = line.split_collect(",");
+    /// assert_eq!(4, fields.len());
+    /// assert_eq!("bc", &fields[1] as &str);
+    /// ```
+    pub fn split_collect<C>(&self, sep: &str) -> C
+    where
+        C: FromIterator<Self>,
+    {
+        self.split_squashed(sep).collect()
+    }
+
+    /// Returns the string with the leading and trailing whitespace removed, as a new owned
+    /// string.
+    ///
+    /// See [`str::trim`]. If there's nothing to remove, the string is only cloned.
+    ///
+    /// # Errors
+    ///
+    /// This makes the string only shorter, so it fails only if cloning does (see
+    /// [`try_clone`][Str::try_clone]).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("  Hello\n").unwrap();
+    /// assert_eq!("Hello", &s.trimmed().unwrap() as &str);
+    /// ```
+    pub fn trimmed(&self) -> Result<Self, TooLong> {
+        self.substring(self.trim())
+    }
+
+    /// Returns the string with the leading whitespace removed, as a new owned string.
+    ///
+    /// See [`trimmed`][Str::trimmed].
+    pub fn trimmed_start(&self) -> Result<Self, TooLong> {
+        self.substring(self.trim_start())
+    }
+
+    /// Returns the string with the trailing whitespace removed, as a new owned string.
+    ///
+    /// See [`trimmed`][Str::trimmed].
+    pub fn trimmed_end(&self) -> Result<Self, TooLong> {
+        self.substring(self.trim_end())
+    }
+
+    /// Checks that the two strings are an ASCII case-insensitive match.
+    ///
+    /// The same as [`str::eq_ignore_ascii_case`], but checks the lengths first before looking at
+    /// the data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("Hello").unwrap();
+    /// assert!(s.eq_ignore_ascii_case("hELLO"));
+    /// assert!(!s.eq_ignore_ascii_case("hELLO!"));
+    /// ```
+    #[inline]
+    pub fn eq_ignore_ascii_case(&self, other: &str) -> bool {
+        self.len() == other.len() && self.deref().eq_ignore_ascii_case(other)
+    }
+
+    /// Converts the string to use a different header.
+    ///
+    /// See [`OwnedSlice::convert`] for details.
+    pub fn convert<H2>(self) -> Result<Str<H2>, TooLong>
+    where
+        H2: Header,
+    {
+        self.0.convert_or_clone().map(Str)
+    }
+
+    /// Provides mutable access to the string, if it is not shared.
+    ///
+    /// See [`OwnedSlice::get_mut`] for details.
+    pub fn get_mut(this: &mut Self) -> Option<&mut str> {
+        // It was created from str originally
+        OwnedSlice::get_mut(&mut this.0).map(|s| unsafe { str::from_utf8_unchecked_mut(s) })
+    }
+}
+
+// Not derived, the derive would require H: Clone
+impl<H> Clone for Str<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        self.try_clone().expect("Already have layout for this size")
+    }
+}
+
+/// Creates the string, or an empty one for `None`.
+///
+/// This can't fail, too long strings are truncated as in [`Str::new_lossy`].
+impl<H> From<Option<&str>> for Str<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn from(s: Option<&str>) -> Self {
+        Self::new_lossy(s.unwrap_or(""))
+    }
+}
+
+/// Creates an empty string.
+///
+/// The empty string doesn't allocate.
+// Not derived, the derive would require H: Default
+impl<H> Default for Str<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn default() -> Self {
+        Self(OwnedSlice::default())
+    }
+}
+
+/// Compares the strings.
+///
+/// The strings can have different headers, only the content matters. The lengths are compared
+/// first, the data are compared only if they match.
+impl<H1, H2> PartialEq<Str<H2>> for Str<H1>
+where
+    H1: Header,
+    H2: Header,
+{
+    #[inline]
+    fn eq(&self, other: &Str<H2>) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<H> PartialEq<str> for Str<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.deref() == other
+    }
+}
+
+impl<H> PartialEq<&str> for Str<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.deref() == *other
+    }
+}
+
+impl<H> PartialEq<Str<H>> for str
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &Str<H>) -> bool {
+        self == other.deref()
+    }
+}
+
+impl<H> PartialEq<Str<H>> for &str
+where
+    H: Header,
+{
+    #[inline]
+    fn eq(&self, other: &Str<H>) -> bool {
+        *self == other.deref()
+    }
+}
+
+impl<H> Eq for Str<H> where H: Header {}
+
+/// Compares the strings lexicographically, by their bytes.
+///
+/// Like with the equality, the strings can have different headers.
+impl<H1, H2> PartialOrd<Str<H2>> for Str<H1>
+where
+    H1: Header,
+    H2: Header,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &Str<H2>) -> Option<Ordering> {
+        Some(self.deref().cmp(other.deref()))
+    }
+}
+
+impl<H> Ord for Str<H>
+where
+    H: Header,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp(other.deref())
+    }
 }
 
 impl<H> Debug for Str<H>
@@ -41,6 +816,32 @@ where
     }
 }
 
+/// Concatenation with `+`, like with [`String`].
+///
+/// This always makes a new allocation, as the length is fixed.
+///
+/// # Panics
+///
+/// If the result is too long for the header. See [`Str::push_str`] for the fallible version.
+///
+/// # Examples
+///
+/// ```rust
+/// use squash::Str;
+/// let s: Str = Str::new("Hello").unwrap() + " " + "World";
+/// assert_eq!("Hello World", &s as &str);
+/// ```
+impl<H> Add<&str> for Str<H>
+where
+    H: Header,
+{
+    type Output = Self;
+
+    fn add(self, rhs: &str) -> Self {
+        self.push_str(rhs).expect("Str too long for the header")
+    }
+}
+
 impl<H> Deref for Str<H>
 where
     H: Header,
@@ -54,6 +855,138 @@ where
     }
 }
 
+impl Str<BoxHeader> {
+    /// Replaces all occurrences of a character in place.
+    ///
+    /// The length of the string is fixed, so this is possible only if both characters take the
+    /// same number of bytes in UTF-8. The ASCII case conversions
+    /// ([`make_ascii_lowercase`][str::make_ascii_lowercase] and
+    /// [`make_ascii_uppercase`][str::make_ascii_uppercase]) are available through the
+    /// [`DerefMut`] to [`str`][prim@str].
+    ///
+    /// # Errors
+    ///
+    /// If the characters have different lengths, [`SameLenError`] is returned and the string is
+    /// left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let mut s: Str = Str::new("a-b-c").unwrap();
+    /// s.replace_char_in_place('-', '+').unwrap();
+    /// assert_eq!("a+b+c", &s as &str);
+    /// assert!(s.replace_char_in_place('+', '→').is_err());
+    /// ```
+    pub fn replace_char_in_place(&mut self, from: char, to: char) -> Result<(), SameLenError> {
+        if from.len_utf8() != to.len_utf8() {
+            return Err(SameLenError);
+        }
+        let mut buf = [0; 4];
+        let to = to.encode_utf8(&mut buf).as_bytes();
+        let mut start = 0;
+        while let Some(pos) = self[start..].find(from) {
+            let pos = start + pos;
+            self.0[pos..pos + to.len()].copy_from_slice(to);
+            start = pos + to.len();
+        }
+        Ok(())
+    }
+
+    /// Provides mutable access to the bytes of the string, to the passed closure.
+    ///
+    /// The bytes are checked to be valid UTF-8 once the closure returns.
+    ///
+    /// # Panics
+    ///
+    /// If the closure leaves invalid UTF-8 behind or if it panics itself. In both cases, the
+    /// bytes are overwritten by zeroes first, so the string stays valid (but its content is lost).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let mut s: Str = Str::new("hello").unwrap();
+    /// s.with_bytes_mut(|bytes| bytes[0] = b'j');
+    /// assert_eq!("jello", &s as &str);
+    /// ```
+    pub fn with_bytes_mut<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let guard = ClearOnUnwind(&mut self.0);
+        let result = f(&mut *guard.0);
+        assert!(
+            str::from_utf8(guard.0).is_ok(),
+            "Invalid UTF-8 written into Str"
+        );
+        mem::forget(guard);
+        result
+    }
+
+    /// Leaks the string, returning a reference to it that lives as long as needed.
+    ///
+    /// See [`OwnedSlice::leak`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use squash::Str;
+    /// let s: Str = Str::new("Hello").unwrap();
+    /// let leaked: &'static str = Str::leak(s);
+    /// assert_eq!("Hello", leaked);
+    /// ```
+    pub fn leak<'a>(this: Self) -> &'a mut str {
+        // It was created from str originally
+        unsafe { str::from_utf8_unchecked_mut(OwnedSlice::leak(this.0)) }
+    }
+
+    /// Exposes the whole heap allocation as bytes.
+    ///
+    /// See [`OwnedSlice::raw_heap_bytes`].
+    pub fn raw_heap_bytes(&self) -> &[u8] {
+        self.0.raw_heap_bytes()
+    }
+
+    /// Reconstructs the string from bytes previously returned by
+    /// [`raw_heap_bytes`][Str::raw_heap_bytes].
+    ///
+    /// Apart from the checks done by [`OwnedSlice::from_raw_heap_bytes`], the data is validated
+    /// to be UTF-8.
+    ///
+    /// # Errors
+    ///
+    /// If the bytes don't form a valid encoding of a string, [`DecodeError`] is returned.
+    pub fn from_raw_heap_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let slice = OwnedSlice::from_raw_heap_bytes(bytes)?;
+        str::from_utf8(&slice)?;
+        Ok(Self(slice))
+    }
+}
+
+/// Creates a [`Str`] from a string literal, without the need to handle errors.
+///
+/// The length of the literal is checked at compile time to fit inline into the [`BoxHeader`]
+/// (at most [`BoxHeader::MAX_INLINE_LEN`], which is 63 bytes), therefore the construction can't
+/// fail. Longer literals are a compile error; use [`Str::new`] or [`Str::new_short`] for them.
+///
+/// # Examples
+///
+/// ```rust
+/// let s = squash::str_lit!("Hello");
+/// assert_eq!("Hello", &s as &str);
+/// ```
+#[macro_export]
+macro_rules! str_lit {
+    ($s: literal) => {{
+        const _: () = ::core::assert!(
+            $s.len() <= $crate::BoxHeader::MAX_INLINE_LEN,
+            "The literal is too long for str_lit!"
+        );
+        $crate::Str::<$crate::BoxHeader>::new_short($s)
+    }};
+}
+
 impl DerefMut for Str<BoxHeader> {
     #[inline]
     fn deref_mut(&mut self) -> &mut Self::Target {
@@ -64,7 +997,273 @@ impl DerefMut for Str<BoxHeader> {
 
 #[cfg(all(feature = "std", test))]
 mod tests {
+    use std::panic;
+
     use super::*;
+    use crate::header::mock::{RcMockHeader, SaturatedMockHeader, ShortMockHeader};
+    use crate::RcHeader;
+
+    #[test]
+    fn case_mapping() {
+        for input in [
+            "Hello WORLD",
+            "İstanbul",
+            "straße",
+            "ΣΑΣ ΌΔΟΣ",
+            "ǅ",
+            "",
+            "123",
+            "日本",
+        ] {
+            let s: Str = Str::new(input).unwrap();
+            assert_eq!(
+                input.to_lowercase(),
+                s.to_lowercase_squashed().unwrap().deref()
+            );
+            assert_eq!(
+                input.to_uppercase(),
+                s.to_uppercase_squashed().unwrap().deref()
+            );
+            let ascii_lower = s.to_ascii_lowercase_squashed().unwrap();
+            assert_eq!(input.to_ascii_lowercase(), ascii_lower.deref());
+            let ascii_upper = s.to_ascii_uppercase_squashed().unwrap();
+            assert_eq!(input.to_ascii_uppercase(), ascii_upper.deref());
+        }
+        // Multi-byte and multi-char mappings
+        let s: Str = Str::new("İß").unwrap();
+        assert_eq!("i\u{307}ß", s.to_lowercase_squashed().unwrap().deref());
+        assert_eq!("İSS", s.to_uppercase_squashed().unwrap().deref());
+
+        Str::<ShortMockHeader>::new("ßß")
+            .unwrap()
+            .to_uppercase_squashed()
+            .unwrap();
+        Str::<ShortMockHeader>::new("ΐ")
+            .unwrap()
+            .to_uppercase_squashed()
+            .unwrap_err();
+    }
+
+    /// When nothing changes, sharing headers share the data instead of copying.
+    #[test]
+    fn unchanged_shared() {
+        let s: Str<RcHeader> = Str::new("hello").unwrap();
+        for same in [
+            s.to_lowercase_squashed().unwrap(),
+            s.to_ascii_lowercase_squashed().unwrap(),
+            s.trimmed().unwrap(),
+            s.trimmed_start().unwrap(),
+            s.trimmed_end().unwrap(),
+        ] {
+            assert_eq!(s.as_ptr(), same.as_ptr());
+        }
+        let upper = s.to_uppercase_squashed().unwrap();
+        assert_eq!("HELLO", upper.deref());
+        assert_ne!(s.as_ptr(), upper.as_ptr());
+    }
+
+    #[test]
+    fn in_place() {
+        let mut s: Str = Str::new("Hello, Wörld").unwrap();
+        s.make_ascii_uppercase();
+        assert_eq!("HELLO, WöRLD", &s as &str);
+        s.make_ascii_lowercase();
+        assert_eq!("hello, wörld", &s as &str);
+
+        s.replace_char_in_place('l', 'L').unwrap();
+        assert_eq!("heLLo, wörLd", &s as &str);
+        s.replace_char_in_place('ö', 'ř').unwrap();
+        assert_eq!("heLLo, wřrLd", &s as &str);
+        assert_eq!(Err(SameLenError), s.replace_char_in_place('ř', 'r'));
+        assert_eq!(Err(SameLenError), s.replace_char_in_place('o', '€'));
+        assert_eq!("heLLo, wřrLd", &s as &str);
+        s.replace_char_in_place('x', 'y').unwrap();
+
+        let mut empty: Str = Str::new("").unwrap();
+        empty.replace_char_in_place('a', 'b').unwrap();
+        empty.with_bytes_mut(|bytes| assert!(bytes.is_empty()));
+    }
+
+    #[test]
+    fn with_bytes_mut() {
+        let mut s: Str = Str::new("čau").unwrap();
+        let len = s.with_bytes_mut(|bytes| {
+            bytes[2] = b'C';
+            bytes.len()
+        });
+        assert_eq!(4, len);
+        assert_eq!("čCu", &s as &str);
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            // Break the first character in half
+            s.with_bytes_mut(|bytes| bytes[0] = b'c');
+        }));
+        let err = result.unwrap_err();
+        assert_eq!(
+            Some(&"Invalid UTF-8 written into Str"),
+            err.downcast_ref::<&str>()
+        );
+        // Still valid UTF-8, but cleared
+        assert_eq!("\0\0\0\0", &s as &str);
+    }
+
+    /// A panic inside the closure doesn't leave half-written UTF-8 behind.
+    #[test]
+    fn with_bytes_mut_panic() {
+        let mut s: Str = Str::new("čau").unwrap();
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            s.with_bytes_mut(|bytes| {
+                bytes[0] = b'c';
+                panic!("Closure panic");
+            });
+        }));
+        let err = result.unwrap_err();
+        assert_eq!(Some(&"Closure panic"), err.downcast_ref::<&str>());
+        assert_eq!("\0\0\0\0", &s as &str);
+    }
+
+    #[test]
+    fn replaced() {
+        let s: Str = Str::new("hello world").unwrap();
+        for (from, to) in [
+            ("o", "0"),
+            ("l", "LLL"),
+            ("world", ""),
+            ("", "-"),
+            ("x", "y"),
+            ("hello world", ""),
+        ] {
+            assert_eq!(s.replace(from, to), s.replaced(from, to).unwrap().deref());
+        }
+        let empty = s.replaced("hello world", "").unwrap();
+        assert_eq!(Str::<BoxHeader>::new("").unwrap().as_ptr(), empty.as_ptr());
+
+        let shared: Str<RcHeader> = Str::new("hello").unwrap();
+        assert_eq!(shared.as_ptr(), shared.replaced("x", "y").unwrap().as_ptr());
+
+        let short: Str<ShortMockHeader> = Str::new("aa").unwrap();
+        assert_eq!("bbbb", short.replaced("a", "bb").unwrap().deref());
+        short.replaced("a", "bbb").unwrap_err();
+    }
+
+    #[test]
+    fn split_collect() {
+        let s: Str = Str::new("a,bc,,d,").unwrap();
+        let fields: Vec<Str> = s.split_collect(",");
+        let fields: Vec<&str> = fields.iter().map(Deref::deref).collect();
+        assert_eq!(vec!["a", "bc", "", "d", ""], fields);
+        let empty: Str = Str::new("").unwrap();
+        let pieces: Vec<Str> = s.split_collect(",");
+        assert_eq!(empty.as_ptr(), pieces[2].as_ptr());
+        assert_eq!(empty.as_ptr(), pieces[4].as_ptr());
+
+        let fields: Vec<Str> = s.split_collect(",,");
+        assert_eq!("a,bc", fields[0].deref());
+        assert_eq!("d,", fields[1].deref());
+
+        let pieces: Vec<Str> = empty.split_collect(",");
+        assert_eq!(1, pieces.len());
+        assert!(pieces[0].is_empty());
+
+        let s: Str<RcHeader> = Str::new("no separator").unwrap();
+        let fields: Vec<Str<RcHeader>> = s.split_collect(",");
+        assert_eq!(s.as_ptr(), fields[0].as_ptr());
+    }
+
+    #[test]
+    fn trim() {
+        let s: Str = Str::new("  Hello world \t\n").unwrap();
+        assert_eq!("Hello world", s.trimmed().unwrap().deref());
+        assert_eq!("Hello world \t\n", s.trimmed_start().unwrap().deref());
+        assert_eq!("  Hello world", s.trimmed_end().unwrap().deref());
+
+        let blank: Str = Str::new(" \u{3000} ").unwrap();
+        assert_eq!(
+            Str::<BoxHeader>::new("").unwrap().as_ptr(),
+            blank.trimmed().unwrap().as_ptr()
+        );
+        assert!(blank.trimmed_start().unwrap().is_empty());
+        assert!(blank.trimmed_end().unwrap().is_empty());
+    }
+
+    #[test]
+    fn from_utf8_lossy() {
+        let s: Str = Str::from_utf8_lossy(&[0x68, 0x80, 0x6c, 0x6c, 0x6f]).unwrap();
+        assert_eq!("h\u{FFFD}llo", s.deref());
+
+        for input in [
+            &b"Hello"[..],
+            "Příliš".as_bytes(),
+            b"",
+            b"\xff\xfe",
+            b"ab\xc4",
+            b"\xf0\x9f\x98",
+        ] {
+            let s: Str<RcHeader> = Str::from_utf8_lossy(input).unwrap();
+            assert_eq!(String::from_utf8_lossy(input), s.deref());
+        }
+
+        Str::<ShortMockHeader>::from_utf8_lossy(b"abcd").unwrap();
+        Str::<ShortMockHeader>::from_utf8_lossy(b"ab\xff").unwrap_err();
+    }
+
+    #[test]
+    fn from_utf8() {
+        let bytes = OwnedSlice::<u8, RcHeader>::new("Příliš".as_bytes()).unwrap();
+        let ptr = bytes.as_ptr();
+        let s = Str::from_utf8(bytes).unwrap();
+        assert_eq!("Příliš", s.deref());
+        // No copy
+        assert_eq!(ptr, s.as_ptr());
+
+        let bytes = OwnedSlice::<u8>::new(b"abc\xc4").unwrap();
+        let ptr = bytes.as_ptr();
+        let (bytes, e) = Str::from_utf8(bytes).unwrap_err();
+        assert_eq!(b"abc\xc4", &bytes[..]);
+        assert_eq!(ptr, bytes.as_ptr());
+        assert_eq!(3, e.valid_up_to());
+
+        let empty = Str::from_utf8(OwnedSlice::<u8>::default()).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn split_squashed() {
+        let s: Str = Str::new("a,b,c").unwrap();
+        let mut pieces = s.split_squashed(",");
+        assert_eq!("a", pieces.next().unwrap().deref());
+        assert_eq!("b", pieces.next().unwrap().deref());
+        assert_eq!("c", pieces.next().unwrap().deref());
+        assert!(pieces.next().is_none());
+
+        let empty: Str = Str::new("").unwrap();
+        let pieces = empty.split_squashed(",").collect::<Vec<_>>();
+        assert_eq!(1, pieces.len());
+        assert!(pieces[0].is_empty());
+    }
+
+    #[test]
+    fn lines_squashed() {
+        let s: Str<RcHeader> = Str::new("one\ntwo\r\n\nfour\n").unwrap();
+        let lines = s.lines_squashed().collect::<Vec<_>>();
+        let lines = lines.iter().map(|l| l.deref()).collect::<Vec<_>>();
+        assert_eq!(vec!["one", "two", "", "four"], lines);
+
+        let empty: Str = Str::new("").unwrap();
+        assert_eq!(0, empty.lines_squashed().count());
+    }
+
+    #[test]
+    fn to_string_owned() {
+        let s: Str<RcHeader> = Str::new("čau").unwrap();
+        let owned = s.to_string_owned();
+        assert_eq!("čau", owned);
+        assert_eq!(s.len(), owned.len());
+        assert!(Str::<BoxHeader>::new("")
+            .unwrap()
+            .to_string_owned()
+            .is_empty());
+    }
 
     #[test]
     fn strings() {
@@ -73,4 +1272,287 @@ mod tests {
         assert_eq!("Hello", s.to_string());
         assert_eq!("\"Hello\"", format!("{:?}", s));
     }
+
+    #[test]
+    fn len() {
+        let s: Str = Str::new("hello").unwrap();
+        assert_eq!(5, s.len());
+        assert_eq!(5, s.char_count());
+        assert!(!s.is_empty());
+        let s: Str<RcHeader> = Str::new("café").unwrap();
+        assert_eq!(5, s.len());
+        assert_eq!(4, s.char_count());
+        let s: Str = Str::default();
+        assert_eq!(0, s.len());
+        assert!(s.is_empty());
+    }
+
+    fn iterators<H: Header>(s: &Str<H>) -> (String, Vec<u8>, Vec<(usize, char)>) {
+        (
+            s.chars().collect(),
+            s.bytes().collect(),
+            s.char_indices().collect(),
+        )
+    }
+
+    #[test]
+    fn iter() {
+        let s: Str<RcHeader> = Str::new("čau").unwrap();
+        let (chars, bytes, indices) = iterators(&s);
+        assert_eq!("čau", chars);
+        assert_eq!("čau".as_bytes(), &bytes[..]);
+        assert_eq!(vec![(0, 'č'), (2, 'a'), (3, 'u')], indices);
+
+        let (chars, bytes, indices) = iterators(&Str::<BoxHeader>::default());
+        assert!(chars.is_empty() && bytes.is_empty() && indices.is_empty());
+    }
+
+    #[test]
+    fn parse() {
+        use std::net::IpAddr;
+
+        fn check<F: FromStr + PartialEq + Debug>(s: &str)
+        where
+            F::Err: PartialEq + Debug,
+        {
+            let squashed: Str<RcHeader> = Str::new(s).unwrap();
+            assert_eq!(s.parse::<F>(), squashed.parse::<F>());
+        }
+
+        check::<i64>("-42");
+        check::<i64>("nope");
+        check::<f64>("3.25");
+        check::<bool>("true");
+        check::<bool>("");
+        check::<IpAddr>("::1");
+        check::<IpAddr>("192.168.0.1");
+        check::<IpAddr>("192.168.0.256");
+    }
+
+    #[test]
+    fn leak() {
+        let leaked = Str::leak(Str::new_short(""));
+        assert_eq!("", leaked);
+
+        let s: Str<RcHeader> = Str::new("Hello").unwrap();
+        // Keep the pointer to reclaim the memory at the end, so miri doesn't report the leak.
+        let header = OwnedSlice::into_raw(s.0);
+        let s = Str(unsafe { OwnedSlice::from_raw(header) });
+        let s2 = s.clone();
+        let s = Str::try_leak(s).unwrap_err();
+        drop(s2);
+        let leaked: &'static str = Str::try_leak(s).unwrap();
+        assert_eq!("Hello", leaked);
+        drop(Str(unsafe { OwnedSlice::<u8, RcHeader>::from_raw(header) }));
+    }
+
+    #[test]
+    fn default() {
+        let s: Str = Str::default();
+        assert_eq!("", s.deref());
+        assert_eq!(0, s.len());
+        assert_eq!(0, s.0.heap_size());
+        let s: Str<RcHeader> = Str::default();
+        assert_eq!(s, *"");
+        assert_eq!(0, s.0.heap_size());
+    }
+
+    #[test]
+    fn new_short() {
+        let s = str_lit!("Hello");
+        assert_eq!("Hello", s.deref());
+        let s = str_lit!("");
+        assert_eq!(0, s.0.heap_size());
+        let long = "x".repeat(BoxHeader::MAX_INLINE_LEN + 1);
+        let s: Str<RcHeader> = Str::new_short(&long);
+        assert_eq!(long, s.deref());
+    }
+
+    #[test]
+    #[should_panic(expected = "too long for the header")]
+    fn new_short_too_long() {
+        use crate::header::mock::ShortMockHeader;
+        let _: Str<ShortMockHeader> = Str::new_short("Hello");
+    }
+
+    #[test]
+    fn get_mut_shared() {
+        let mut s: Str<RcMockHeader> = Str::new("hello").unwrap();
+        Str::get_mut(&mut s).unwrap().make_ascii_uppercase();
+        let mut s2 = s.clone();
+        assert!(Str::get_mut(&mut s2).is_none());
+        drop(s2);
+        assert_eq!("HELLO", Str::get_mut(&mut s).unwrap());
+    }
+
+    #[test]
+    fn concat() {
+        let parts = ["Hello", ", ", "", "World"];
+        let s: Str = Str::concat(&parts).unwrap();
+        assert_eq!(parts.concat(), s.deref());
+
+        let s: Str = Str::concat(&["Hello"]).unwrap();
+        assert_eq!("Hello", s.deref());
+
+        let s: Str = Str::concat(&[]).unwrap();
+        assert_eq!("", s.deref());
+    }
+
+    #[test]
+    fn new_or_empty() {
+        let s: Str = Str::new_or_empty(Some("Hello")).unwrap();
+        assert_eq!("Hello", s.deref());
+        let s: Str = Str::new_or_empty(None).unwrap();
+        assert!(s.is_empty());
+        Str::<ShortMockHeader>::new_or_empty(Some("Hello")).unwrap_err();
+
+        let s: Str = Some("Hello").into();
+        assert_eq!("Hello", s.deref());
+        let s: Str = None.into();
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn new_lossy() {
+        let s: Str = Str::new_lossy("Hello");
+        assert_eq!("Hello", s.deref());
+        let s: Str<ShortMockHeader> = Str::new_lossy("Hello");
+        assert_eq!("Hell", s.deref());
+        // Cut on the char boundary, not in the middle of the č.
+        let s: Str<ShortMockHeader> = Str::new_lossy("abcčd");
+        assert_eq!("abc", s.deref());
+        let s: Str<ShortMockHeader> = Some("Hello").into();
+        assert_eq!("Hell", s.deref());
+    }
+
+    #[test]
+    fn clear_to_empty() {
+        let mut s: Str<RcHeader> = Str::new("Hello").unwrap();
+        let s2 = s.clone();
+        s.clear_to_empty();
+        assert!(s.is_empty());
+        assert_eq!("", s.deref());
+        assert_eq!("Hello", s2.deref());
+    }
+
+    #[test]
+    fn push_str() {
+        let s: Str = Str::new("Hello").unwrap();
+        assert_eq!("Hello, World", s.push_str(", World").unwrap().deref());
+        assert_eq!("Hello", s.push_str("").unwrap().deref());
+        let empty: Str = Str::new("").unwrap();
+        assert_eq!("Hello", empty.push_str("Hello").unwrap().deref());
+    }
+
+    #[test]
+    fn add() {
+        let s: Str = Str::new("hello").unwrap() + " " + "world";
+        assert_eq!(Str::<BoxHeader>::new("hello world").unwrap(), s);
+        let s: Str = Str::new("").unwrap() + "hello";
+        assert_eq!("hello", s.deref());
+        let s: Str = Str::new("hello").unwrap() + "";
+        assert_eq!("hello", s.deref());
+        let s: Str = Str::new("").unwrap() + "";
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Str too long for the header")]
+    fn add_too_long() {
+        let short: Str<ShortMockHeader> = Str::new("abc").unwrap();
+        let _ = short + "de";
+    }
+
+    #[test]
+    fn convert() {
+        let s: Str = Str::new("Hello").unwrap();
+        let s: Str<RcMockHeader> = s.convert().unwrap();
+        let s2 = s.clone();
+        let s: Str = s.convert().unwrap();
+        assert_eq!("Hello", s.deref());
+        assert_eq!("Hello", s2.deref());
+
+        let empty: Str<RcMockHeader> = Str::<BoxHeader>::new("").unwrap().convert().unwrap();
+        assert_eq!("", empty.deref());
+    }
+
+    #[test]
+    fn try_clone() {
+        let s: Str<SaturatedMockHeader> = Str::new("Hello").unwrap();
+        let s2 = s.try_clone().unwrap();
+        assert_eq!(s.deref(), s2.deref());
+    }
+
+    #[test]
+    fn max_len() {
+        assert_eq!(BoxHeader::MAX_LEN, Str::<BoxHeader>::MAX_LEN);
+        assert_eq!(4, Str::<ShortMockHeader>::MAX_LEN);
+        Str::<ShortMockHeader>::new("abcd").unwrap();
+        Str::<ShortMockHeader>::new("abcde").unwrap_err();
+    }
+
+    #[test]
+    fn repeat() {
+        for n in [0, 1, 2, 3, 7, 31, 32, 33, 100] {
+            let s: Str = Str::repeat_str("ab", n).unwrap();
+            assert_eq!("ab".repeat(n), s.deref());
+        }
+        let s: Str = Str::repeat_str("", 10).unwrap();
+        assert_eq!("", s.deref());
+        assert!(Str::<BoxHeader>::repeat_str("ab", usize::MAX).is_err());
+    }
+
+    #[test]
+    fn raw_heap_bytes() {
+        let s: Str = Str::new("hello").unwrap();
+        let s2 = Str::from_raw_heap_bytes(s.raw_heap_bytes()).unwrap();
+        assert_eq!("hello", s2.deref());
+
+        let e = Str::from_raw_heap_bytes(b"\x02\xff\xff").unwrap_err();
+        assert!(matches!(e, DecodeError::Utf8(_)));
+    }
+
+    #[test]
+    fn eq() {
+        for len in [0, 1, 63, 64, 65] {
+            let src = "x".repeat(len);
+            let a: Str = Str::new(&src).unwrap();
+            let b: Str = Str::new(&src).unwrap();
+            assert_eq!(a, b);
+            let longer: Str = Str::new(&(src.clone() + "x")).unwrap();
+            assert_ne!(a, longer);
+            assert!(a.eq_ignore_ascii_case(&src.to_uppercase()));
+            assert!(!a.eq_ignore_ascii_case(&(src + "X")));
+        }
+    }
+
+    #[test]
+    fn eq_cross_header() {
+        let boxed: Str = Str::new("hello").unwrap();
+        let rc: Str<RcHeader> = Str::new("hello").unwrap();
+        assert_eq!(boxed, rc);
+        assert_eq!(rc, boxed);
+        assert!(rc == *"hello");
+        let other: Str<RcHeader> = Str::new("world").unwrap();
+        assert_ne!(boxed, other);
+        assert!(other != *"hello");
+        assert!(rc == "hello");
+        assert!("hello" == rc);
+        assert!(*"hello" == rc);
+        assert!("world" != boxed);
+    }
+
+    #[test]
+    fn ord() {
+        let boxed: Str = Str::new("hello").unwrap();
+        let rc: Str<RcHeader> = Str::new("help").unwrap();
+        assert!(boxed < rc);
+        assert!(rc > boxed);
+        let mut sorted: Vec<Str> = ["b", "", "ab", "a"]
+            .iter()
+            .map(|s| Str::new(s).unwrap())
+            .collect();
+        sorted.sort();
+        assert_eq!(["", "a", "ab", "b"], sorted.as_slice());
+    }
 }
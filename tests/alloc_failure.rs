@@ -0,0 +1,140 @@
+//! The fallible constructors report allocation failures and clean up after themselves.
+//!
+//! This uses its own global allocator that can fail on request. Only the allocations made by the
+//! thread that armed it are counted and failed, so the test harness doesn't interfere.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::panic;
+use std::ptr;
+
+use squash::{AllocOrTooLong, OwnedSlice, Str};
+
+thread_local! {
+    /// Fail the allocation once this many more succeed (if armed).
+    static FAIL_AFTER: Cell<Option<usize>> = const { Cell::new(None) };
+    /// Number of allocations alive, made by this thread.
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+}
+
+struct FailingAlloc;
+
+unsafe impl GlobalAlloc for FailingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let fail = FAIL_AFTER.with(|f| match f.get() {
+            Some(0) => true,
+            Some(n) => {
+                f.set(Some(n - 1));
+                false
+            }
+            None => false,
+        });
+        if fail {
+            return ptr::null_mut();
+        }
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            LIVE.with(|l| l.set(l.get() + 1));
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.with(|l| l.set(l.get() - 1));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: FailingAlloc = FailingAlloc;
+
+/// Runs the closure with the n-th allocation failing, checking nothing leaked.
+#[track_caller]
+fn failing_at<R>(n: usize, f: impl FnOnce() -> R) -> R {
+    let live = LIVE.with(Cell::get);
+    FAIL_AFTER.with(|f| f.set(Some(n)));
+    let result = f();
+    FAIL_AFTER.with(|f| f.set(None));
+    assert_eq!(live, LIVE.with(Cell::get), "Leaked allocation");
+    result
+}
+
+/// Holds a heap allocation and panics when cloning the one with 1.
+#[derive(Debug)]
+struct PanicClone(Box<u32>);
+
+impl Clone for PanicClone {
+    fn clone(&self) -> Self {
+        assert_ne!(*self.0, 1, "Panicking for the good measure of it");
+        PanicClone(self.0.clone())
+    }
+}
+
+#[test]
+fn new_no_abort_reports_failure() {
+    let src = vec!["Hello".to_owned(), "World".to_owned()];
+
+    match failing_at(0, || OwnedSlice::<String>::new_no_abort(&src)) {
+        Err(AllocOrTooLong::Alloc(_)) => (),
+        other => panic!("Unexpected result {:?}", other),
+    }
+    match failing_at(0, || Str::<squash::ArcHeader>::new_no_abort("Hello")) {
+        Err(AllocOrTooLong::Alloc(_)) => (),
+        other => panic!("Unexpected result {:?}", other),
+    }
+
+    // Too long ones are rejected before allocating anything.
+    let long = vec![0u8; 256];
+    match failing_at(0, || {
+        OwnedSlice::<u8, squash::ByteHeader>::new_no_abort(&long)
+    }) {
+        Err(AllocOrTooLong::TooLong(e)) => {
+            assert_eq!(256, e.len);
+            assert_eq!(255, e.max);
+        }
+        other => panic!("Unexpected result {:?}", other),
+    }
+
+    // Empty ones don't allocate at all.
+    failing_at(0, || OwnedSlice::<String>::new_no_abort(&[]).unwrap());
+
+    // A panicking clone in the middle frees the already cloned elements and the slice itself.
+    // Silence the panic hook and panic once up front. The panic machinery lazily makes some
+    // allocations of its own and these would look like leaks.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| ()));
+    panic::catch_unwind(|| panic!("Warming up")).unwrap_err();
+    let panicky = vec![PanicClone(Box::new(0)), PanicClone(Box::new(1))];
+    failing_at(usize::MAX, || {
+        let result = panic::catch_unwind(|| drop(OwnedSlice::<PanicClone>::new_no_abort(&panicky)));
+        assert!(result.is_err());
+    });
+    panic::set_hook(hook);
+
+    failing_at(usize::MAX, || {
+        let s = OwnedSlice::<String>::new_no_abort(&src).unwrap();
+        assert_eq!(&src[..], &s[..]);
+    });
+}
+
+#[test]
+fn from_fn_no_abort_reports_failure() {
+    // The closure allocates too, but it is not called at all if the slice can't be allocated.
+    match failing_at(0, || {
+        OwnedSlice::<String>::from_fn_no_abort(2, |i| i.to_string())
+    }) {
+        Err(AllocOrTooLong::Alloc(_)) => (),
+        other => panic!("Unexpected result {:?}", other),
+    }
+    match failing_at(0, || OwnedSlice::<u32>::from_iter_exact_no_abort(0..3)) {
+        Err(AllocOrTooLong::Alloc(_)) => (),
+        other => panic!("Unexpected result {:?}", other),
+    }
+
+    failing_at(usize::MAX, || {
+        let s = OwnedSlice::<String>::from_fn_no_abort(2, |i| i.to_string()).unwrap();
+        assert_eq!(&["0", "1"], &s[..]);
+        let s = OwnedSlice::<u32>::from_iter_exact_no_abort(0..3).unwrap();
+        assert_eq!(&[0, 1, 2], &s[..]);
+    });
+}
@@ -0,0 +1,15 @@
+//! Checks that misuses are rejected at compile time.
+//!
+//! That uniquely owned slices of non-`Clone` elements are not `Clone`, that the auto trait bounds
+//! don't let non-thread-safe data escape to other threads and that the compile-time checks of the
+//! macros work.
+//!
+//! The expected compiler messages differ between compiler versions, so this runs only on stable.
+
+#[rustversion::attr(not(stable), ignore)]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,179 @@
+//! The allocation counters.
+//!
+//! The counters are global, so the tests are serialized by a lock (see [`start`]) to not interfere
+//! with each other when running in parallel.
+#![cfg(all(feature = "stats", target_has_atomic = "ptr"))]
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use squash::stats::{self, Stats};
+use squash::{OwnedSlice, RcHeader, SliceBuf, Str};
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Takes the lock for the duration of the test and starts with clean counters.
+fn start() -> MutexGuard<'static, ()> {
+    // A failed test doesn't leave anything broken behind, the counters get reset anyway.
+    let guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    stats::reset();
+    guard
+}
+
+#[test]
+fn counters() {
+    let _lock = start();
+    assert_eq!(Stats::default(), stats::snapshot());
+
+    let hello: Str = Str::new("Hello").unwrap();
+    let empty: Str = Str::new("").unwrap();
+    let long: OwnedSlice<u32> = OwnedSlice::new(&[42; 100]).unwrap();
+    let s = stats::snapshot();
+    assert_eq!(2, s.allocations);
+    assert_eq!(0, s.deallocations);
+    // 1 byte of header + 5 bytes; 4 bytes of header (with padding) + 400 bytes
+    assert_eq!(6 + 404, s.allocated_bytes);
+    assert_eq!(6 + 404, s.live_bytes);
+    assert_eq!(1, s.sentinel_constructions);
+
+    drop(hello);
+    drop(empty);
+    let s = stats::snapshot();
+    assert_eq!(1, s.deallocations);
+    assert_eq!(404, s.live_bytes);
+    assert_eq!(6 + 404, s.allocated_bytes);
+
+    // Sharing doesn't allocate
+    let shared: OwnedSlice<u8, RcHeader> = OwnedSlice::new(b"abc").unwrap();
+    let shared2 = shared.clone();
+    assert_eq!(3, stats::snapshot().allocations);
+    drop(shared);
+    assert_eq!(1, stats::snapshot().deallocations);
+    drop(shared2);
+    drop(long);
+    let s = stats::snapshot();
+    assert_eq!(3, s.allocations);
+    assert_eq!(3, s.deallocations);
+    assert_eq!(0, s.live_bytes);
+}
+
+#[test]
+fn many_strings() {
+    let _lock = start();
+    let strings = (0..1000)
+        .map(|i| Str::<squash::BoxHeader>::new(&i.to_string()).unwrap())
+        .collect::<Vec<_>>();
+    let s = stats::snapshot();
+    assert_eq!(1000, s.allocations);
+    // 10 single digit numbers, 90 with two, 900 with three digits, each with a byte of header.
+    assert_eq!(10 * 2 + 90 * 3 + 900 * 4, s.live_bytes);
+    drop(strings);
+    assert_eq!(0, stats::snapshot().live_bytes);
+}
+
+/// Case mapping writes directly into a single allocation, and shares when nothing changes.
+#[test]
+fn case_mapping() {
+    let _lock = start();
+    let hello: Str<RcHeader> = Str::new("  Straße ").unwrap();
+    stats::reset();
+    let upper = hello.to_uppercase_squashed().unwrap();
+    assert_eq!(1, stats::snapshot().allocations);
+    let trimmed = upper.trimmed().unwrap();
+    assert_eq!(2, stats::snapshot().allocations);
+    let same = trimmed.to_ascii_uppercase_squashed().unwrap();
+    let same2 = trimmed.trimmed().unwrap();
+    assert_eq!(2, stats::snapshot().allocations);
+    assert_eq!("STRASSE", &same as &str);
+    drop((hello, upper, trimmed, same, same2));
+}
+
+/// Splitting allocates once for each non-empty piece.
+#[test]
+fn split() {
+    let _lock = start();
+    let line: Str = Str::new("a,bc,,d,").unwrap();
+    stats::reset();
+    let fields: Vec<Str> = line.split_collect(",");
+    let s = stats::snapshot();
+    // Plus the Vec, which is not counted.
+    assert_eq!(3, s.allocations);
+    assert_eq!(2 + 3 + 2, s.allocated_bytes);
+    assert_eq!(2, s.sentinel_constructions);
+    drop(fields);
+}
+
+/// The buffer grows by reallocating and finishing shrinks in place, so it is a single
+/// allocation, like copying from a Vec (which is not counted).
+#[test]
+fn buf() {
+    let _lock = start();
+    let v: Vec<u32> = (0..1000).collect();
+    stats::reset();
+    let copied = OwnedSlice::<u32>::new(&v).unwrap();
+    let mut buf = SliceBuf::<u32>::new();
+    for i in 0..1000 {
+        buf.push(i);
+    }
+    let built = buf.finish().unwrap();
+    let s = stats::snapshot();
+    assert_eq!(copied, built);
+    assert_eq!(2, s.allocations);
+    assert_eq!(0, s.deallocations);
+    assert_eq!(2 * 4004, s.live_bytes);
+    drop((copied, built));
+
+    stats::reset();
+    let empty = SliceBuf::<u32>::new().finish().unwrap();
+    let s = stats::snapshot();
+    assert_eq!(0, s.allocations);
+    assert_eq!(1, s.sentinel_constructions);
+    drop(empty);
+}
+
+/// Iterators knowing their length are written into a single exact allocation.
+#[test]
+fn from_iter_exact() {
+    let _lock = start();
+    let squares =
+        OwnedSlice::<u64>::from_iter_exact((0..100u32).map(|i| u64::from(i * i))).unwrap();
+    let s = stats::snapshot();
+    assert_eq!(1, s.allocations);
+    // 1 byte of header + 1 extra, padded to 8
+    assert_eq!(8 + 800, s.allocated_bytes);
+    assert_eq!(99 * 99, squares[99]);
+    drop(squares);
+}
+
+/// Failed reads free the allocation made up front.
+#[test]
+fn copy_from_reader() {
+    let _lock = start();
+    let mut input: &[u8] = b"short";
+    assert!(OwnedSlice::<u8>::copy_from_reader(10, &mut input).is_err());
+    let read = OwnedSlice::<u8>::copy_from_reader(0, &mut input).unwrap();
+    let s = stats::snapshot();
+    assert_eq!(1, s.allocations);
+    assert_eq!(1, s.deallocations);
+    assert_eq!(0, s.live_bytes);
+    assert_eq!(1, s.sentinel_constructions);
+    drop(read);
+}
+
+/// Only new keys get allocated in the set, the lookups work with the borrowed bytes.
+#[cfg(feature = "hashbrown")]
+#[test]
+fn set() {
+    let _lock = start();
+    let mut set = squash::collections::SquashSet::<squash::BoxHeader>::new();
+    assert!(set.insert(b"hello").unwrap());
+    assert!(set.insert(b"world").unwrap());
+    assert_eq!(2, stats::snapshot().allocations);
+    assert!(!set.insert(b"hello").unwrap());
+    assert!(set.contains(b"world"));
+    assert!(set.get(b"nope").is_none());
+    let s = stats::snapshot();
+    assert_eq!(2, s.allocations);
+    assert_eq!(0, s.deallocations);
+    drop(set);
+    assert_eq!(0, stats::snapshot().live_bytes);
+}
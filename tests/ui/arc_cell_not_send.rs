@@ -0,0 +1,9 @@
+use std::cell::Cell;
+
+use squash::{ArcHeader, OwnedSlice};
+
+fn assert_send<T: Send>() {}
+
+fn main() {
+    assert_send::<OwnedSlice<Cell<u8>, ArcHeader>>();
+}
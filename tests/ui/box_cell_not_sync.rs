@@ -0,0 +1,9 @@
+use std::cell::Cell;
+
+use squash::OwnedSlice;
+
+fn assert_sync<T: Sync>() {}
+
+fn main() {
+    assert_sync::<OwnedSlice<Cell<u8>>>();
+}
@@ -0,0 +1,10 @@
+use squash::OwnedSlice;
+
+struct NoClone;
+
+fn assert_clone<T: Clone>() {}
+
+fn main() {
+    // Uniquely owned slices need to clone the elements.
+    assert_clone::<OwnedSlice<NoClone>>();
+}
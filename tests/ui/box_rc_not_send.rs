@@ -0,0 +1,9 @@
+use std::rc::Rc;
+
+use squash::OwnedSlice;
+
+fn assert_send<T: Send>() {}
+
+fn main() {
+    assert_send::<OwnedSlice<Rc<u8>>>();
+}
@@ -0,0 +1,9 @@
+use std::rc::Rc;
+
+use squash::OwnedSlice;
+
+fn assert_sync<T: Sync>() {}
+
+fn main() {
+    assert_sync::<OwnedSlice<Rc<u8>>>();
+}
@@ -0,0 +1,3 @@
+fn main() {
+    let _ = squash::str_lit!("This literal is longer than what fits inline into the box header.");
+}